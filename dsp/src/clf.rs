@@ -0,0 +1,467 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{
+    HlgPqMapper,
+    Mapper,
+    PqHlgMapper,
+    lut::Lut3d,
+    pixel::{RgbPixel, XyzPixel},
+    tf::{hlg_ioetf, hlg_iootf, hlg_oetf, hlg_ootf, pq_eotf, pq_ieotf},
+    tm::ToneMapMethod,
+};
+use std::io::{Result, Write};
+
+const CURVE_SIZE: usize = 4_096;
+const HALF_DOMAIN_SIZE: usize = 65_536;
+
+//
+// Process List
+//
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessList {
+    pub id: String,
+    pub name: String,
+    pub description: Vec<String>,
+    pub input_descriptor: String,
+    pub output_descriptor: String,
+    pub generator: Option<String>,
+    pub parameters: Vec<(String, String)>,
+    pub nodes: Vec<ProcessNode>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessNode {
+    Matrix {
+        id: String,
+        description: String,
+        matrix: [[f64; 3]; 3],
+    },
+    Lut1d {
+        id: String,
+        description: String,
+        half_domain: bool,
+        table: Vec<f64>,
+    },
+    Range {
+        id: String,
+        description: String,
+        min_in: f64,
+        max_in: f64,
+        min_out: f64,
+        max_out: f64,
+    },
+    Lut3d {
+        id: String,
+        description: String,
+        lut: Lut3d,
+    },
+}
+
+impl ProcessList {
+
+    pub fn write_clf(&self, output: &mut dyn Write) -> Result<()> {
+
+        writeln!(output, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(output, "<ProcessList id=\"{}\" name=\"{}\" compCLFversion=\"3.0\">",
+            escape(&self.id),
+            escape(&self.name),
+        )?;
+        for description in self.description.iter() {
+            writeln!(output, "    <Description>{}</Description>", escape(description))?;
+        }
+        writeln!(output, "    <InputDescriptor>{}</InputDescriptor>",
+            escape(&self.input_descriptor))?;
+        writeln!(output, "    <OutputDescriptor>{}</OutputDescriptor>",
+            escape(&self.output_descriptor))?;
+        writeln!(output, "    <Info>")?;
+        if let Some(generator) = self.generator.as_ref() {
+            writeln!(output, "        <Generator>{}</Generator>", escape(generator))?;
+        }
+        for (name, value) in self.parameters.iter() {
+            writeln!(output, "        <Parameter name=\"{}\">{}</Parameter>",
+                escape(name),
+                escape(value),
+            )?;
+        }
+        writeln!(output, "    </Info>")?;
+
+        for node in self.nodes.iter() {
+            node.write_clf(output)?;
+        }
+
+        writeln!(output, "</ProcessList>")
+    }
+}
+
+impl Mapper for ProcessList {
+
+    fn map(&self, input: RgbPixel) -> RgbPixel {
+        self.nodes.iter().fold(input, |pixel, node| node.map(pixel))
+    }
+}
+
+impl ProcessNode {
+
+    fn write_clf(&self, output: &mut dyn Write) -> Result<()> {
+        match self {
+            ProcessNode::Matrix { id, description, matrix } => {
+                writeln!(output,
+                    "    <Matrix id=\"{}\" inBitDepth=\"32f\" outBitDepth=\"32f\">",
+                    escape(id),
+                )?;
+                writeln!(output, "        <Description>{}</Description>", escape(description))?;
+                writeln!(output, "        <Array dim=\"3 3\">")?;
+                for row in matrix.iter() {
+                    writeln!(output, "            {} {} {}", row[0], row[1], row[2])?;
+                }
+                writeln!(output, "        </Array>")?;
+                writeln!(output, "    </Matrix>")
+            }
+            ProcessNode::Lut1d { id, description, half_domain, table } => {
+                writeln!(output,
+                    "    <LUT1D id=\"{}\" inBitDepth=\"32f\" outBitDepth=\"32f\"{}>",
+                    escape(id),
+                    if *half_domain { " halfDomain=\"true\"" } else { "" },
+                )?;
+                writeln!(output, "        <Description>{}</Description>", escape(description))?;
+                writeln!(output, "        <Array dim=\"{} 1\">", table.len())?;
+                for value in table.iter() {
+                    writeln!(output, "            {}", *value as f32)?;
+                }
+                writeln!(output, "        </Array>")?;
+                writeln!(output, "    </LUT1D>")
+            }
+            ProcessNode::Range { id, description, min_in, max_in, min_out, max_out } => {
+                writeln!(output,
+                    "    <Range id=\"{}\" inBitDepth=\"32f\" outBitDepth=\"32f\">",
+                    escape(id),
+                )?;
+                writeln!(output, "        <Description>{}</Description>", escape(description))?;
+                writeln!(output, "        <minInValue>{}</minInValue>", min_in)?;
+                writeln!(output, "        <maxInValue>{}</maxInValue>", max_in)?;
+                writeln!(output, "        <minOutValue>{}</minOutValue>", min_out)?;
+                writeln!(output, "        <maxOutValue>{}</maxOutValue>", max_out)?;
+                writeln!(output, "    </Range>")
+            }
+            ProcessNode::Lut3d { id, description, lut } => {
+                let size = lut.size();
+                writeln!(output,
+                    "    <LUT3D id=\"{}\" inBitDepth=\"32f\" outBitDepth=\"32f\" \
+                        interpolation=\"tetrahedral\">",
+                    escape(id),
+                )?;
+                writeln!(output, "        <Description>{}</Description>", escape(description))?;
+                writeln!(output, "        <Array dim=\"{} {} {} 3\">", size, size, size)?;
+                // Like 3DL but unlike Cube, the blue index moves fastest.
+                for r in 0..size {
+                    for g in 0..size {
+                        for b in 0..size {
+                            let pixel = lut.get(r, g, b);
+                            writeln!(output, "            {} {} {}",
+                                pixel.red as f32,
+                                pixel.green as f32,
+                                pixel.blue as f32,
+                            )?;
+                        }
+                    }
+                }
+                writeln!(output, "        </Array>")?;
+                writeln!(output, "    </LUT3D>")
+            }
+        }
+    }
+
+    fn map(&self, input: RgbPixel) -> RgbPixel {
+        match self {
+            ProcessNode::Matrix { matrix, .. } => {
+                RgbPixel {
+                    red: matrix[0][0] * input.red
+                        + matrix[0][1] * input.green
+                        + matrix[0][2] * input.blue,
+                    green: matrix[1][0] * input.red
+                        + matrix[1][1] * input.green
+                        + matrix[1][2] * input.blue,
+                    blue: matrix[2][0] * input.red
+                        + matrix[2][1] * input.green
+                        + matrix[2][2] * input.blue,
+                }
+            }
+            ProcessNode::Lut1d { half_domain, table, .. } => {
+                if *half_domain {
+                    input.with_each_channel(|x| half_domain_lookup(table, x))
+                } else {
+                    input.with_each_channel(|x| {
+                        let last = table.len() - 1;
+                        let s = (x * last as f64).clamp(0.0, last as f64);
+                        let prev = s as usize;
+                        let next = (prev + 1).min(last);
+                        table[prev] + (table[next] - table[prev]) * (s - prev as f64)
+                    })
+                }
+            }
+            ProcessNode::Range { min_in, max_in, min_out, max_out, .. } => {
+                input.with_each_channel(|x| {
+                    let scale = (max_out - min_out) / (max_in - min_in);
+                    ((x - min_in) * scale + min_out).clamp(*min_out, *max_out)
+                })
+            }
+            ProcessNode::Lut3d { lut, .. } => {
+                lut.map(input)
+            }
+        }
+    }
+}
+
+//
+// Pipelines
+//
+
+pub fn pq_hlg_process_list(mapper: &PqHlgMapper, size: usize) -> ProcessList {
+
+    //
+    // Curves are emitted as 1D LUTs sampled in whichever domain they are well conditioned in,
+    // using a half-float domain whenever the input is linear light. Stages that couple the
+    // channels nonlinearly (maxRGB tone mapping and the HLG inverse OOTF) cannot be described
+    // by any analytic CLF node, so they are baked into 3D LUTs whose inputs are PQ encoded.
+    //
+
+    let prepper = &mapper.prepper;
+    let exposure = prepper.factor.powf(3.0);
+    let pq_max = pq_ieotf(0.1);
+    let tm = &prepper.tm;
+    let mut nodes = vec![
+        ProcessNode::Lut1d {
+            id: String::from("pq_eotf"),
+            description: String::from("PQ signal to display light (1.0 = 10,000 nits)"),
+            half_domain: false,
+            table: sample_curve(|x| pq_eotf(x).clamp(0.0, 1.0)),
+        },
+        ProcessNode::Matrix {
+            id: String::from("bt2020_to_xyz"),
+            description: String::from("BT.2020 RGB to CIE XYZ"),
+            matrix: rgb_matrix(|pixel| {
+                let xyz = pixel.bt2020_to_xyz();
+                RgbPixel::new_rgb(xyz.x, xyz.y, xyz.z)
+            }),
+        },
+        ProcessNode::Matrix {
+            id: String::from("exposure"),
+            description: format!(
+                "Oklab exposure scaling by {}, which is linear scaling by its cube",
+                prepper.factor,
+            ),
+            matrix: [[exposure, 0.0, 0.0], [0.0, exposure, 0.0], [0.0, 0.0, exposure]],
+        },
+        ProcessNode::Matrix {
+            id: String::from("xyz_to_bt2020"),
+            description: String::from("CIE XYZ to BT.2020 RGB"),
+            matrix: rgb_matrix(|pixel| {
+                XyzPixel { x: pixel.red, y: pixel.green, z: pixel.blue }.to_rgb_bt2020()
+            }),
+        },
+        ProcessNode::Lut1d {
+            id: String::from("pq_ieotf"),
+            description: String::from("Display light to PQ signal"),
+            half_domain: true,
+            table: sample_half_domain(|x| pq_ieotf(x.clamp(0.0, 1.0))),
+        },
+    ];
+
+    nodes.push(match tm.method() {
+        ToneMapMethod::Rgb => {
+            ProcessNode::Lut1d {
+                id: String::from("tone_map"),
+                description: String::from(
+                    "BT.2408 R'G'B' tone mapping and 1,000 nit clamping in PQ"
+                ),
+                half_domain: false,
+                table: sample_curve(|x| {
+                    pq_ieotf(tm.map(RgbPixel::new_y(pq_eotf(x))).red.clamp(0.0, 0.1))
+                }),
+            }
+        }
+        ToneMapMethod::MaxRgb => {
+            ProcessNode::Lut3d {
+                id: String::from("tone_map"),
+                description: String::from(
+                    "BT.2408 maxRGB tone mapping and 1,000 nit clamping in PQ"
+                ),
+                lut: Lut3d::from_fn(size, |pixel| {
+                    tm.map(pixel.with_each_channel(pq_eotf))
+                        .clamp(0.0, 0.1)
+                        .with_each_channel(pq_ieotf)
+                }),
+            }
+        }
+    });
+    nodes.push(ProcessNode::Range {
+        id: String::from("pq_1000_nits"),
+        description: String::from("Stretches PQ up to 1,000 nits across the full range"),
+        min_in: 0.0,
+        max_in: pq_max,
+        min_out: 0.0,
+        max_out: 1.0,
+    });
+    nodes.push(ProcessNode::Lut3d {
+        id: String::from("hlg_iootf_oetf"),
+        description: String::from("HLG inverse OOTF and OETF for a 1,000 nit display"),
+        lut: Lut3d::from_fn(size, |pixel| {
+            let display = pixel.with_each_channel(|x| pq_eotf(x * pq_max)) * 10.0;
+            hlg_iootf(display).with_each_channel(|x| hlg_oetf(x).clamp(0.0, 1.0))
+        }),
+    });
+
+    ProcessList {
+        id: String::from("pq2hlg"),
+        name: String::from("PQ to HLG"),
+        description: vec![String::from("BT.2408 conversion from PQ to HLG")],
+        input_descriptor: String::from("ITU-R BT.2100 PQ"),
+        output_descriptor: String::from("ITU-R BT.2100 HLG"),
+        generator: None,
+        parameters: Vec::new(),
+        nodes,
+    }
+}
+
+pub fn hlg_pq_process_list(mapper: &HlgPqMapper, size: usize) -> ProcessList {
+
+    //
+    // The HLG OOTF couples the channels through scene luminance, so it is baked into a 3D LUT
+    // whose input is scene light re-encoded with the PQ curve for even sampling.
+    //
+
+    let gamma = mapper.gamma;
+    let scale = mapper.max_cll / 10_000.0;
+    let nodes = vec![
+        ProcessNode::Lut1d {
+            id: String::from("hlg_ioetf"),
+            description: String::from("HLG signal to scene light"),
+            half_domain: false,
+            table: sample_curve(hlg_ioetf),
+        },
+        ProcessNode::Lut1d {
+            id: String::from("pq_ieotf"),
+            description: String::from("Scene light to PQ signal"),
+            half_domain: true,
+            table: sample_half_domain(|x| pq_ieotf(x.clamp(0.0, 1.0))),
+        },
+        ProcessNode::Lut3d {
+            id: String::from("hlg_ootf"),
+            description: format!(
+                "HLG OOTF with a system gamma of {} and scaling to {} nits",
+                gamma,
+                mapper.max_cll,
+            ),
+            lut: Lut3d::from_fn(size, |pixel| {
+                let scene = pixel.with_each_channel(pq_eotf).clamp(0.0, 1.0);
+                (hlg_ootf(scene, gamma) * scale)
+                    .with_each_channel(|x| pq_ieotf(x).clamp(0.0, 1.0))
+            }),
+        },
+    ];
+
+    ProcessList {
+        id: String::from("hlg2pq"),
+        name: String::from("HLG to PQ"),
+        description: vec![String::from("BT.2408 conversion from HLG to PQ")],
+        input_descriptor: String::from("ITU-R BT.2100 HLG"),
+        output_descriptor: String::from("ITU-R BT.2100 PQ"),
+        generator: None,
+        parameters: Vec::new(),
+        nodes,
+    }
+}
+
+pub(crate) fn rgb_matrix<F>(f: F) -> [[f64; 3]; 3]
+    where F: Fn(RgbPixel) -> RgbPixel {
+
+    let red = f(RgbPixel::new_rgb(1.0, 0.0, 0.0));
+    let green = f(RgbPixel::new_rgb(0.0, 1.0, 0.0));
+    let blue = f(RgbPixel::new_rgb(0.0, 0.0, 1.0));
+
+    [
+        [red.red, green.red, blue.red],
+        [red.green, green.green, blue.green],
+        [red.blue, green.blue, blue.blue],
+    ]
+}
+
+fn sample_curve<F>(f: F) -> Vec<f64>
+    where F: Fn(f64) -> f64 {
+    (0..CURVE_SIZE).map(|i| f(i as f64 / (CURVE_SIZE - 1) as f64)).collect()
+}
+
+fn sample_half_domain<F>(f: F) -> Vec<f64>
+    where F: Fn(f64) -> f64 {
+    (0..HALF_DOMAIN_SIZE)
+        .map(|bits| {
+            let x = half_to_f64(bits as u16);
+            if x.is_nan() {
+                0.0
+            } else {
+                f(x)
+            }
+        })
+        .collect()
+}
+
+fn half_domain_lookup(table: &[f64], x: f64) -> f64 {
+
+    //
+    // Finds the two neighboring half floats around a non-negative input and linearly
+    // interpolates between their entries. Negative inputs share entries with zero.
+    //
+
+    let x = x.max(0.0);
+    let mut bits = 0_u16;
+
+    for bit in (0..15).rev() {
+        let candidate = bits | (1 << bit);
+        if candidate < 0x7C00 && half_to_f64(candidate) <= x {
+            bits = candidate;
+        }
+    }
+
+    let low = half_to_f64(bits);
+    let high = half_to_f64(bits + 1);
+    let a = table[bits as usize];
+
+    if bits + 1 >= 0x7C00 || high <= low {
+        a
+    } else {
+        let b = table[bits as usize + 1];
+        a + (b - a) * ((x - low) / (high - low)).clamp(0.0, 1.0)
+    }
+}
+
+fn half_to_f64(bits: u16) -> f64 {
+
+    let sign = if bits & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = ((bits >> 10) & 0x1F) as i32;
+    let mantissa = (bits & 0x3FF) as f64;
+
+    sign * match exponent {
+        0 => mantissa * 2.0_f64.powi(-24),
+        31 => if mantissa == 0.0 { f64::INFINITY } else { f64::NAN },
+        _ => (1.0 + mantissa / 1_024.0) * 2.0_f64.powi(exponent - 15),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
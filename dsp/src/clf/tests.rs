@@ -0,0 +1,154 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use assert_approx_eq::assert_approx_eq;
+
+const DIFF: f64 = 0.0000000001;
+
+fn node_ids(list: &ProcessList) -> Vec<&str> {
+    list.nodes
+        .iter()
+        .map(|node| match node {
+            ProcessNode::Matrix { id, .. } => id.as_str(),
+            ProcessNode::Lut1d { id, .. } => id.as_str(),
+            ProcessNode::Range { id, .. } => id.as_str(),
+            ProcessNode::Lut3d { id, .. } => id.as_str(),
+        })
+        .collect()
+}
+
+fn assert_neutral_accuracy<M>(list: &ProcessList, mapper: &M, max_error: f64)
+    where M: Mapper {
+
+    for i in 0..1_000 {
+
+        let in_pixel = RgbPixel::new_y((i as f64 + 0.37) / 1_000.0);
+        let expected = mapper.map(in_pixel);
+        let actual = list.map(in_pixel);
+
+        assert_approx_eq!(actual.red, expected.red, max_error);
+        assert_approx_eq!(actual.green, expected.green, max_error);
+        assert_approx_eq!(actual.blue, expected.blue, max_error);
+    }
+}
+
+#[test]
+fn test_half_to_f64() {
+    assert_eq!(half_to_f64(0x0000), 0.0);
+    assert_eq!(half_to_f64(0x0001), 2.0_f64.powi(-24));
+    assert_eq!(half_to_f64(0x3C00), 1.0);
+    assert_eq!(half_to_f64(0x3555), 0.333251953125);
+    assert_eq!(half_to_f64(0xC000), -2.0);
+    assert_eq!(half_to_f64(0x7BFF), 65_504.0);
+    assert!(half_to_f64(0x7C00).is_infinite());
+    assert!(half_to_f64(0x7E00).is_nan());
+}
+
+#[test]
+fn test_half_domain_lookup() {
+
+    let table = sample_half_domain(|x| x);
+
+    assert_eq!(table.len(), HALF_DOMAIN_SIZE);
+
+    for x in [0.0, 0.000001, 0.001, 0.25, 0.3, 1.0, 7.5] {
+        assert_approx_eq!(half_domain_lookup(&table, x), x, DIFF);
+    }
+
+    assert_approx_eq!(half_domain_lookup(&table, -1.0), 0.0, DIFF);
+}
+
+#[test]
+fn test_rgb_matrix_white() {
+
+    let matrix = rgb_matrix(|pixel| {
+        let xyz = pixel.bt2020_to_xyz();
+        RgbPixel::new_rgb(xyz.x, xyz.y, xyz.z)
+    });
+    let xyz = RgbPixel::new_y(1.0).bt2020_to_xyz();
+
+    assert_approx_eq!(matrix[0].iter().sum::<f64>(), xyz.x, DIFF);
+    assert_approx_eq!(matrix[1].iter().sum::<f64>(), xyz.y, DIFF);
+    assert_approx_eq!(matrix[2].iter().sum::<f64>(), xyz.z, DIFF);
+}
+
+#[test]
+fn test_pq_hlg_process_list_nodes() {
+
+    let rgb = pq_hlg_process_list(&PqHlgMapper::new(4_000.0, ToneMapMethod::Rgb), 17);
+    let max_rgb = pq_hlg_process_list(&PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb), 17);
+    let ids = [
+        "pq_eotf",
+        "bt2020_to_xyz",
+        "exposure",
+        "xyz_to_bt2020",
+        "pq_ieotf",
+        "tone_map",
+        "pq_1000_nits",
+        "hlg_iootf_oetf",
+    ];
+
+    assert_eq!(node_ids(&rgb), ids);
+    assert_eq!(node_ids(&max_rgb), ids);
+    assert!(matches!(rgb.nodes[5], ProcessNode::Lut1d { .. }));
+    assert!(matches!(max_rgb.nodes[5], ProcessNode::Lut3d { .. }));
+}
+
+#[test]
+fn test_pq_hlg_process_list_accuracy() {
+    for ref_white in [203.0, 300.0] {
+        for tm_method in [ToneMapMethod::Rgb, ToneMapMethod::MaxRgb] {
+            let mapper = PqHlgMapper::new_by_ref_white(ref_white, 4_000.0, tm_method);
+            assert_neutral_accuracy(&pq_hlg_process_list(&mapper, 33), &mapper, 0.002);
+        }
+    }
+}
+
+#[test]
+fn test_hlg_pq_process_list_accuracy() {
+    for max_cll in [1_000.0, 4_000.0] {
+        let mapper = HlgPqMapper::new(max_cll);
+        assert_neutral_accuracy(&hlg_pq_process_list(&mapper, 33), &mapper, 0.002);
+    }
+}
+
+#[test]
+fn test_write_clf() {
+
+    let mut list = hlg_pq_process_list(&HlgPqMapper::new(1_000.0), 2);
+
+    list.generator = Some(String::from("HLG2PQ <test>"));
+    list.parameters.push((String::from("max-cll"), String::from("1000")));
+
+    let mut output = Vec::new();
+
+    list.write_clf(&mut output).unwrap();
+
+    let text = String::from_utf8(output).unwrap();
+    let lines = text.lines().collect::<Vec<&str>>();
+
+    assert_eq!(lines[0], "<?xml version=\"1.0\" encoding=\"UTF-8\"?>");
+    assert_eq!(lines[1], "<ProcessList id=\"hlg2pq\" name=\"HLG to PQ\" compCLFversion=\"3.0\">");
+    assert!(lines.contains(&"    <InputDescriptor>ITU-R BT.2100 HLG</InputDescriptor>"));
+    assert!(lines.contains(&"        <Generator>HLG2PQ &lt;test&gt;</Generator>"));
+    assert!(lines.contains(&"        <Parameter name=\"max-cll\">1000</Parameter>"));
+    assert!(lines.contains(&"        <Array dim=\"4096 1\">"));
+    assert!(lines.contains(&"        <Array dim=\"65536 1\">"));
+    assert!(lines.contains(&"        <Array dim=\"2 2 2 3\">"));
+    assert_eq!(lines[lines.len() - 1], "</ProcessList>");
+    assert_eq!(text.matches("halfDomain=\"true\"").count(), 1);
+    assert_eq!(
+        lines.len(),
+        2 + 1 + 2 + 4 + (5 + 4_096) + (5 + 65_536) + (5 + 8) + 1,
+    );
+}
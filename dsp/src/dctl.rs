@@ -0,0 +1,129 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{
+    PqHlgMapper,
+    clf::rgb_matrix,
+    gpu::{
+        Dialect, OKLAB_FUNCTIONS, PQ_FUNCTIONS, ShaderParameters, TONE_MAP_FUNCTIONS,
+    },
+    pixel::{RgbPixel, XyzPixel},
+};
+use std::io::{Result, Write};
+
+//
+// DaVinci Resolve Color Transform Language
+//
+
+pub struct Dctl {
+    pub comments: Vec<String>,
+    parameters: ShaderParameters,
+}
+
+impl Dctl {
+
+    pub fn write_dctl(&self, output: &mut dyn Write) -> Result<()> {
+
+        for comment in self.comments.iter() {
+            writeln!(output, "// {}", comment)?;
+        }
+        if !self.comments.is_empty() {
+            writeln!(output)?;
+        }
+
+        self.parameters.write(output, Dialect::Dctl)?;
+        writeln!(output)?;
+        Dialect::Dctl.write(output, PQ_FUNCTIONS)?;
+        writeln!(output)?;
+        Dialect::Dctl.write_matrix_function(output, "bt2020_to_xyz", &rgb_matrix(|pixel| {
+            let xyz = pixel.bt2020_to_xyz();
+            RgbPixel::new_rgb(xyz.x, xyz.y, xyz.z)
+        }))?;
+        writeln!(output)?;
+        Dialect::Dctl.write_matrix_function(output, "xyz_to_bt2020", &rgb_matrix(|pixel| {
+            XyzPixel { x: pixel.red, y: pixel.green, z: pixel.blue }.to_rgb_bt2020()
+        }))?;
+        writeln!(output)?;
+        Dialect::Dctl.write(output, OKLAB_FUNCTIONS)?;
+        writeln!(output)?;
+        write!(output, "{}", EXPOSURE_FUNCTION)?;
+        writeln!(output)?;
+        Dialect::Dctl.write(output, TONE_MAP_FUNCTIONS)?;
+        writeln!(output)?;
+        write!(output, "{}", HLG_FUNCTIONS)?;
+        writeln!(output)?;
+        write!(output, "{}", PQ_HLG_TRANSFORM)
+    }
+}
+
+pub fn pq_hlg_dctl(mapper: &PqHlgMapper) -> Dctl {
+    Dctl {
+        comments: Vec::new(),
+        parameters: ShaderParameters::new(&mapper.prepper),
+    }
+}
+
+const EXPOSURE_FUNCTION: &str = "\
+__DEVICE__ float3 exposure(float3 rgb) {
+    float3 lab = xyz_to_oklab(bt2020_to_xyz(rgb));
+    lab = make_float3(lab.x * EXPOSURE_FACTOR, lab.y * EXPOSURE_FACTOR, lab.z * EXPOSURE_FACTOR);
+    return xyz_to_bt2020(oklab_to_xyz(lab));
+}
+";
+
+const HLG_FUNCTIONS: &str = "\
+__DEVICE__ float3 hlg_iootf(float3 rgb) {
+    float y = 0.2627f * rgb.x + 0.6780f * rgb.y + 0.0593f * rgb.z;
+    float factor = y > 0.0f ? _powf(y, -0.16666666666666663f) : 0.0f;
+    return make_float3(rgb.x * factor, rgb.y * factor, rgb.z * factor);
+}
+
+__DEVICE__ float hlg_oetf(float o) {
+    return o < 0.08333333333333333f
+        ? _sqrtf(3.0f * o)
+        : 0.17883277f * _logf(12.0f * o - 0.28466892f) + 0.559910729529562f;
+}
+";
+
+//
+// Resolve can hand values outside of 0.0 to 1.0 to a DCTL, so the PQ signal is clamped before
+// it reaches any power function.
+//
+
+const PQ_HLG_TRANSFORM: &str = "\
+__DEVICE__ float3 transform(int p_Width, int p_Height, int p_X, int p_Y, float p_R, float p_G, \
+float p_B) {
+    float3 rgb = make_float3(
+        pq_eotf(_clampf(p_R, 0.0f, 1.0f)),
+        pq_eotf(_clampf(p_G, 0.0f, 1.0f)),
+        pq_eotf(_clampf(p_B, 0.0f, 1.0f))
+    );
+    rgb = exposure(rgb);
+    if (TONE_MAP_METHOD == TONE_MAP_RGB) {
+        rgb = tone_map_rgb(rgb);
+    } else if (TONE_MAP_METHOD == TONE_MAP_MAX_RGB) {
+        rgb = tone_map_max_rgb(rgb);
+    }
+    rgb = make_float3(
+        _clampf(rgb.x, 0.0f, 0.1f) * 10.0f,
+        _clampf(rgb.y, 0.0f, 0.1f) * 10.0f,
+        _clampf(rgb.z, 0.0f, 0.1f) * 10.0f
+    );
+    rgb = hlg_iootf(rgb);
+    return make_float3(
+        _clampf(hlg_oetf(rgb.x), 0.0f, 1.0f),
+        _clampf(hlg_oetf(rgb.y), 0.0f, 1.0f),
+        _clampf(hlg_oetf(rgb.z), 0.0f, 1.0f)
+    );
+}
+";
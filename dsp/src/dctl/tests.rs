@@ -0,0 +1,96 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use crate::{
+    gpu::tests::{assert_balanced, generate},
+    tf::pq_ieotf,
+    tm::ToneMapMethod,
+};
+use std::collections::HashMap;
+
+fn generate_dctl(mapper: &PqHlgMapper) -> String {
+
+    let mut dctl = pq_hlg_dctl(mapper);
+
+    dctl.comments.push(String::from("max-cll: 4000"));
+
+    generate(|output| dctl.write_dctl(output))
+}
+
+fn constants(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .filter_map(|line| line.strip_prefix("__CONSTANT__ "))
+        .map(|line| {
+            let (declaration, value) = line.strip_suffix(';').unwrap().split_once(" = ").unwrap();
+            let name = declaration.split_whitespace().nth(1).unwrap();
+            (String::from(name), String::from(value))
+        })
+        .collect()
+}
+
+fn constant_f32(constants: &HashMap<String, String>, name: &str) -> f32 {
+    constants[name].strip_suffix('f').unwrap().parse::<f32>().unwrap()
+}
+
+#[test]
+fn test_dctl_structure() {
+
+    let text = generate_dctl(&PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb));
+    let code = assert_balanced(&text);
+
+    assert!(text.starts_with("// max-cll: 4000\n"));
+
+    // Every function must be defined before it is used, as in C.
+    let mut defined = Vec::new();
+
+    for line in code.lines().filter(|line| line.starts_with("__DEVICE__ ")) {
+        let name = line.split_whitespace().nth(2).unwrap().split('(').next().unwrap();
+        assert!(!defined.contains(&name), "{} is defined twice", name);
+        defined.push(name);
+    }
+    assert_eq!(defined, [
+        "pq_eotf", "pq_ieotf", "bt2020_to_xyz", "xyz_to_bt2020", "xyz_to_oklab", "oklab_to_xyz",
+        "exposure", "eetf", "tone_map_channel", "tone_map_rgb", "tone_map_max_rgb", "hlg_iootf",
+        "hlg_oetf", "transform",
+    ]);
+    assert!(code.contains("__DEVICE__ float3 transform(int p_Width, int p_Height, int p_X, \
+        int p_Y, float p_R, float p_G, float p_B) {"));
+}
+
+#[test]
+fn test_dctl_constants() {
+
+    let mapper = PqHlgMapper::new_by_factor(0.9, 4_000.0, ToneMapMethod::Rgb);
+    let constants = constants(&generate_dctl(&mapper));
+    let peak = constant_f32(&constants, "TM_PEAK") as f64;
+    let lwp = pq_ieotf(peak);
+    let ml = pq_ieotf(0.1) / lwp;
+
+    assert_eq!(constant_f32(&constants, "EXPOSURE_FACTOR"), 0.9);
+    assert_eq!(constants["TONE_MAP_METHOD"], "TONE_MAP_RGB");
+    assert_eq!(constant_f32(&constants, "TM_TARGET"), 0.1);
+    assert!(peak > 0.1 && peak < 0.4 * 0.9 * 0.9 * 0.9 + 0.0001);
+    assert!((constant_f32(&constants, "TM_LWP") as f64 - lwp).abs() < 0.000001);
+    assert!((constant_f32(&constants, "TM_ML") as f64 - ml).abs() < 0.000001);
+    assert!((constant_f32(&constants, "TM_KS") as f64 - (1.5 * ml - 0.5)).abs() < 0.000001);
+}
+
+#[test]
+fn test_dctl_tone_map_method() {
+
+    let max_rgb = generate_dctl(&PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb));
+    let passthrough = generate_dctl(&PqHlgMapper::new(600.0, ToneMapMethod::MaxRgb));
+
+    assert_eq!(constants(&max_rgb)["TONE_MAP_METHOD"], "TONE_MAP_MAX_RGB");
+    assert_eq!(constants(&passthrough)["TONE_MAP_METHOD"], "TONE_MAP_NONE");
+}
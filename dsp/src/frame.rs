@@ -0,0 +1,104 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{Mapper, pixel::RgbPixel};
+use std::thread::{available_parallelism, scope};
+
+//
+// Row Bands
+//
+// A frame is split into one band of whole rows per thread. Every band is handled by its own
+// scoped thread and the results are returned in band order, so the output never depends upon
+// how the threads happen to be scheduled.
+//
+
+pub fn default_threads() -> usize {
+    available_parallelism().map(|threads| threads.get()).unwrap_or(1)
+}
+
+pub fn map_bands<E, T, F>(frame: &[E], row_size: usize, threads: usize, f: F) -> Vec<T>
+    where E: Sync, T: Send, F: Fn(&[E]) -> T + Sync {
+
+    let band_size = band_size(frame.len(), row_size, threads);
+
+    if band_size >= frame.len() {
+        return vec![f(frame)]
+    }
+
+    scope(|s| {
+        frame
+            .chunks(band_size)
+            .map(|band| s.spawn(|| f(band)))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|handle| handle.join().expect("Band thread panicked."))
+            .collect()
+    })
+}
+
+pub fn for_each_band_mut<F>(frame: &mut [u8], row_size: usize, threads: usize, f: F)
+    where F: Fn(&mut [u8]) + Sync {
+
+    let band_size = band_size(frame.len(), row_size, threads);
+
+    if band_size >= frame.len() {
+        return f(frame)
+    }
+
+    scope(|s| {
+        for band in frame.chunks_mut(band_size) {
+            s.spawn(|| f(band));
+        }
+    });
+}
+
+fn band_size(frame_size: usize, row_size: usize, threads: usize) -> usize {
+
+    let rows = frame_size.div_ceil(row_size.max(1));
+
+    rows.div_ceil(threads.max(1)).max(1) * row_size.max(1)
+}
+
+//
+// RGB48LE
+//
+
+pub fn code_to_unit(code: u16) -> f64 {
+    code as f64 / 65_535.0
+}
+
+pub fn unit_to_code(value: f64) -> u16 {
+    (value.clamp(0.0, 1.0) * 65_535.0).round() as u16
+}
+
+pub fn map_rgb48le(
+    mapper: &(dyn Mapper + Sync),
+    frame: &mut [u8],
+    width: usize,
+    threads: usize,
+) {
+    for_each_band_mut(frame, 6 * width, threads, |band| {
+        for pixel in band.chunks_exact_mut(6) {
+
+            let mapped = mapper.map(RgbPixel::new_rgb(
+                code_to_unit(u16::from_le_bytes([pixel[0], pixel[1]])),
+                code_to_unit(u16::from_le_bytes([pixel[2], pixel[3]])),
+                code_to_unit(u16::from_le_bytes([pixel[4], pixel[5]])),
+            ));
+
+            pixel[0..2].copy_from_slice(&unit_to_code(mapped.red).to_le_bytes());
+            pixel[2..4].copy_from_slice(&unit_to_code(mapped.green).to_le_bytes());
+            pixel[4..6].copy_from_slice(&unit_to_code(mapped.blue).to_le_bytes());
+        }
+    });
+}
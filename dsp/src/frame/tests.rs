@@ -0,0 +1,99 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use crate::{PqHlgMapper, tm::ToneMapMethod};
+
+fn test_frame(width: usize, height: usize) -> Vec<u8> {
+    (0..3 * width * height)
+        .flat_map(|i| ((i * 7_919 % 65_536) as u16).to_le_bytes())
+        .collect()
+}
+
+#[test]
+fn test_band_size() {
+    assert_eq!(band_size(60, 6, 1), 60);
+    assert_eq!(band_size(60, 6, 2), 30);
+    assert_eq!(band_size(60, 6, 3), 24);
+    assert_eq!(band_size(60, 6, 4), 18);
+    assert_eq!(band_size(60, 6, 100), 6);
+    assert_eq!(band_size(0, 6, 4), 6);
+}
+
+#[test]
+fn test_map_bands_order() {
+
+    let frame = (0..100_u8).collect::<Vec<u8>>();
+
+    for threads in 1..12 {
+
+        let bands = map_bands(&frame, 10, threads, |band| band.to_vec());
+
+        assert!(bands.len() <= threads);
+        assert!(bands.iter().all(|band| band.len() % 10 == 0));
+        assert_eq!(bands.concat(), frame);
+    }
+}
+
+#[test]
+fn test_for_each_band_mut() {
+
+    let mut frame = vec![1_u8; 99];
+
+    for_each_band_mut(&mut frame, 9, 4, |band| {
+        let length = band.len() as u8;
+        for value in band.iter_mut() {
+            *value += length;
+        }
+    });
+
+    assert_eq!(&frame[0..27], &[28; 27]);
+    assert_eq!(&frame[81..99], &[19; 18]);
+}
+
+#[test]
+fn test_code_round_trip() {
+    for code in [0, 1, 0x4000, 0x8000, 0xFFFE, 0xFFFF] {
+        assert_eq!(unit_to_code(code_to_unit(code)), code);
+    }
+    assert_eq!(unit_to_code(-0.5), 0);
+    assert_eq!(unit_to_code(1.5), 0xFFFF);
+}
+
+#[test]
+fn test_map_rgb48le_threads() {
+
+    let mapper = PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb);
+    let mut expected = test_frame(7, 5);
+
+    for pixel in expected.chunks_exact_mut(6) {
+
+        let mapped = mapper.map(RgbPixel::new_rgb(
+            code_to_unit(u16::from_le_bytes([pixel[0], pixel[1]])),
+            code_to_unit(u16::from_le_bytes([pixel[2], pixel[3]])),
+            code_to_unit(u16::from_le_bytes([pixel[4], pixel[5]])),
+        ));
+
+        pixel[0..2].copy_from_slice(&unit_to_code(mapped.red).to_le_bytes());
+        pixel[2..4].copy_from_slice(&unit_to_code(mapped.green).to_le_bytes());
+        pixel[4..6].copy_from_slice(&unit_to_code(mapped.blue).to_le_bytes());
+    }
+
+    for threads in [1, 2, 3, 5, 8] {
+
+        let mut frame = test_frame(7, 5);
+
+        map_rgb48le(&mapper, &mut frame, 7, threads);
+
+        assert_eq!(frame, expected);
+    }
+}
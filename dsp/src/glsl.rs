@@ -0,0 +1,242 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{
+    PqHlgMapper,
+    PqPrepper,
+    PqSdrMapper,
+    clf::rgb_matrix,
+    gpu::{
+        Dialect, OKLAB_FUNCTIONS, PQ_FUNCTIONS, ShaderParameters, TONE_MAP_FUNCTIONS, float,
+    },
+    pixel::{
+        BLUE_FACTOR_2020, BLUE_FACTOR_709, GREEN_FACTOR_2020, GREEN_FACTOR_709, RED_FACTOR_2020,
+        RED_FACTOR_709, RgbPixel, XyzPixel,
+    },
+};
+use std::io::{Result, Write};
+
+//
+// mpv User Shader
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MpvHook {
+    Main,
+    Output,
+}
+
+impl MpvHook {
+
+    fn name(&self) -> &'static str {
+        match self {
+            MpvHook::Main => "MAIN",
+            MpvHook::Output => "OUTPUT",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Conversion {
+    Hlg,
+    Sdr,
+}
+
+pub struct MpvShader {
+    pub description: String,
+    pub comments: Vec<String>,
+    pub hook: MpvHook,
+    conversion: Conversion,
+    parameters: ShaderParameters,
+}
+
+impl MpvShader {
+
+    pub fn write_glsl(&self, output: &mut dyn Write) -> Result<()> {
+
+        for comment in self.comments.iter() {
+            writeln!(output, "// {}", comment)?;
+        }
+        if !self.comments.is_empty() {
+            writeln!(output)?;
+        }
+
+        // Whichever texture is hooked, mpv keeps describing the image as the PQ signal it
+        // decoded, so it must already be targeting PQ or it will convert the output again.
+        writeln!(output, "// This shader replaces PQ with {}, which mpv still treats as PQ.",
+            match self.conversion {
+                Conversion::Hlg => "HLG",
+                Conversion::Sdr => "a black and white SDR preview",
+            },
+        )?;
+        writeln!(output,
+            "// Play it with --target-trc=pq --target-prim=bt.2020 to leave it as is.",
+        )?;
+        writeln!(output)?;
+        writeln!(output, "//!HOOK {}", self.hook.name())?;
+        writeln!(output, "//!BIND HOOKED")?;
+        writeln!(output, "//!DESC {}", self.description.replace('\n', " "))?;
+        writeln!(output)?;
+
+        self.parameters.write(output, Dialect::Glsl)?;
+        writeln!(output)?;
+        Dialect::Glsl.write(output, PQ_FUNCTIONS)?;
+        writeln!(output)?;
+        Dialect::Glsl.write_matrix_function(output, "bt2020_to_xyz", &rgb_matrix(|pixel| {
+            let xyz = pixel.bt2020_to_xyz();
+            RgbPixel::new_rgb(xyz.x, xyz.y, xyz.z)
+        }))?;
+        writeln!(output)?;
+        Dialect::Glsl.write_matrix_function(output, "xyz_to_bt2020", &rgb_matrix(|pixel| {
+            XyzPixel { x: pixel.red, y: pixel.green, z: pixel.blue }.to_rgb_bt2020()
+        }))?;
+        writeln!(output)?;
+        write!(output, "{}", CBRT_FUNCTION)?;
+        writeln!(output)?;
+        Dialect::Glsl.write(output, OKLAB_FUNCTIONS)?;
+        writeln!(output)?;
+        Dialect::Glsl.write(output, TONE_MAP_FUNCTIONS)?;
+        writeln!(output)?;
+        write!(output, "{}", PREPARE_FUNCTION)?;
+        writeln!(output)?;
+
+        match self.conversion {
+            Conversion::Hlg => {
+                writeln!(output, "float hlg_iootf_factor(vec3 rgb) {{")?;
+                writeln!(output, "    float y = dot(vec3({}, {}, {}), rgb);",
+                    float(RED_FACTOR_2020),
+                    float(GREEN_FACTOR_2020),
+                    float(BLUE_FACTOR_2020),
+                )?;
+                writeln!(output, "    return y > 0.0 ? pow(y, -0.16666666666666663) : 0.0;")?;
+                writeln!(output, "}}")?;
+                writeln!(output)?;
+                write!(output, "{}", HLG_FUNCTIONS)?;
+            }
+            Conversion::Sdr => {
+                Dialect::Glsl.write_matrix_function(output, "bt709_to_xyz", &rgb_matrix(|pixel| {
+                    let xyz = pixel.bt709_to_xyz();
+                    RgbPixel::new_rgb(xyz.x, xyz.y, xyz.z)
+                }))?;
+                writeln!(output)?;
+                Dialect::Glsl.write_matrix_function(output, "xyz_to_bt709", &rgb_matrix(|pixel| {
+                    XyzPixel { x: pixel.red, y: pixel.green, z: pixel.blue }.to_rgb_bt709()
+                }))?;
+                writeln!(output)?;
+                writeln!(output, "float y_bt709(vec3 rgb) {{")?;
+                writeln!(output, "    return dot(vec3({}, {}, {}), rgb);",
+                    float(RED_FACTOR_709),
+                    float(GREEN_FACTOR_709),
+                    float(BLUE_FACTOR_709),
+                )?;
+                writeln!(output, "}}")?;
+                writeln!(output)?;
+                write!(output, "{}", SDR_FUNCTIONS)?;
+            }
+        }
+        writeln!(output)?;
+
+        writeln!(output, "vec4 hook() {{")?;
+        writeln!(output, "    vec4 color = HOOKED_texOff(0);")?;
+        writeln!(output, "    color.rgb = {}(color.rgb);", match self.conversion {
+            Conversion::Hlg => "pq_hlg",
+            Conversion::Sdr => "pq_sdr",
+        })?;
+        writeln!(output, "    return color;")?;
+        writeln!(output, "}}")
+    }
+}
+
+pub fn pq_hlg_shader(mapper: &PqHlgMapper) -> MpvShader {
+    shader(&mapper.prepper, Conversion::Hlg, "PQ to HLG")
+}
+
+pub fn pq_sdr_shader(mapper: &PqSdrMapper) -> MpvShader {
+    shader(&mapper.prepper, Conversion::Sdr, "PQ to SDR preview")
+}
+
+fn shader(prepper: &PqPrepper, conversion: Conversion, description: &str) -> MpvShader {
+    MpvShader {
+        description: String::from(description),
+        comments: Vec::new(),
+        hook: MpvHook::Output,
+        conversion,
+        parameters: ShaderParameters::new(prepper),
+    }
+}
+
+//
+// GLSL has no cube root and leaves `pow` undefined for negative bases, so the sign is carried
+// around it.
+//
+
+const CBRT_FUNCTION: &str = "\
+float cbrt(float x) {
+    return sign(x) * pow(abs(x), 0.3333333333333333);
+}
+";
+
+const PREPARE_FUNCTION: &str = "\
+vec3 prepare(vec3 signal) {
+    signal = clamp(signal, 0.0, 1.0);
+    vec3 rgb = clamp(
+        vec3(pq_eotf(signal.r), pq_eotf(signal.g), pq_eotf(signal.b)),
+        0.0,
+        1.0
+    );
+    rgb = xyz_to_bt2020(oklab_to_xyz(xyz_to_oklab(bt2020_to_xyz(rgb)) * EXPOSURE_FACTOR));
+    if (TONE_MAP_METHOD == TONE_MAP_RGB) {
+        rgb = tone_map_rgb(rgb);
+    } else if (TONE_MAP_METHOD == TONE_MAP_MAX_RGB) {
+        rgb = tone_map_max_rgb(rgb);
+    }
+    return clamp(rgb, 0.0, 0.1);
+}
+";
+
+const HLG_FUNCTIONS: &str = "\
+float hlg_oetf(float o) {
+    return o < 0.08333333333333333
+        ? sqrt(3.0 * o)
+        : 0.17883277 * log(12.0 * o - 0.28466892) + 0.559910729529562;
+}
+
+vec3 pq_hlg(vec3 signal) {
+    vec3 rgb = prepare(signal) * 10.0;
+    rgb *= hlg_iootf_factor(rgb);
+    return clamp(vec3(hlg_oetf(rgb.r), hlg_oetf(rgb.g), hlg_oetf(rgb.b)), 0.0, 1.0);
+}
+";
+
+const SDR_FUNCTIONS: &str = "\
+float sdn_tone_map(float o) {
+    if (o < 0.0) {
+        return 0.0;
+    }
+    if (o <= 0.203) {
+        vec3 lab = xyz_to_oklab(bt709_to_xyz(vec3(o))) * 0.7331586840443699;
+        return y_bt709(xyz_to_bt709(oklab_to_xyz(lab))) * 10.0;
+    }
+    if (o <= 1.0) {
+        return log(o - 0.19) / 21.0 + 1.007;
+    }
+    return 1.0;
+}
+
+vec3 pq_sdr(vec3 signal) {
+    vec3 rgb = prepare(signal);
+    float l = xyz_to_oklab(bt2020_to_xyz(rgb)).x;
+    float y = y_bt709(xyz_to_bt709(oklab_to_xyz(vec3(l, 0.0, 0.0))));
+    return vec3(clamp(pow(max(sdn_tone_map(y * 10.0), 0.0), 0.4166666666666667), 0.0, 1.0));
+}
+";
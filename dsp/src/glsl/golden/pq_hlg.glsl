@@ -0,0 +1,153 @@
+// max-cll: 4000
+// ref-white: 300
+
+// This shader replaces PQ with HLG, which mpv still treats as PQ.
+// Play it with --target-trc=pq --target-prim=bt.2020 to leave it as is.
+
+//!HOOK OUTPUT
+//!BIND HOOKED
+//!DESC PQ to HLG
+
+// Oklab exposure scaling factor
+const float EXPOSURE_FACTOR = 0.8779267;
+
+// BT.2408 tone mapping (1.0 = 10,000 nits)
+const int TONE_MAP_NONE = 0;
+const int TONE_MAP_RGB = 1;
+const int TONE_MAP_MAX_RGB = 2;
+const int TONE_MAP_METHOD = TONE_MAP_MAX_RGB;
+const float TM_PEAK = 0.27066666;
+const float TM_TARGET = 0.1;
+const float TM_LWP = 0.860328;
+const float TM_ML = 0.8738842;
+const float TM_KS = 0.8108263;
+
+float pq_eotf(float e) {
+    float p = pow(e, 0.012683313515655966);
+    return pow(max(p - 0.8359375, 0.0) / (18.8515625 - 18.6875 * p), 6.277394636015326);
+}
+
+float pq_ieotf(float o) {
+    float p = pow(o, 0.1593017578125);
+    return pow((0.8359375 + 18.8515625 * p) / (1.0 + 18.6875 * p), 78.84375);
+}
+
+vec3 bt2020_to_xyz(vec3 c) {
+    return vec3(
+        0.6370102 * c.x + 0.14461502 * c.y + 0.16884477 * c.z,
+        0.26272172 * c.x + 0.6779893 * c.y + 0.05928901 * c.z,
+        0.0 * c.x + 0.02807233 * c.y + 1.0607576 * c.z
+    );
+}
+
+vec3 xyz_to_bt2020(vec3 c) {
+    return vec3(
+        1.7165107 * c.x + -0.35564166 * c.y + -0.25334555 * c.z,
+        -0.666693 * c.x + 1.6165022 * c.y + 0.01576875 * c.z,
+        0.017643638 * c.x + -0.04277978 * c.y + 0.9423051 * c.z
+    );
+}
+
+float cbrt(float x) {
+    return sign(x) * pow(abs(x), 0.3333333333333333);
+}
+
+vec3 xyz_to_oklab(vec3 c) {
+    float l = cbrt(0.8189330101 * c.x + 0.3618667424 * c.y - 0.1288597137 * c.z);
+    float m = cbrt(0.0329845436 * c.x + 0.9293118715 * c.y + 0.0361456387 * c.z);
+    float s = cbrt(0.0482003018 * c.x + 0.2643662691 * c.y + 0.6338517070 * c.z);
+    return vec3(
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s
+    );
+}
+
+vec3 oklab_to_xyz(vec3 c) {
+    float l = 0.99999999845051981432 * c.x
+        + 0.39633779217376785678 * c.y
+        + 0.21580375806075880339 * c.z;
+    float m = 1.0000000088817607767 * c.x
+        - 0.1055613423236563494 * c.y
+        - 0.063854174771705903402 * c.z;
+    float s = 1.0000000546724109177 * c.x
+        - 0.089484182094965759684 * c.y
+        - 1.2914855378640917399 * c.z;
+    l = l * l * l;
+    m = m * m * m;
+    s = s * s * s;
+    return vec3(
+        1.227013851103521026 * l - 0.5577999806518222383 * m + 0.28125614896646780758 * s,
+        -0.040580178423280593977 * l + 1.1122568696168301049 * m - 0.071676678665601200577 * s,
+        -0.076381284505706892869 * l - 0.42148197841801273055 * m + 1.5861632204407947575 * s
+    );
+}
+
+float eetf(float e) {
+    float e1 = e / TM_LWP;
+    if (TM_KS < e1 && e1 <= 1.0) {
+        float t = (e1 - TM_KS) / (1.0 - TM_KS);
+        float t2 = t * t;
+        float t3 = t2 * t;
+        e1 = (2.0 * t3 - 3.0 * t2 + 1.0) * TM_KS
+            + (t3 - 2.0 * t2 + t) * (1.0 - TM_KS)
+            + (-2.0 * t3 + 3.0 * t2) * TM_ML;
+    }
+    return e1 * TM_LWP;
+}
+
+float tone_map_channel(float o) {
+    return o > 0.0 ? pq_eotf(eetf(pq_ieotf(o))) : 0.0;
+}
+
+vec3 tone_map_rgb(vec3 rgb) {
+    return vec3(tone_map_channel(rgb.x), tone_map_channel(rgb.y), tone_map_channel(rgb.z));
+}
+
+vec3 tone_map_max_rgb(vec3 rgb) {
+    float m1 = max(rgb.x, max(rgb.y, rgb.z));
+    if (m1 <= 0.0) {
+        return rgb;
+    }
+    float factor = pq_eotf(eetf(pq_ieotf(m1))) / m1;
+    return vec3(rgb.x * factor, rgb.y * factor, rgb.z * factor);
+}
+
+vec3 prepare(vec3 signal) {
+    signal = clamp(signal, 0.0, 1.0);
+    vec3 rgb = clamp(
+        vec3(pq_eotf(signal.r), pq_eotf(signal.g), pq_eotf(signal.b)),
+        0.0,
+        1.0
+    );
+    rgb = xyz_to_bt2020(oklab_to_xyz(xyz_to_oklab(bt2020_to_xyz(rgb)) * EXPOSURE_FACTOR));
+    if (TONE_MAP_METHOD == TONE_MAP_RGB) {
+        rgb = tone_map_rgb(rgb);
+    } else if (TONE_MAP_METHOD == TONE_MAP_MAX_RGB) {
+        rgb = tone_map_max_rgb(rgb);
+    }
+    return clamp(rgb, 0.0, 0.1);
+}
+
+float hlg_iootf_factor(vec3 rgb) {
+    float y = dot(vec3(0.2627, 0.678, 0.0593), rgb);
+    return y > 0.0 ? pow(y, -0.16666666666666663) : 0.0;
+}
+
+float hlg_oetf(float o) {
+    return o < 0.08333333333333333
+        ? sqrt(3.0 * o)
+        : 0.17883277 * log(12.0 * o - 0.28466892) + 0.559910729529562;
+}
+
+vec3 pq_hlg(vec3 signal) {
+    vec3 rgb = prepare(signal) * 10.0;
+    rgb *= hlg_iootf_factor(rgb);
+    return clamp(vec3(hlg_oetf(rgb.r), hlg_oetf(rgb.g), hlg_oetf(rgb.b)), 0.0, 1.0);
+}
+
+vec4 hook() {
+    vec4 color = HOOKED_texOff(0);
+    color.rgb = pq_hlg(color.rgb);
+    return color;
+}
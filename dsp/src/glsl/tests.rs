@@ -0,0 +1,80 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use crate::{
+    gpu::tests::{assert_balanced, generate},
+    tm::ToneMapMethod,
+};
+
+const PQ_HLG_GOLDEN: &str = include_str!("golden/pq_hlg.glsl");
+const PQ_SDR_GOLDEN: &str = include_str!("golden/pq_sdr.glsl");
+
+fn generate_glsl(mut shader: MpvShader) -> String {
+
+    shader.comments.push(String::from("max-cll: 4000"));
+    shader.comments.push(String::from("ref-white: 300"));
+
+    generate(|output| shader.write_glsl(output))
+}
+
+fn assert_golden(actual: &str, golden: &str) {
+    for (index, (actual, golden)) in actual.lines().zip(golden.lines()).enumerate() {
+        assert_eq!(actual, golden, "line {} differs from the golden file", index + 1);
+    }
+    assert_eq!(actual.lines().count(), golden.lines().count());
+}
+
+fn assert_structure(text: &str) {
+    assert_eq!(assert_balanced(text).matches("vec4 hook() {").count(), 1);
+}
+
+#[test]
+fn test_pq_hlg_golden() {
+
+    let mapper = PqHlgMapper::new_by_ref_white(300.0, 4_000.0, ToneMapMethod::MaxRgb);
+    let text = generate_glsl(pq_hlg_shader(&mapper));
+
+    assert_structure(&text);
+    assert_golden(&text, PQ_HLG_GOLDEN);
+}
+
+#[test]
+fn test_pq_sdr_golden() {
+
+    let mapper = PqSdrMapper::new_by_ref_white(300.0, 4_000.0, ToneMapMethod::Rgb);
+    let mut shader = pq_sdr_shader(&mapper);
+
+    shader.hook = MpvHook::Main;
+
+    let text = generate_glsl(shader);
+
+    assert_structure(&text);
+    assert_golden(&text, PQ_SDR_GOLDEN);
+}
+
+#[test]
+fn test_shader_header() {
+
+    let mapper = PqHlgMapper::new(600.0, ToneMapMethod::MaxRgb);
+    let mut shader = pq_hlg_shader(&mapper);
+
+    shader.description = String::from("Line one\nline two");
+
+    let text = generate(|output| shader.write_glsl(output));
+
+    assert!(text.starts_with("// This shader replaces PQ with HLG, which mpv still treats as PQ.\n\
+        // Play it with --target-trc=pq --target-prim=bt.2020 to leave it as is.\n\n\
+        //!HOOK OUTPUT\n//!BIND HOOKED\n//!DESC Line one line two\n"));
+    assert!(text.contains("const int TONE_MAP_METHOD = TONE_MAP_NONE;\n"));
+}
+
@@ -0,0 +1,278 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+pub(crate) mod tests;
+
+use super::{PqPrepper, tm::ToneMapMethod};
+use std::io::{Result, Write};
+
+//
+// GPU Source
+//
+// The DCTL and GLSL writers share the source below, which is written once in GLSL and
+// translated into DCTL by renaming its vector type and built-in functions, marking its
+// functions as device code and its constants as constant memory, and giving its floating point
+// literals an f suffix. Comments are left alone. Only the subset of GLSL used here is
+// understood: scalar math on the components of vec3 values and nothing that relies upon
+// swizzling or vector operators.
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Dialect {
+    Dctl,
+    Glsl,
+}
+
+impl Dialect {
+
+    pub fn write(&self, output: &mut dyn Write, source: &str) -> Result<()> {
+        match self {
+            Dialect::Dctl => write!(output, "{}", dctl(source)),
+            Dialect::Glsl => write!(output, "{}", source),
+        }
+    }
+
+    pub fn write_matrix_function(
+        &self,
+        output: &mut dyn Write,
+        name: &str,
+        matrix: &[[f64; 3]; 3],
+    ) -> Result<()> {
+
+        let mut source = format!("vec3 {}(vec3 c) {{\n    return vec3(\n", name);
+
+        for (index, row) in matrix.iter().enumerate() {
+            source.push_str(&format!("        {} * c.x + {} * c.y + {} * c.z{}\n",
+                float(row[0]),
+                float(row[1]),
+                float(row[2]),
+                if index < 2 { "," } else { "" },
+            ));
+        }
+        source.push_str("    );\n}\n");
+
+        self.write(output, &source)
+    }
+}
+
+pub(crate) fn float(value: f64) -> String {
+    format!("{:?}", value as f32)
+}
+
+fn dctl(source: &str) -> String {
+
+    let mut translated = String::with_capacity(source.len() * 5 / 4);
+
+    for line in source.split_inclusive('\n') {
+
+        let is_function = (line.starts_with("float ") || line.starts_with("vec3 "))
+            && line.trim_end().ends_with('{');
+
+        if is_function {
+            translated.push_str("__DEVICE__ ");
+        }
+
+        let mut rest = line;
+
+        while let Some(c) = rest.chars().next() {
+            let length = if rest.starts_with("//") {
+                translated.push_str(rest);
+                rest.len()
+            } else if c.is_ascii_alphabetic() || c == '_' {
+                let length = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                let is_call = rest[length..].starts_with('(');
+                translated.push_str(match &rest[..length] {
+                    "const" => "__CONSTANT__",
+                    "vec3" if is_call => "make_float3",
+                    "vec3" => "float3",
+                    "pow" => "_powf",
+                    "max" => "_fmaxf",
+                    "cbrt" => "_cbrtf",
+                    word => word,
+                });
+                length
+            } else if c.is_ascii_digit() {
+                let bytes = rest.as_bytes();
+                let mut length = 0;
+                while length < bytes.len() && match bytes[length] {
+                    b'0'..=b'9' | b'.' | b'e' => true,
+                    b'-' => bytes[length - 1] == b'e',
+                    _ => false,
+                } {
+                    length += 1;
+                }
+                translated.push_str(&rest[..length]);
+                if rest[..length].contains(['.', 'e']) {
+                    translated.push('f');
+                }
+                length
+            } else {
+                translated.push(c);
+                c.len_utf8()
+            };
+            rest = &rest[length..];
+        }
+    }
+
+    translated
+}
+
+//
+// Parameters
+//
+
+pub(crate) struct ShaderParameters {
+    factor: f64,
+    tone_map: Option<ToneMapMethod>,
+    peak: f64,
+    target: f64,
+    lwp: f64,
+    ml: f64,
+    ks: f64,
+}
+
+impl ShaderParameters {
+
+    pub fn new(prepper: &PqPrepper) -> Self {
+
+        let tm = &prepper.tm;
+
+        Self {
+            factor: prepper.factor,
+            tone_map: if tm.peak > tm.target {
+                Some(tm.method())
+            } else {
+                None
+            },
+            peak: tm.peak,
+            target: tm.target,
+            lwp: tm.lwp,
+            ml: tm.ml,
+            ks: tm.ks,
+        }
+    }
+
+    pub fn write(&self, output: &mut dyn Write, dialect: Dialect) -> Result<()> {
+        dialect.write(output, &format!("\
+// Oklab exposure scaling factor
+const float EXPOSURE_FACTOR = {};
+
+// BT.2408 tone mapping (1.0 = 10,000 nits)
+const int TONE_MAP_NONE = 0;
+const int TONE_MAP_RGB = 1;
+const int TONE_MAP_MAX_RGB = 2;
+const int TONE_MAP_METHOD = {};
+const float TM_PEAK = {};
+const float TM_TARGET = {};
+const float TM_LWP = {};
+const float TM_ML = {};
+const float TM_KS = {};
+",
+            float(self.factor),
+            match self.tone_map {
+                None => "TONE_MAP_NONE",
+                Some(ToneMapMethod::Rgb) => "TONE_MAP_RGB",
+                Some(ToneMapMethod::MaxRgb) => "TONE_MAP_MAX_RGB",
+            },
+            float(self.peak),
+            float(self.target),
+            float(self.lwp),
+            float(self.ml),
+            float(self.ks),
+        ))
+    }
+}
+
+//
+// Shared Functions
+//
+// The cube root is a built-in function of DCTL, but GLSL writers must define it themselves
+// before OKLAB_FUNCTIONS.
+//
+
+pub(crate) const PQ_FUNCTIONS: &str = "\
+float pq_eotf(float e) {
+    float p = pow(e, 0.012683313515655966);
+    return pow(max(p - 0.8359375, 0.0) / (18.8515625 - 18.6875 * p), 6.277394636015326);
+}
+
+float pq_ieotf(float o) {
+    float p = pow(o, 0.1593017578125);
+    return pow((0.8359375 + 18.8515625 * p) / (1.0 + 18.6875 * p), 78.84375);
+}
+";
+
+pub(crate) const OKLAB_FUNCTIONS: &str = "\
+vec3 xyz_to_oklab(vec3 c) {
+    float l = cbrt(0.8189330101 * c.x + 0.3618667424 * c.y - 0.1288597137 * c.z);
+    float m = cbrt(0.0329845436 * c.x + 0.9293118715 * c.y + 0.0361456387 * c.z);
+    float s = cbrt(0.0482003018 * c.x + 0.2643662691 * c.y + 0.6338517070 * c.z);
+    return vec3(
+        0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+        1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+        0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s
+    );
+}
+
+vec3 oklab_to_xyz(vec3 c) {
+    float l = 0.99999999845051981432 * c.x
+        + 0.39633779217376785678 * c.y
+        + 0.21580375806075880339 * c.z;
+    float m = 1.0000000088817607767 * c.x
+        - 0.1055613423236563494 * c.y
+        - 0.063854174771705903402 * c.z;
+    float s = 1.0000000546724109177 * c.x
+        - 0.089484182094965759684 * c.y
+        - 1.2914855378640917399 * c.z;
+    l = l * l * l;
+    m = m * m * m;
+    s = s * s * s;
+    return vec3(
+        1.227013851103521026 * l - 0.5577999806518222383 * m + 0.28125614896646780758 * s,
+        -0.040580178423280593977 * l + 1.1122568696168301049 * m - 0.071676678665601200577 * s,
+        -0.076381284505706892869 * l - 0.42148197841801273055 * m + 1.5861632204407947575 * s
+    );
+}
+";
+
+pub(crate) const TONE_MAP_FUNCTIONS: &str = "\
+float eetf(float e) {
+    float e1 = e / TM_LWP;
+    if (TM_KS < e1 && e1 <= 1.0) {
+        float t = (e1 - TM_KS) / (1.0 - TM_KS);
+        float t2 = t * t;
+        float t3 = t2 * t;
+        e1 = (2.0 * t3 - 3.0 * t2 + 1.0) * TM_KS
+            + (t3 - 2.0 * t2 + t) * (1.0 - TM_KS)
+            + (-2.0 * t3 + 3.0 * t2) * TM_ML;
+    }
+    return e1 * TM_LWP;
+}
+
+float tone_map_channel(float o) {
+    return o > 0.0 ? pq_eotf(eetf(pq_ieotf(o))) : 0.0;
+}
+
+vec3 tone_map_rgb(vec3 rgb) {
+    return vec3(tone_map_channel(rgb.x), tone_map_channel(rgb.y), tone_map_channel(rgb.z));
+}
+
+vec3 tone_map_max_rgb(vec3 rgb) {
+    float m1 = max(rgb.x, max(rgb.y, rgb.z));
+    if (m1 <= 0.0) {
+        return rgb;
+    }
+    float factor = pq_eotf(eetf(pq_ieotf(m1))) / m1;
+    return vec3(rgb.x * factor, rgb.y * factor, rgb.z * factor);
+}
+";
@@ -0,0 +1,91 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+
+// Runs a DCTL or GLSL writer to completion and returns what it wrote.
+pub(crate) fn generate<F>(write: F) -> String
+    where F: FnOnce(&mut dyn Write) -> Result<()> {
+
+    let mut output = Vec::new();
+
+    write(&mut output).unwrap();
+
+    String::from_utf8(output).unwrap()
+}
+
+// Asserts that every parenthesis and brace outside of a comment is balanced, returning the
+// source with its comments removed.
+pub(crate) fn assert_balanced(text: &str) -> String {
+
+    let code = text
+        .lines()
+        .map(|line| line.split("//").next().unwrap())
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let mut stack = Vec::new();
+
+    for c in code.chars() {
+        match c {
+            '(' | '{' => stack.push(c),
+            ')' => assert_eq!(stack.pop(), Some('(')),
+            '}' => assert_eq!(stack.pop(), Some('{')),
+            _ => {}
+        }
+    }
+    assert!(stack.is_empty());
+
+    code
+}
+
+#[test]
+fn test_float() {
+    assert_eq!(float(1.0), "1.0");
+    assert_eq!(float(0.1), "0.1");
+    assert_eq!(float(-0.25), "-0.25");
+}
+
+#[test]
+fn test_dctl_translation() {
+    assert_eq!(
+        dctl("float f(vec3 c) {\n    return max(pow(c.x, 2.0), 1e-5) * t2 - vec3(0).y;\n}\n"),
+        "__DEVICE__ float f(float3 c) {\n    \
+            return _fmaxf(_powf(c.x, 2.0f), 1e-5f) * t2 - make_float3(0).y;\n}\n",
+    );
+    assert_eq!(
+        dctl("const int A = 2; // vec3 1.0\n"),
+        "__CONSTANT__ int A = 2; // vec3 1.0\n",
+    );
+    assert_eq!(dctl("    float l = cbrt(-0.5 * c.z);"), "    float l = _cbrtf(-0.5f * c.z);");
+}
+
+#[test]
+fn test_glsl_unchanged() {
+
+    let text = generate(|output| Dialect::Glsl.write(output, PQ_FUNCTIONS));
+
+    assert_eq!(text, PQ_FUNCTIONS);
+}
+
+#[test]
+fn test_matrix_function() {
+
+    let matrix = [[1.0, 0.5, -0.25], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    let glsl = generate(|output| Dialect::Glsl.write_matrix_function(output, "m", &matrix));
+    let dctl = generate(|output| Dialect::Dctl.write_matrix_function(output, "m", &matrix));
+
+    assert!(glsl.starts_with("vec3 m(vec3 c) {\n    return vec3(\n"));
+    assert!(glsl.contains("        1.0 * c.x + 0.5 * c.y + -0.25 * c.z,\n"));
+    assert!(dctl.starts_with("__DEVICE__ float3 m(float3 c) {\n    return make_float3(\n"));
+    assert!(dctl.contains("        1.0f * c.x + 0.5f * c.y + -0.25f * c.z,\n"));
+    assert_balanced(&dctl);
+}
@@ -11,12 +11,23 @@
 #[cfg(test)]
 mod tests;
 
+pub mod clf;
+pub mod dctl;
+pub mod frame;
+pub mod glsl;
+mod gpu;
+pub mod lut;
+pub mod ocio;
 pub mod pixel;
+pub mod range;
+pub mod stats;
 pub mod tf;
 pub mod tm;
+pub mod y4m;
+pub mod yuv;
 
 use pixel::RgbPixel;
-use tf::{hlg_eotf, hlg_iootf, hlg_oetf, pq_eotf, pq_ieotf, sdr_o_to_e};
+use tf::{hlg_eotf, hlg_iootf, hlg_oetf, pq_eotf, pq_ieotf, sdr_e_to_o, sdr_o_to_e};
 use tm::{sdn_tone_map, Bt2408ToneMapper, ToneMapMethod};
 
 //
@@ -47,7 +58,7 @@ impl PqHlgMapper {
         tm_method: ToneMapMethod,
     ) -> Self {
 
-        let factor = scale_nits_factor(ref_white, 203.0);
+        let factor = ref_white_factor(ref_white);
 
         Self::new_by_factor(factor, max_cll, tm_method)
     }
@@ -111,7 +122,7 @@ impl PqSdrMapper {
 
     pub fn new_by_ref_white(ref_white: f64, max_cll: f64, tm_method: ToneMapMethod) -> Self {
 
-        let factor = scale_nits_factor(ref_white, 203.0);
+        let factor = ref_white_factor(ref_white);
 
         Self::new_by_factor(factor, max_cll, tm_method)
     }
@@ -156,6 +167,36 @@ impl Mapper for PqSdrMapper {
     }
 }
 
+//
+// SDR -> SDR Monochrome Mapper
+//
+
+pub struct SdrMonoMapper;
+
+impl SdrMonoMapper {
+
+    pub fn map(&self, input: RgbPixel) -> RgbPixel {
+
+        let mut pixel = input;
+
+        // SDR GAMMA -> SDR LINEAR
+        pixel = pixel.with_each_channel(sdr_e_to_o);
+
+        // OKLAB MONOCHROME
+        pixel = pixel.bt709_to_xyz().to_oklab().monochrome().to_xyz().to_rgb_bt709();
+
+        // SDR LINEAR -> SDR GAMMA
+        pixel.with_each_channel(sdr_o_to_e)
+    }
+}
+
+impl Mapper for SdrMonoMapper {
+
+    fn map(&self, input: RgbPixel) -> RgbPixel {
+        self.map(input)
+    }
+}
+
 //
 // HLG to PQ Mapper
 //
@@ -224,7 +265,7 @@ impl PqPrepper {
         let mut pixel = input;
 
         // PQ SIGNAL -> DISPLAY LINEAR
-        pixel = pixel.with_each_channel(|x| pq_eotf(x)).clamp(0.0, 1.0);
+        pixel = pixel.with_each_channel(pq_eotf).clamp(0.0, 1.0);
 
         // REFERENCE WHITE ADJUSTMENT
         pixel = (pixel.bt2020_to_xyz().to_oklab() * self.factor).to_xyz().to_rgb_bt2020();
@@ -245,6 +286,12 @@ fn lum_scale_to_factor(lum_scale: f64) -> f64 {
 // Shared
 //
 
+// Returns the exposure factor that brings the given reference white level to the 203 nits of
+// BT.2408, as passed to new_by_factor.
+pub fn ref_white_factor(ref_white: f64) -> f64 {
+    scale_nits_factor(ref_white, 203.0)
+}
+
 fn scale_nits_factor(from: f64, to: f64) -> f64 {
 
     let l_from = RgbPixel::new_y(from / 10_000.0).bt2020_to_xyz().to_oklab().l;
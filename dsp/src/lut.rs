@@ -0,0 +1,445 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+pub mod accuracy;
+pub mod cube;
+pub mod hald;
+pub mod spi;
+pub mod three_dl;
+
+use super::{Mapper, RgbPixel};
+
+const SHAPER_FLOOR: f64 = 6.0;
+
+//
+// 1D LUT
+//
+// As with Lut3d, the domain is only changed through set_domain so that it is never empty.
+//
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut1d {
+    pub title: Option<String>,
+    pub comments: Vec<String>,
+    domain_min: RgbPixel,
+    domain_max: RgbPixel,
+    table: Vec<RgbPixel>,
+}
+
+impl Lut1d {
+
+    pub fn new(table: Vec<RgbPixel>) -> Self {
+
+        assert!(table.len() >= 2, "1D LUT size must be at least 2");
+
+        Self {
+            title: None,
+            comments: Vec::new(),
+            domain_min: RgbPixel::new_y(0.0),
+            domain_max: RgbPixel::new_y(1.0),
+            table,
+        }
+    }
+
+    pub fn from_fn<F>(size: usize, f: F) -> Self
+        where F: Fn(f64) -> f64 {
+        Self::from_fn_with_domain(size, 0.0, 1.0, f)
+    }
+
+    pub fn from_fn_with_domain<F>(size: usize, domain_min: f64, domain_max: f64, f: F) -> Self
+        where F: Fn(f64) -> f64 {
+
+        assert!(size >= 2, "1D LUT size must be at least 2");
+        assert!(domain_min < domain_max, "1D LUT domain minimum must be below its maximum");
+
+        let max = (size - 1) as f64;
+        let mut lut = Self::new((0..size)
+            .map(|i| RgbPixel::new_y(f(domain_min + (domain_max - domain_min) * i as f64 / max)))
+            .collect());
+
+        lut.set_domain(RgbPixel::new_y(domain_min), RgbPixel::new_y(domain_max));
+
+        lut
+    }
+
+    pub fn domain_min(&self) -> RgbPixel {
+        self.domain_min
+    }
+
+    pub fn domain_max(&self) -> RgbPixel {
+        self.domain_max
+    }
+
+    pub fn set_domain(&mut self, min: RgbPixel, max: RgbPixel) {
+        assert!(is_domain(min, max), "1D LUT domain minimum must be below its maximum");
+        self.domain_min = min;
+        self.domain_max = max;
+    }
+
+    pub fn size(&self) -> usize {
+        self.table.len()
+    }
+
+    pub fn table(&self) -> &[RgbPixel] {
+        &self.table
+    }
+
+    pub fn get(&self, i: usize) -> RgbPixel {
+        self.table[i]
+    }
+
+    pub fn linear(&self, input: RgbPixel) -> RgbPixel {
+        RgbPixel {
+            red: self.linear_channel(input.red, |pixel| pixel.red,
+                self.domain_min.red, self.domain_max.red),
+            green: self.linear_channel(input.green, |pixel| pixel.green,
+                self.domain_min.green, self.domain_max.green),
+            blue: self.linear_channel(input.blue, |pixel| pixel.blue,
+                self.domain_min.blue, self.domain_max.blue),
+        }
+    }
+
+    fn linear_channel<F>(&self, x: f64, channel: F, min: f64, max: f64) -> f64
+        where F: Fn(&RgbPixel) -> f64 {
+
+        let last = self.table.len() - 1;
+        let s = ((x - min) / (max - min) * last as f64).clamp(0.0, last as f64);
+        let prev = s as usize;
+        let next = (prev + 1).min(last);
+        let a = channel(&self.table[prev]);
+        let b = channel(&self.table[next]);
+
+        a + (b - a) * (s - prev as f64)
+    }
+}
+
+impl Mapper for Lut1d {
+
+    fn map(&self, input: RgbPixel) -> RgbPixel {
+        self.linear(input)
+    }
+}
+
+//
+// 3D LUT
+//
+// Each channel of the input is scaled from the domain onto the table, which divides by the
+// width of the domain. It is therefore only changed through set_domain, which insists that
+// every minimum is below its maximum.
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InterpolationMethod {
+    Trilinear,
+    Tetrahedral,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lut3d {
+    pub title: Option<String>,
+    pub comments: Vec<String>,
+    domain_min: RgbPixel,
+    domain_max: RgbPixel,
+    pub interpolation: InterpolationMethod,
+    size: usize,
+    table: Vec<RgbPixel>,
+}
+
+impl Lut3d {
+
+    pub fn new(size: usize, table: Vec<RgbPixel>) -> Self {
+
+        assert!(size >= 2, "3D LUT size must be at least 2");
+        assert_eq!(table.len(), size * size * size, "3D LUT table does not match its size");
+
+        Self {
+            title: None,
+            comments: Vec::new(),
+            domain_min: RgbPixel::new_y(0.0),
+            domain_max: RgbPixel::new_y(1.0),
+            interpolation: InterpolationMethod::Tetrahedral,
+            size,
+            table,
+        }
+    }
+
+    pub fn from_mapper<M>(mapper: &M, size: usize) -> Self
+        where M: Mapper + ?Sized {
+        Self::from_fn(size, |pixel| mapper.map(pixel))
+    }
+
+    pub fn domain_min(&self) -> RgbPixel {
+        self.domain_min
+    }
+
+    pub fn domain_max(&self) -> RgbPixel {
+        self.domain_max
+    }
+
+    pub fn set_domain(&mut self, min: RgbPixel, max: RgbPixel) {
+        assert!(is_domain(min, max), "3D LUT domain minimum must be below its maximum");
+        self.domain_min = min;
+        self.domain_max = max;
+    }
+
+    pub fn from_fn<F>(size: usize, f: F) -> Self
+        where F: Fn(RgbPixel) -> RgbPixel {
+
+        assert!(size >= 2, "3D LUT size must be at least 2");
+
+        let mut table = Vec::with_capacity(size * size * size);
+        let max = (size - 1) as f64;
+
+        // The red index moves fastest, as required by the Cube format.
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    table.push(f(RgbPixel {
+                        red: r as f64 / max,
+                        green: g as f64 / max,
+                        blue: b as f64 / max,
+                    }));
+                }
+            }
+        }
+
+        Self::new(size, table)
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn table(&self) -> &[RgbPixel] {
+        &self.table
+    }
+
+    pub fn get(&self, r: usize, g: usize, b: usize) -> RgbPixel {
+        self.table[(b * self.size + g) * self.size + r]
+    }
+
+    pub fn trilinear(&self, input: RgbPixel) -> RgbPixel {
+
+        let (prev, next, d) = self.locate(input);
+
+        let c000 = self.get(prev.0, prev.1, prev.2);
+        let c001 = self.get(prev.0, prev.1, next.2);
+        let c010 = self.get(prev.0, next.1, prev.2);
+        let c011 = self.get(prev.0, next.1, next.2);
+        let c100 = self.get(next.0, prev.1, prev.2);
+        let c101 = self.get(next.0, prev.1, next.2);
+        let c110 = self.get(next.0, next.1, prev.2);
+        let c111 = self.get(next.0, next.1, next.2);
+
+        let c00 = lerp(c000, c100, d.red);
+        let c10 = lerp(c010, c110, d.red);
+        let c01 = lerp(c001, c101, d.red);
+        let c11 = lerp(c011, c111, d.red);
+        let c0 = lerp(c00, c10, d.green);
+        let c1 = lerp(c01, c11, d.green);
+
+        lerp(c0, c1, d.blue)
+    }
+
+    pub fn tetrahedral(&self, input: RgbPixel) -> RgbPixel {
+
+        //
+        // This follows the same tetrahedron selection as the lut3d filter in FFmpeg so that
+        // evaluating a LUT here matches what FFmpeg produces when applying it.
+        //
+
+        let (prev, next, d) = self.locate(input);
+
+        let c000 = self.get(prev.0, prev.1, prev.2);
+        let c111 = self.get(next.0, next.1, next.2);
+
+        if d.red > d.green {
+            if d.green > d.blue {
+                let c100 = self.get(next.0, prev.1, prev.2);
+                let c110 = self.get(next.0, next.1, prev.2);
+                c000 * (1.0 - d.red)
+                    + c100 * (d.red - d.green)
+                    + c110 * (d.green - d.blue)
+                    + c111 * d.blue
+            } else if d.red > d.blue {
+                let c100 = self.get(next.0, prev.1, prev.2);
+                let c101 = self.get(next.0, prev.1, next.2);
+                c000 * (1.0 - d.red)
+                    + c100 * (d.red - d.blue)
+                    + c101 * (d.blue - d.green)
+                    + c111 * d.green
+            } else {
+                let c001 = self.get(prev.0, prev.1, next.2);
+                let c101 = self.get(next.0, prev.1, next.2);
+                c000 * (1.0 - d.blue)
+                    + c001 * (d.blue - d.red)
+                    + c101 * (d.red - d.green)
+                    + c111 * d.green
+            }
+        } else if d.blue > d.green {
+            let c001 = self.get(prev.0, prev.1, next.2);
+            let c011 = self.get(prev.0, next.1, next.2);
+            c000 * (1.0 - d.blue)
+                + c001 * (d.blue - d.green)
+                + c011 * (d.green - d.red)
+                + c111 * d.red
+        } else if d.blue > d.red {
+            let c010 = self.get(prev.0, next.1, prev.2);
+            let c011 = self.get(prev.0, next.1, next.2);
+            c000 * (1.0 - d.green)
+                + c010 * (d.green - d.blue)
+                + c011 * (d.blue - d.red)
+                + c111 * d.red
+        } else {
+            let c010 = self.get(prev.0, next.1, prev.2);
+            let c110 = self.get(next.0, next.1, prev.2);
+            c000 * (1.0 - d.green)
+                + c010 * (d.green - d.red)
+                + c110 * (d.red - d.blue)
+                + c111 * d.blue
+        }
+    }
+
+    fn locate(
+        &self,
+        input: RgbPixel,
+    ) -> ((usize, usize, usize), (usize, usize, usize), RgbPixel) {
+
+        let max = (self.size - 1) as f64;
+        let scale = |x: f64, min: f64, max_domain: f64| {
+            ((x - min) / (max_domain - min) * max).clamp(0.0, max)
+        };
+        let s = RgbPixel {
+            red: scale(input.red, self.domain_min.red, self.domain_max.red),
+            green: scale(input.green, self.domain_min.green, self.domain_max.green),
+            blue: scale(input.blue, self.domain_min.blue, self.domain_max.blue),
+        };
+        let prev = (s.red as usize, s.green as usize, s.blue as usize);
+        let next = (
+            (prev.0 + 1).min(self.size - 1),
+            (prev.1 + 1).min(self.size - 1),
+            (prev.2 + 1).min(self.size - 1),
+        );
+        let d = RgbPixel {
+            red: s.red - prev.0 as f64,
+            green: s.green - prev.1 as f64,
+            blue: s.blue - prev.2 as f64,
+        };
+
+        (prev, next, d)
+    }
+}
+
+impl Mapper for Lut3d {
+
+    fn map(&self, input: RgbPixel) -> RgbPixel {
+        match self.interpolation {
+            InterpolationMethod::Trilinear => {
+                self.trilinear(input)
+            }
+            InterpolationMethod::Tetrahedral => {
+                self.tetrahedral(input)
+            }
+        }
+    }
+}
+
+//
+// Shaped LUT
+//
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapedLut {
+    pub shaper: Lut1d,
+    pub lut_3d: Lut3d,
+}
+
+impl ShapedLut {
+
+    pub fn from_mapper<M>(mapper: &M, shaper_size: usize, size: usize) -> Self
+        where M: Mapper + ?Sized {
+
+        //
+        // A uniform lattice spends as many points where the mapper is nearly linear as it does
+        // where it bends sharply. The shaper redistributes the lattice along the neutral axis
+        // so that point density follows the square root of the mapper's curvature there, which
+        // is the density that minimizes the error of piecewise linear interpolation. A floor of
+        // several times the mean curvature density keeps nearly linear regions from being
+        // starved, since off-axis colors still depend on them.
+        //
+
+        assert!(shaper_size >= 3, "Shaper size must be at least 3");
+
+        let last = (shaper_size - 1) as f64;
+        let response = (0..shaper_size)
+            .map(|i| mapper.map(RgbPixel::new_y(i as f64 / last)))
+            .collect::<Vec<RgbPixel>>();
+        let mut density = vec![0.0; shaper_size];
+
+        for i in 1..(shaper_size - 1) {
+
+            let difference = response[i - 1] + response[i + 1] - response[i] * 2.0;
+            let curvature = difference.red.abs()
+                .max(difference.green.abs())
+                .max(difference.blue.abs());
+
+            // Anything this small is rounding noise from a mapper that is linear here.
+            if curvature > 1e-12 {
+                density[i] = (curvature * last * last).sqrt();
+            }
+        }
+        density[0] = density[1];
+        density[shaper_size - 1] = density[shaper_size - 2];
+
+        let floor = density.iter().sum::<f64>() / shaper_size as f64 * SHAPER_FLOOR;
+        let mut positions = vec![0.0; shaper_size];
+
+        for i in 1..shaper_size {
+            positions[i] = positions[i - 1]
+                + (density[i - 1] + density[i]) / 2.0
+                + floor.max(f64::MIN_POSITIVE);
+        }
+
+        let total = positions[shaper_size - 1];
+
+        for position in positions.iter_mut() {
+            *position /= total;
+        }
+
+        let shaper = Lut1d::new(positions.iter().map(|x| RgbPixel::new_y(*x)).collect());
+        let inverse = |u: f64| {
+            let next = positions.partition_point(|x| *x < u).clamp(1, shaper_size - 1);
+            let prev = next - 1;
+            let t = (u - positions[prev]) / (positions[next] - positions[prev]);
+            (prev as f64 + t) / last
+        };
+        let lut_3d = Lut3d::from_fn(size, |pixel| mapper.map(pixel.with_each_channel(inverse)));
+
+        Self { shaper, lut_3d }
+    }
+}
+
+impl Mapper for ShapedLut {
+
+    fn map(&self, input: RgbPixel) -> RgbPixel {
+        self.lut_3d.map(self.shaper.map(input))
+    }
+}
+
+fn lerp(a: RgbPixel, b: RgbPixel, t: f64) -> RgbPixel {
+    a + (b - a) * t
+}
+
+fn is_domain(min: RgbPixel, max: RgbPixel) -> bool {
+    min.red < max.red && min.green < max.green && min.blue < max.blue
+}
@@ -0,0 +1,215 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{InterpolationMethod, Lut3d, Mapper, RgbPixel, ShapedLut};
+use crate::tf::{hlg_eotf, pq_eotf, sdr_e_to_o};
+use std::fmt::{self, Display, Formatter};
+
+pub const BAND_LIMITS: [f64; 7] = [0.0, 1.0, 10.0, 100.0, 203.0, 1_000.0, 10_000.0];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputSignal {
+    Pq,
+    Hlg,
+    Sdr,
+}
+
+impl OutputSignal {
+
+    pub fn to_display_linear(&self, pixel: RgbPixel) -> RgbPixel {
+
+        //
+        // Display light is normalized such that 1.0 represents 10,000 nits. HLG is decoded for
+        // a 1,000 nit nominal peak display and SDR for a 100 nit one.
+        //
+
+        match self {
+            OutputSignal::Pq => {
+                pixel.with_each_channel(pq_eotf)
+            }
+            OutputSignal::Hlg => {
+                hlg_eotf(pixel.clamp(0.0, 1.0), 1.2) * 0.1
+            }
+            OutputSignal::Sdr => {
+                pixel.with_each_channel(sdr_e_to_o) * 0.01
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ErrorStats {
+    pub max: f64,
+    pub mean: f64,
+    pub p99: f64,
+}
+
+impl ErrorStats {
+
+    fn new(errors: &mut [f64]) -> Self {
+
+        if errors.is_empty() {
+            return Self { max: 0.0, mean: 0.0, p99: 0.0 }
+        }
+
+        errors.sort_by(|a, b| a.total_cmp(b));
+
+        let count = errors.len();
+        let rank = ((count as f64 * 0.99).ceil() as usize).clamp(1, count);
+
+        Self {
+            max: errors[count - 1],
+            mean: errors.iter().sum::<f64>() / count as f64,
+            p99: errors[rank - 1],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BandReport {
+    pub min_nits: f64,
+    pub max_nits: f64,
+    pub samples: usize,
+    pub delta_e_itp: ErrorStats,
+    pub nits: ErrorStats,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccuracyReport {
+    pub size: usize,
+    pub shaper_size: Option<usize>,
+    pub interpolation: InterpolationMethod,
+    pub overall: BandReport,
+    pub bands: Vec<BandReport>,
+}
+
+pub fn accuracy_report<M>(
+    mapper: &M,
+    size: usize,
+    shaper_size: Option<usize>,
+    interpolation: InterpolationMethod,
+    signal: OutputSignal,
+    samples: usize,
+) -> AccuracyReport
+    where M: Mapper + ?Sized {
+
+    let lut: Box<dyn Mapper> = match shaper_size {
+        Some(shaper_size) => {
+            let mut lut = ShapedLut::from_mapper(mapper, shaper_size, size);
+            lut.lut_3d.interpolation = interpolation;
+            Box::new(lut)
+        }
+        None => {
+            let mut lut = Lut3d::from_mapper(mapper, size);
+            lut.interpolation = interpolation;
+            Box::new(lut)
+        }
+    };
+    let band_count = BAND_LIMITS.len() - 1;
+    let mut delta_e_itps = vec![Vec::new(); band_count];
+    let mut nits = vec![Vec::new(); band_count];
+
+    //
+    // Samples sit at the centers of a regular grid so that, for any grid size that differs from
+    // the LUT size, nearly all of them fall between lattice points.
+    //
+
+    for b in 0..samples {
+        for g in 0..samples {
+            for r in 0..samples {
+
+                let input = RgbPixel {
+                    red: (r as f64 + 0.5) / samples as f64,
+                    green: (g as f64 + 0.5) / samples as f64,
+                    blue: (b as f64 + 0.5) / samples as f64,
+                };
+                let expected = signal.to_display_linear(mapper.map(input));
+                let actual = signal.to_display_linear(lut.map(input));
+                let expected_nits = expected.y_bt2020() * 10_000.0;
+                let actual_nits = actual.y_bt2020() * 10_000.0;
+                let band = BAND_LIMITS[1..band_count]
+                    .iter()
+                    .take_while(|limit| expected_nits >= **limit)
+                    .count();
+
+                delta_e_itps[band].push(
+                    expected.bt2020_to_ictcp().delta_e_itp(&actual.bt2020_to_ictcp())
+                );
+                nits[band].push((expected_nits - actual_nits).abs());
+            }
+        }
+    }
+
+    let mut all_delta_e_itps = delta_e_itps.concat();
+    let mut all_nits = nits.concat();
+    let overall = BandReport {
+        min_nits: BAND_LIMITS[0],
+        max_nits: BAND_LIMITS[band_count],
+        samples: all_nits.len(),
+        delta_e_itp: ErrorStats::new(&mut all_delta_e_itps),
+        nits: ErrorStats::new(&mut all_nits),
+    };
+    let bands = delta_e_itps
+        .iter_mut()
+        .zip(nits.iter_mut())
+        .enumerate()
+        .map(|(index, (delta_e_itps, nits))| BandReport {
+            min_nits: BAND_LIMITS[index],
+            max_nits: BAND_LIMITS[index + 1],
+            samples: nits.len(),
+            delta_e_itp: ErrorStats::new(delta_e_itps),
+            nits: ErrorStats::new(nits),
+        })
+        .collect();
+
+    AccuracyReport { size, shaper_size, interpolation, overall, bands }
+}
+
+impl Display for AccuracyReport {
+
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+
+        writeln!(f, "LUT size: {}", self.size)?;
+        if let Some(shaper_size) = self.shaper_size {
+            writeln!(f, "Shaper size: {}", shaper_size)?;
+        }
+        writeln!(f, "Interpolation: {}", match self.interpolation {
+            InterpolationMethod::Trilinear => "trilinear",
+            InterpolationMethod::Tetrahedral => "tetrahedral",
+        })?;
+        writeln!(f)?;
+        writeln!(f, "{:>17} {:>9} {:>33} {:>33}", "", "", "Delta E ITP", "Nits")?;
+        writeln!(f, "{:>17} {:>9} {:>10} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "Band (nits)", "Samples", "Max", "Mean", "P99", "Max", "Mean", "P99")?;
+
+        for band in self.bands.iter() {
+            write_band(f, &format!("{}-{}", band.min_nits, band.max_nits), band)?;
+        }
+        write_band(f, "All", &self.overall)?;
+
+        Ok(())
+    }
+}
+
+fn write_band(f: &mut Formatter, label: &str, band: &BandReport) -> fmt::Result {
+    writeln!(f, "{:>17} {:>9} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>10.4}",
+        label,
+        band.samples,
+        band.delta_e_itp.max,
+        band.delta_e_itp.mean,
+        band.delta_e_itp.p99,
+        band.nits.max,
+        band.nits.mean,
+        band.nits.p99,
+    )
+}
@@ -0,0 +1,81 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use crate::{HlgPqMapper, PqHlgMapper, tm::ToneMapMethod};
+use assert_approx_eq::assert_approx_eq;
+use more_asserts::{assert_gt, assert_lt};
+
+const DIFF: f64 = 0.0000000001;
+
+#[test]
+fn test_accuracy_report_linear_mapper() {
+
+    let mapper = Lut3d::from_fn(2, |pixel| pixel * 0.5);
+    let report = accuracy_report(
+        &mapper, 5, None, InterpolationMethod::Tetrahedral, OutputSignal::Pq, 10,
+    );
+
+    assert_eq!(report.overall.samples, 1_000);
+    assert_eq!(report.bands.iter().map(|band| band.samples).sum::<usize>(), 1_000);
+    assert_approx_eq!(report.overall.delta_e_itp.max, 0.0, DIFF);
+    assert_approx_eq!(report.overall.nits.max, 0.0, DIFF);
+}
+
+#[test]
+fn test_accuracy_report_bands() {
+
+    let report = accuracy_report(
+        &HlgPqMapper::new(1_000.0), 9, None, InterpolationMethod::Trilinear, OutputSignal::Pq, 8,
+    );
+
+    assert_eq!(report.bands.len(), BAND_LIMITS.len() - 1);
+    assert_eq!(report.bands[0].min_nits, 0.0);
+    assert_eq!(report.bands[4].min_nits, 203.0);
+    assert_eq!(report.bands[4].max_nits, 1_000.0);
+
+    // HLG at a 1,000 nit peak never reaches the top band.
+    assert_eq!(report.bands[5].samples, 0);
+
+    for band in report.bands.iter().filter(|band| band.samples > 0) {
+        assert_lt!(band.delta_e_itp.mean, band.delta_e_itp.max + DIFF);
+        assert_lt!(band.delta_e_itp.p99, band.delta_e_itp.max + DIFF);
+        assert_lt!(band.delta_e_itp.max, report.overall.delta_e_itp.max + DIFF);
+    }
+}
+
+#[test]
+fn test_accuracy_report_size() {
+
+    let mapper = PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb);
+    let small = accuracy_report(
+        &mapper, 9, None, InterpolationMethod::Tetrahedral, OutputSignal::Hlg, 12,
+    );
+    let large = accuracy_report(
+        &mapper, 33, None, InterpolationMethod::Tetrahedral, OutputSignal::Hlg, 12,
+    );
+
+    assert_gt!(small.overall.delta_e_itp.mean, large.overall.delta_e_itp.mean);
+    assert_gt!(small.overall.nits.mean, large.overall.nits.mean);
+}
+
+#[test]
+fn test_output_signal_reference_white() {
+
+    let pq = OutputSignal::Pq.to_display_linear(RgbPixel::new_y(0.5806888810416109));
+    let hlg = OutputSignal::Hlg.to_display_linear(RgbPixel::new_y(0.75));
+    let sdr = OutputSignal::Sdr.to_display_linear(RgbPixel::new_y(1.0));
+
+    assert_approx_eq!(pq.y_bt2020() * 10_000.0, 203.0, 0.001);
+    assert_approx_eq!(hlg.y_bt2020() * 10_000.0, 203.0, 0.5);
+    assert_approx_eq!(sdr.y_bt2020() * 10_000.0, 100.0, DIFF);
+}
@@ -0,0 +1,390 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{Lut1d, Lut3d, RgbPixel, ShapedLut, is_domain};
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, BufRead, Write},
+    ops::RangeInclusive,
+};
+
+pub const LUT_1D_SIZE_RANGE: RangeInclusive<usize> = 2..=65_536;
+pub const LUT_3D_SIZE_RANGE: RangeInclusive<usize> = 2..=256;
+
+//
+// Cube
+//
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cube {
+    pub lut_1d: Option<Lut1d>,
+    pub lut_3d: Option<Lut3d>,
+}
+
+#[derive(Debug)]
+pub enum CubeError {
+    Io(io::Error),
+    UnknownKeyword { line: usize, keyword: String },
+    DuplicateKeyword { line: usize, keyword: String },
+    KeywordAfterData { line: usize, keyword: String },
+    Malformed { line: usize },
+    SizeOutOfRange { line: usize, size: usize },
+    InvalidDomain { line: usize },
+    DataBeforeSize { line: usize },
+    MissingSize,
+    TooManyEntries { line: usize, expected: usize },
+    TooFewEntries { expected: usize, found: usize },
+}
+
+impl Display for CubeError {
+
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            CubeError::Io(err) => {
+                write!(f, "I/O error: {}", err)
+            }
+            CubeError::UnknownKeyword { line, keyword } => {
+                write!(f, "line {}: unknown keyword {}", line, keyword)
+            }
+            CubeError::DuplicateKeyword { line, keyword } => {
+                write!(f, "line {}: {} is defined more than once", line, keyword)
+            }
+            CubeError::KeywordAfterData { line, keyword } => {
+                write!(f, "line {}: {} appears after the table data", line, keyword)
+            }
+            CubeError::Malformed { line } => {
+                write!(f, "line {}: malformed entry", line)
+            }
+            CubeError::SizeOutOfRange { line, size } => {
+                write!(f, "line {}: size {} is out of range", line, size)
+            }
+            CubeError::InvalidDomain { line } => {
+                write!(f, "line {}: domain minimum is not below domain maximum", line)
+            }
+            CubeError::DataBeforeSize { line } => {
+                write!(f, "line {}: table data appears before any LUT size", line)
+            }
+            CubeError::MissingSize => {
+                write!(f, "no LUT_1D_SIZE or LUT_3D_SIZE is defined")
+            }
+            CubeError::TooManyEntries { line, expected } => {
+                write!(f, "line {}: more than the expected {} entries", line, expected)
+            }
+            CubeError::TooFewEntries { expected, found } => {
+                write!(f, "expected {} entries but found {}", expected, found)
+            }
+        }
+    }
+}
+
+impl Error for CubeError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CubeError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for CubeError {
+
+    fn from(err: io::Error) -> Self {
+        CubeError::Io(err)
+    }
+}
+
+pub fn read_cube(input: &mut dyn BufRead) -> Result<Cube, CubeError> {
+
+    let mut title = None;
+    let mut comments = Vec::new();
+    let mut size_1d = None;
+    let mut size_3d = None;
+    let mut domain_min = None;
+    let mut domain_max = None;
+    let mut range_1d = None;
+    let mut range_3d = None;
+    let mut entries = Vec::new();
+    let mut expected = 0;
+
+    for (index, line) in input.lines().enumerate() {
+
+        let number = index + 1;
+        let line = line?;
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            continue
+        }
+
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            comments.push(comment.trim().to_string());
+            continue
+        }
+
+        let (keyword, rest) = trimmed
+            .split_once(char::is_whitespace)
+            .map(|(keyword, rest)| (keyword, rest.trim()))
+            .unwrap_or((trimmed, ""));
+
+        if keyword.starts_with(|c: char| c.is_ascii_digit() || "+-.".contains(c)) {
+
+            if size_1d.is_none() && size_3d.is_none() {
+                return Err(CubeError::DataBeforeSize { line: number })
+            }
+            if entries.len() == expected {
+                return Err(CubeError::TooManyEntries { line: number, expected })
+            }
+
+            let values = parse_floats(trimmed, 3, number)?;
+
+            entries.push(RgbPixel::new_rgb(values[0], values[1], values[2]));
+
+            continue
+        }
+
+        if !entries.is_empty() {
+            return Err(CubeError::KeywordAfterData {
+                line: number,
+                keyword: keyword.to_string(),
+            })
+        }
+
+        match keyword {
+            "TITLE" => {
+                check_unset(&title, keyword, number)?;
+                title = Some(parse_title(rest, number)?);
+            }
+            "LUT_1D_SIZE" => {
+                check_unset(&size_1d, keyword, number)?;
+                let size = parse_size(rest, LUT_1D_SIZE_RANGE, number)?;
+                expected += size;
+                size_1d = Some(size);
+            }
+            "LUT_3D_SIZE" => {
+                check_unset(&size_3d, keyword, number)?;
+                let size = parse_size(rest, LUT_3D_SIZE_RANGE, number)?;
+                expected += size * size * size;
+                size_3d = Some(size);
+            }
+            "DOMAIN_MIN" => {
+                check_unset(&domain_min, keyword, number)?;
+                let values = parse_floats(rest, 3, number)?;
+                domain_min = Some((RgbPixel::new_rgb(values[0], values[1], values[2]), number));
+            }
+            "DOMAIN_MAX" => {
+                check_unset(&domain_max, keyword, number)?;
+                let values = parse_floats(rest, 3, number)?;
+                domain_max = Some((RgbPixel::new_rgb(values[0], values[1], values[2]), number));
+            }
+            "LUT_1D_INPUT_RANGE" => {
+                check_unset(&range_1d, keyword, number)?;
+                range_1d = Some(parse_input_range(rest, number)?);
+            }
+            "LUT_3D_INPUT_RANGE" => {
+                check_unset(&range_3d, keyword, number)?;
+                range_3d = Some(parse_input_range(rest, number)?);
+            }
+            _ => {
+                return Err(CubeError::UnknownKeyword {
+                    line: number,
+                    keyword: keyword.to_string(),
+                })
+            }
+        }
+    }
+
+    if size_1d.is_none() && size_3d.is_none() {
+        return Err(CubeError::MissingSize)
+    }
+    if entries.len() < expected {
+        return Err(CubeError::TooFewEntries { expected, found: entries.len() })
+    }
+
+    let domain = match (domain_min, domain_max) {
+        (None, None) => {
+            None
+        }
+        (min, max) => {
+            let (min, min_line) = min.unwrap_or((RgbPixel::new_y(0.0), 0));
+            let (max, max_line) = max.unwrap_or((RgbPixel::new_y(1.0), 0));
+            if !is_domain(min, max) {
+                return Err(CubeError::InvalidDomain { line: min_line.max(max_line) })
+            }
+            Some((min, max))
+        }
+    };
+    let lut_3d = size_3d.map(|size| {
+        let mut lut = Lut3d::new(size, entries.split_off(entries.len() - size * size * size));
+        if let Some((min, max)) = range_3d.or(domain) {
+            lut.set_domain(min, max);
+        }
+        lut.title = title.clone();
+        lut.comments = comments.clone();
+        lut
+    });
+    let lut_1d = size_1d.map(|_| {
+        let mut lut = Lut1d::new(entries);
+        if let Some((min, max)) = range_1d.or(domain) {
+            lut.set_domain(min, max);
+        }
+        lut.title = title;
+        lut.comments = comments;
+        lut
+    });
+
+    Ok(Cube { lut_1d, lut_3d })
+}
+
+fn check_unset<T>(value: &Option<T>, keyword: &str, line: usize) -> Result<(), CubeError> {
+    if value.is_some() {
+        Err(CubeError::DuplicateKeyword { line, keyword: keyword.to_string() })
+    } else {
+        Ok(())
+    }
+}
+
+fn parse_title(text: &str, line: usize) -> Result<String, CubeError> {
+    match text.strip_prefix('"').and_then(|text| text.strip_suffix('"')) {
+        Some(title) if !title.contains('"') => Ok(title.to_string()),
+        _ => Err(CubeError::Malformed { line }),
+    }
+}
+
+fn parse_size(
+    text: &str,
+    range: RangeInclusive<usize>,
+    line: usize,
+) -> Result<usize, CubeError> {
+
+    let size = text.parse::<usize>().map_err(|_| CubeError::Malformed { line })?;
+
+    if range.contains(&size) {
+        Ok(size)
+    } else {
+        Err(CubeError::SizeOutOfRange { line, size })
+    }
+}
+
+fn parse_input_range(text: &str, line: usize) -> Result<(RgbPixel, RgbPixel), CubeError> {
+
+    let values = parse_floats(text, 2, line)?;
+
+    if values[0] < values[1] {
+        Ok((RgbPixel::new_y(values[0]), RgbPixel::new_y(values[1])))
+    } else {
+        Err(CubeError::InvalidDomain { line })
+    }
+}
+
+fn parse_floats(text: &str, count: usize, line: usize) -> Result<Vec<f64>, CubeError> {
+
+    let values = text
+        .split_whitespace()
+        .map(|value| value.parse::<f64>().ok().filter(|value| value.is_finite()))
+        .collect::<Option<Vec<f64>>>()
+        .ok_or(CubeError::Malformed { line })?;
+
+    if values.len() == count {
+        Ok(values)
+    } else {
+        Err(CubeError::Malformed { line })
+    }
+}
+
+//
+// Writers
+//
+
+impl Lut1d {
+
+    pub fn write_cube(&self, output: &mut dyn Write) -> io::Result<()> {
+
+        write_header(output, &self.title, &self.comments)?;
+        writeln!(output, "LUT_1D_SIZE {}", self.size())?;
+        write_domain(output, self.domain_min(), self.domain_max())?;
+        write_table(output, self.table())
+    }
+}
+
+impl Lut3d {
+
+    pub fn write_cube(&self, output: &mut dyn Write) -> io::Result<()> {
+
+        write_header(output, &self.title, &self.comments)?;
+        writeln!(output, "LUT_3D_SIZE {}", self.size())?;
+        write_domain(output, self.domain_min(), self.domain_max())?;
+        write_table(output, self.table())
+    }
+}
+
+impl ShapedLut {
+
+    pub fn write_cube(&self, output: &mut dyn Write) -> io::Result<()> {
+
+        //
+        // This is the DaVinci Resolve layout for a shaper followed by a 3D LUT. The 1D entries
+        // come first and the 3D entries follow immediately after them.
+        //
+
+        write_header(output, &self.lut_3d.title, &self.lut_3d.comments)?;
+        writeln!(output, "LUT_1D_SIZE {}", self.shaper.size())?;
+        writeln!(output, "LUT_1D_INPUT_RANGE {} {}",
+            self.shaper.domain_min().red as f32,
+            self.shaper.domain_max().red as f32,
+        )?;
+        writeln!(output, "LUT_3D_SIZE {}", self.lut_3d.size())?;
+        writeln!(output, "LUT_3D_INPUT_RANGE {} {}",
+            self.lut_3d.domain_min().red as f32,
+            self.lut_3d.domain_max().red as f32,
+        )?;
+        write_table(output, self.shaper.table())?;
+        write_table(output, self.lut_3d.table())
+    }
+}
+
+fn write_header(
+    output: &mut dyn Write,
+    title: &Option<String>,
+    comments: &[String],
+) -> io::Result<()> {
+
+    for comment in comments.iter() {
+        writeln!(output, "# {}", comment)?;
+    }
+    if let Some(title) = title.as_ref() {
+        writeln!(output, "TITLE \"{}\"", title)?;
+    }
+
+    Ok(())
+}
+
+fn write_domain(output: &mut dyn Write, min: RgbPixel, max: RgbPixel) -> io::Result<()> {
+
+    writeln!(output, "DOMAIN_MIN {} {} {}", min.red as f32, min.green as f32, min.blue as f32)?;
+    writeln!(output, "DOMAIN_MAX {} {} {}", max.red as f32, max.green as f32, max.blue as f32)
+}
+
+fn write_table(output: &mut dyn Write, table: &[RgbPixel]) -> io::Result<()> {
+
+    for pixel in table.iter() {
+        writeln!(output, "{} {} {}",
+            pixel.red as f32,
+            pixel.green as f32,
+            pixel.blue as f32,
+        )?;
+    }
+
+    Ok(())
+}
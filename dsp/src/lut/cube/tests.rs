@@ -0,0 +1,288 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use std::io::Cursor;
+
+fn read(text: &str) -> Result<Cube, CubeError> {
+    read_cube(&mut Cursor::new(text))
+}
+
+#[test]
+fn test_lut_3d_write_cube() {
+
+    let mut lut = Lut3d::from_fn(2, |pixel| pixel * 0.5);
+    let mut output = vec![0_u8; 0];
+
+    lut.title = Some(String::from("Half"));
+    lut.comments.push(String::from("Generated by test"));
+    lut.comments.push(String::from("factor: 0.5"));
+    lut.write_cube(&mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "# Generated by test\n\
+        # factor: 0.5\n\
+        TITLE \"Half\"\n\
+        LUT_3D_SIZE 2\n\
+        DOMAIN_MIN 0 0 0\n\
+        DOMAIN_MAX 1 1 1\n\
+        0 0 0\n\
+        0.5 0 0\n\
+        0 0.5 0\n\
+        0.5 0.5 0\n\
+        0 0 0.5\n\
+        0.5 0 0.5\n\
+        0 0.5 0.5\n\
+        0.5 0.5 0.5\n",
+    );
+}
+
+#[test]
+fn test_lut_3d_write_cube_untitled() {
+
+    let lut = Lut3d::from_fn(2, |pixel| pixel);
+    let mut output = vec![0_u8; 0];
+
+    lut.write_cube(&mut output).unwrap();
+
+    let text = String::from_utf8(output).unwrap();
+
+    assert!(!text.contains("TITLE"));
+    assert!(text.starts_with("LUT_3D_SIZE 2\n"));
+    assert_eq!(text.lines().count(), 3 + 8);
+}
+
+#[test]
+fn test_lut_1d_write_cube() {
+
+    let lut = Lut1d::from_fn(3, |x| x);
+    let mut output = vec![0_u8; 0];
+
+    lut.write_cube(&mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "LUT_1D_SIZE 3\n\
+        DOMAIN_MIN 0 0 0\n\
+        DOMAIN_MAX 1 1 1\n\
+        0 0 0\n\
+        0.5 0.5 0.5\n\
+        1 1 1\n",
+    );
+}
+
+#[test]
+fn test_read_cube_3d_round_trip() {
+
+    let mut lut = Lut3d::from_fn(5, |pixel| pixel * 0.25);
+    let mut output = vec![0_u8; 0];
+
+    lut.title = Some(String::from("Quarter"));
+    lut.comments.push(String::from("Generated by test"));
+    lut.write_cube(&mut output).unwrap();
+
+    let cube = read(&String::from_utf8(output).unwrap()).unwrap();
+
+    assert_eq!(cube.lut_1d, None);
+    assert_eq!(cube.lut_3d, Some(lut));
+}
+
+#[test]
+fn test_read_cube_1d_round_trip() {
+
+    let lut = Lut1d::from_fn(1_024, |x| x.powf(2.4));
+    let mut output = vec![0_u8; 0];
+
+    lut.write_cube(&mut output).unwrap();
+
+    let cube = read(&String::from_utf8(output).unwrap()).unwrap();
+    let read_lut = cube.lut_1d.unwrap();
+
+    assert_eq!(cube.lut_3d, None);
+    assert_eq!(read_lut.size(), 1_024);
+    for (a, b) in read_lut.table().iter().zip(lut.table().iter()) {
+        assert_eq!(a.red as f32, b.red as f32);
+    }
+}
+
+#[test]
+fn test_read_cube_shaper_and_input_ranges() {
+
+    let cube = read(
+        "# Resolve style\n\
+        \n\
+        TITLE \"Shaped\"\n\
+        LUT_1D_SIZE 2\n\
+        LUT_3D_SIZE 2\n\
+        LUT_1D_INPUT_RANGE 0.0 4.0\n\
+        LUT_3D_INPUT_RANGE 0.0 1.0\n\
+        0 0 0\n\
+        1 1 1\n\
+        0 0 0\n\
+        1 0 0\n\
+        0 1 0\n\
+        1 1 0\n\
+        0 0 1\n\
+        1 0 1\n\
+        0 1 1\n\
+        1 1 1\n"
+    ).unwrap();
+    let lut_1d = cube.lut_1d.unwrap();
+    let lut_3d = cube.lut_3d.unwrap();
+
+    assert_eq!(lut_1d.size(), 2);
+    assert_eq!(lut_1d.domain_max(), RgbPixel::new_y(4.0));
+    assert_eq!(lut_1d.title.as_deref(), Some("Shaped"));
+    assert_eq!(lut_1d.comments, vec![String::from("Resolve style")]);
+    assert_eq!(lut_3d.size(), 2);
+    assert_eq!(lut_3d.domain_max(), RgbPixel::new_y(1.0));
+    assert_eq!(lut_3d.get(1, 0, 1), RgbPixel::new_rgb(1.0, 0.0, 1.0));
+}
+
+#[test]
+fn test_read_cube_domain() {
+
+    let cube = read(
+        "LUT_1D_SIZE 2\n\
+        DOMAIN_MIN -0.5 0 0\n\
+        DOMAIN_MAX 1 2 3\n\
+        0 0 0\n\
+        1 1 1\n"
+    ).unwrap();
+    let lut = cube.lut_1d.unwrap();
+
+    assert_eq!(lut.domain_min(), RgbPixel::new_rgb(-0.5, 0.0, 0.0));
+    assert_eq!(lut.domain_max(), RgbPixel::new_rgb(1.0, 2.0, 3.0));
+}
+
+#[test]
+fn test_read_cube_unknown_keyword() {
+    assert!(matches!(
+        read("LUT_3D_SIZE 2\nLUT_4D_SIZE 2\n"),
+        Err(CubeError::UnknownKeyword { line: 2, .. }),
+    ));
+}
+
+#[test]
+fn test_read_cube_duplicate_keyword() {
+    assert!(matches!(
+        read("TITLE \"a\"\nTITLE \"b\"\n"),
+        Err(CubeError::DuplicateKeyword { line: 2, .. }),
+    ));
+}
+
+#[test]
+fn test_read_cube_keyword_after_data() {
+    assert!(matches!(
+        read("LUT_1D_SIZE 2\n0 0 0\nTITLE \"late\"\n1 1 1\n"),
+        Err(CubeError::KeywordAfterData { line: 3, .. }),
+    ));
+}
+
+#[test]
+fn test_read_cube_malformed() {
+    assert!(matches!(
+        read("LUT_1D_SIZE 2\n0 0 0\n1 1\n"),
+        Err(CubeError::Malformed { line: 3 }),
+    ));
+    assert!(matches!(
+        read("LUT_1D_SIZE 2\n0 0 0\n1 x 1\n"),
+        Err(CubeError::Malformed { line: 3 }),
+    ));
+    assert!(matches!(
+        read("LUT_1D_SIZE 2\n0 0 0\n1 1 1 1\n"),
+        Err(CubeError::Malformed { line: 3 }),
+    ));
+    assert!(matches!(
+        read("TITLE unquoted\n"),
+        Err(CubeError::Malformed { line: 1 }),
+    ));
+    assert!(matches!(
+        read("LUT_3D_SIZE two\n"),
+        Err(CubeError::Malformed { line: 1 }),
+    ));
+}
+
+#[test]
+fn test_read_cube_size_out_of_range() {
+    assert!(matches!(
+        read("# comment\nLUT_3D_SIZE 257\n"),
+        Err(CubeError::SizeOutOfRange { line: 2, size: 257 }),
+    ));
+    assert!(matches!(
+        read("LUT_1D_SIZE 1\n"),
+        Err(CubeError::SizeOutOfRange { line: 1, size: 1 }),
+    ));
+}
+
+#[test]
+fn test_read_cube_invalid_domain() {
+    assert!(matches!(
+        read("LUT_1D_SIZE 2\nDOMAIN_MIN 0 0 0\nDOMAIN_MAX 1 0 1\n0 0 0\n1 1 1\n"),
+        Err(CubeError::InvalidDomain { line: 3 }),
+    ));
+    assert!(matches!(
+        read("LUT_1D_SIZE 2\nLUT_1D_INPUT_RANGE 1 0\n"),
+        Err(CubeError::InvalidDomain { line: 2 }),
+    ));
+}
+
+#[test]
+fn test_read_cube_missing_size() {
+    assert!(matches!(read("TITLE \"empty\"\n"), Err(CubeError::MissingSize)));
+    assert!(matches!(read("0 0 0\n"), Err(CubeError::DataBeforeSize { line: 1 })));
+}
+
+#[test]
+fn test_read_cube_entry_count() {
+    assert!(matches!(
+        read("LUT_1D_SIZE 2\n0 0 0\n1 1 1\n1 1 1\n"),
+        Err(CubeError::TooManyEntries { line: 4, expected: 2 }),
+    ));
+    assert!(matches!(
+        read("LUT_3D_SIZE 2\n0 0 0\n1 1 1\n"),
+        Err(CubeError::TooFewEntries { expected: 8, found: 2 }),
+    ));
+}
+
+#[test]
+fn test_read_cube_shaped_round_trip() {
+
+    let mut lut = ShapedLut::from_mapper(&Lut3d::from_fn(2, |pixel| pixel * 0.5), 16, 3);
+    let mut output = vec![0_u8; 0];
+
+    lut.lut_3d.title = Some(String::from("Shaped"));
+    lut.write_cube(&mut output).unwrap();
+
+    let text = String::from_utf8(output).unwrap();
+    let cube = read(&text).unwrap();
+    let lut_1d = cube.lut_1d.unwrap();
+    let lut_3d = cube.lut_3d.unwrap();
+
+    assert!(text.starts_with(
+        "TITLE \"Shaped\"\n\
+        LUT_1D_SIZE 16\n\
+        LUT_1D_INPUT_RANGE 0 1\n\
+        LUT_3D_SIZE 3\n\
+        LUT_3D_INPUT_RANGE 0 1\n"
+    ));
+    assert_eq!(lut_1d.size(), 16);
+    assert_eq!(lut_3d.size(), 3);
+    assert_eq!(lut_3d.title.as_deref(), Some("Shaped"));
+    for (a, b) in lut_1d.table().iter().zip(lut.shaper.table().iter()) {
+        assert_eq!(a.red as f32, b.red as f32);
+    }
+    for (a, b) in lut_3d.table().iter().zip(lut.lut_3d.table().iter()) {
+        assert_eq!(a.green as f32, b.green as f32);
+    }
+}
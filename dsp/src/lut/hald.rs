@@ -0,0 +1,120 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+mod png;
+
+use super::{Lut3d, RgbPixel};
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, Read, Write},
+};
+
+pub const HALD_LEVELS: [usize; 3] = [8, 12, 16];
+
+//
+// A Hald CLUT of level L is a square image L^3 pixels wide holding a 3D LUT of size L^2. Pixels
+// run left to right and then top to bottom with red moving fastest, exactly like Cube entries.
+//
+
+#[derive(Debug)]
+pub enum HaldError {
+    Io(io::Error),
+    NotPng,
+    Corrupt { reason: &'static str },
+    Unsupported { reason: &'static str },
+    NotHald { width: usize, height: usize },
+}
+
+impl Display for HaldError {
+
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            HaldError::Io(err) => {
+                write!(f, "I/O error: {}", err)
+            }
+            HaldError::NotPng => {
+                write!(f, "not a PNG image")
+            }
+            HaldError::Corrupt { reason } => {
+                write!(f, "corrupt PNG image: {}", reason)
+            }
+            HaldError::Unsupported { reason } => {
+                write!(f, "unsupported PNG image: {}", reason)
+            }
+            HaldError::NotHald { width, height } => {
+                write!(f, "a {}x{} image is not a Hald CLUT", width, height)
+            }
+        }
+    }
+}
+
+impl Error for HaldError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            HaldError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for HaldError {
+
+    fn from(err: io::Error) -> Self {
+        HaldError::Io(err)
+    }
+}
+
+pub fn read_hald(input: &mut dyn Read) -> Result<Lut3d, HaldError> {
+
+    let image = png::read_png(input)?;
+    let level = hald_level(image.width, image.height)
+        .ok_or(HaldError::NotHald { width: image.width, height: image.height })?;
+    let table = image.samples
+        .chunks_exact(3)
+        .map(|sample| RgbPixel {
+            red: sample[0] as f64 / 65_535.0,
+            green: sample[1] as f64 / 65_535.0,
+            blue: sample[2] as f64 / 65_535.0,
+        })
+        .collect();
+
+    Ok(Lut3d::new(level * level, table))
+}
+
+// The PNG reader also checks this before inflating any image data, since the dimensions come
+// from an untrusted header.
+fn hald_level(width: usize, height: usize) -> Option<usize> {
+    (2..=16)
+        .find(|level: &usize| level.pow(3) == width)
+        .filter(|_| width == height)
+}
+
+impl Lut3d {
+
+    pub fn write_hald(&self, output: &mut dyn Write) -> io::Result<()> {
+
+        let size = self.size();
+        let level = (2..=16)
+            .find(|level: &usize| level * level == size)
+            .unwrap_or_else(|| panic!("A 3D LUT of size {} is not a Hald CLUT", size));
+        let quantize = |x: f64| (x.clamp(0.0, 1.0) * 65_535.0).round() as u16;
+        let samples = self.table()
+            .iter()
+            .flat_map(|pixel| [quantize(pixel.red), quantize(pixel.green), quantize(pixel.blue)])
+            .collect::<Vec<u16>>();
+        let side = level * level * level;
+
+        png::write_png(output, side, side, &samples)
+    }
+}
@@ -0,0 +1,667 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+//
+// Just enough PNG to exchange Hald CLUTs: the writer emits 16-bit RGB using stored (uncompressed)
+// deflate blocks, while the reader accepts any non-interlaced 8-bit or 16-bit RGB or RGBA image
+// that an image editor is likely to save.
+//
+
+use super::{HaldError, hald_level};
+use std::io::{self, Read, Write};
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+const STORED_BLOCK_MAX: usize = 65_535;
+// PNG limits chunk lengths to 2^31 - 1 bytes.
+const CHUNK_LENGTH_MAX: usize = 0x7FFF_FFFF;
+
+pub(super) struct Image {
+    pub width: usize,
+    pub height: usize,
+    // RGB samples scaled to 16 bits, in raster order.
+    pub samples: Vec<u16>,
+}
+
+//
+// Writer
+//
+
+pub(super) fn write_png(
+    output: &mut dyn Write,
+    width: usize,
+    height: usize,
+    samples: &[u16],
+) -> io::Result<()> {
+
+    assert_eq!(samples.len(), width * height * 3, "Sample count does not match dimensions");
+
+    let mut ihdr = Vec::with_capacity(13);
+
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // 16 bits per sample, truecolor, deflate, adaptive filtering, no interlacing
+    ihdr.extend_from_slice(&[16, 2, 0, 0, 0]);
+
+    let mut raw = Vec::with_capacity(height * (1 + width * 6));
+
+    for row in samples.chunks_exact(width * 3) {
+        // filter type None
+        raw.push(0);
+        for sample in row.iter() {
+            raw.extend_from_slice(&sample.to_be_bytes());
+        }
+    }
+
+    output.write_all(&SIGNATURE)?;
+    write_chunk(output, b"IHDR", &ihdr)?;
+    write_chunk(output, b"IDAT", &zlib_stored(&raw))?;
+    write_chunk(output, b"IEND", &[])
+}
+
+pub(super) fn write_chunk(output: &mut dyn Write, kind: &[u8; 4], data: &[u8]) -> io::Result<()> {
+
+    let mut crc = Crc32::new();
+
+    crc.update(kind);
+    crc.update(data);
+
+    output.write_all(&(data.len() as u32).to_be_bytes())?;
+    output.write_all(kind)?;
+    output.write_all(data)?;
+    output.write_all(&crc.finish().to_be_bytes())
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+
+    let block_count = data.len().div_ceil(STORED_BLOCK_MAX).max(1);
+    let mut stream = Vec::with_capacity(data.len() + block_count * 5 + 6);
+
+    // deflate with a 32K window, no preset dictionary, and a valid FCHECK
+    stream.extend_from_slice(&[0x78, 0x01]);
+
+    if data.is_empty() {
+        stream.extend_from_slice(&[1, 0, 0, 0xFF, 0xFF]);
+    }
+
+    let mut blocks = data.chunks(STORED_BLOCK_MAX).peekable();
+
+    while let Some(block) = blocks.next() {
+        let length = block.len() as u16;
+        stream.push(if blocks.peek().is_none() { 1 } else { 0 });
+        stream.extend_from_slice(&length.to_le_bytes());
+        stream.extend_from_slice(&(!length).to_le_bytes());
+        stream.extend_from_slice(block);
+    }
+
+    stream.extend_from_slice(&adler32(data).to_be_bytes());
+
+    stream
+}
+
+//
+// Reader
+//
+
+pub(super) fn read_png(input: &mut dyn Read) -> Result<Image, HaldError> {
+
+    let mut signature = [0_u8; 8];
+
+    input.read_exact(&mut signature).map_err(|err| match err.kind() {
+        io::ErrorKind::UnexpectedEof => HaldError::NotPng,
+        _ => HaldError::Io(err),
+    })?;
+
+    if signature != SIGNATURE {
+        return Err(HaldError::NotPng)
+    }
+
+    let mut header = None;
+    let mut idat = Vec::new();
+
+    loop {
+
+        let mut prefix = [0_u8; 8];
+
+        input.read_exact(&mut prefix).map_err(truncated)?;
+
+        let length = u32::from_be_bytes([prefix[0], prefix[1], prefix[2], prefix[3]]) as usize;
+        let kind = [prefix[4], prefix[5], prefix[6], prefix[7]];
+        let mut data = Vec::new();
+        let mut crc_bytes = [0_u8; 4];
+
+        if length > CHUNK_LENGTH_MAX {
+            return Err(HaldError::Corrupt { reason: "chunk length out of range" })
+        }
+
+        // The length is untrusted, so the chunk is only allocated as its data actually arrives.
+        if input.take(length as u64).read_to_end(&mut data)? != length {
+            return Err(HaldError::Corrupt { reason: "truncated chunk" })
+        }
+
+        input.read_exact(&mut crc_bytes).map_err(truncated)?;
+
+        let mut crc = Crc32::new();
+
+        crc.update(&kind);
+        crc.update(&data);
+
+        if crc.finish() != u32::from_be_bytes(crc_bytes) {
+            return Err(HaldError::Corrupt { reason: "chunk CRC mismatch" })
+        }
+
+        match &kind {
+            b"IHDR" => {
+                header = Some(parse_header(&data)?);
+            }
+            b"IDAT" => {
+                if header.is_none() {
+                    return Err(HaldError::Corrupt { reason: "IDAT before IHDR" })
+                }
+                idat.extend_from_slice(&data);
+            }
+            b"IEND" => {
+                break
+            }
+            _ => {
+                // Bit 5 of the first byte marks ancillary chunks, which are safe to ignore.
+                if kind[0] & 0x20 == 0 {
+                    return Err(HaldError::Unsupported { reason: "unknown critical chunk" })
+                }
+            }
+        }
+    }
+
+    let (width, height, bit_depth, channels) = header
+        .ok_or(HaldError::Corrupt { reason: "missing IHDR" })?;
+
+    if hald_level(width, height).is_none() {
+        return Err(HaldError::NotHald { width, height })
+    }
+
+    let bytes_per_pixel = channels * bit_depth / 8;
+    let stride = width
+        .checked_mul(bytes_per_pixel)
+        .ok_or(HaldError::Corrupt { reason: "image dimensions out of range" })?;
+    let size = stride
+        .checked_add(1)
+        .and_then(|row| row.checked_mul(height))
+        .ok_or(HaldError::Corrupt { reason: "image dimensions out of range" })?;
+    let raw = zlib_inflate(&idat, size)?;
+
+    if raw.len() != size {
+        return Err(HaldError::Corrupt { reason: "image data does not match dimensions" })
+    }
+
+    let mut samples = Vec::with_capacity(width * height * 3);
+    let mut previous = vec![0_u8; stride];
+    let mut current = vec![0_u8; stride];
+
+    for row in raw.chunks_exact(stride + 1) {
+
+        current.copy_from_slice(&row[1..]);
+        unfilter(row[0], &mut current, &previous, bytes_per_pixel)?;
+
+        for pixel in current.chunks_exact(bytes_per_pixel) {
+            for channel in 0..3 {
+                samples.push(match bit_depth {
+                    8 => pixel[channel] as u16 * 257,
+                    _ => u16::from_be_bytes([pixel[channel * 2], pixel[channel * 2 + 1]]),
+                });
+            }
+        }
+
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    Ok(Image { width, height, samples })
+}
+
+fn truncated(err: io::Error) -> HaldError {
+    match err.kind() {
+        io::ErrorKind::UnexpectedEof => HaldError::Corrupt { reason: "truncated chunk" },
+        _ => HaldError::Io(err),
+    }
+}
+
+fn parse_header(data: &[u8]) -> Result<(usize, usize, usize, usize), HaldError> {
+
+    if data.len() != 13 {
+        return Err(HaldError::Corrupt { reason: "malformed IHDR" })
+    }
+
+    let width = u32::from_be_bytes([data[0], data[1], data[2], data[3]]) as usize;
+    let height = u32::from_be_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let bit_depth = data[8] as usize;
+    let channels = match data[9] {
+        2 => 3,
+        6 => 4,
+        _ => return Err(HaldError::Unsupported { reason: "color type is not RGB or RGBA" }),
+    };
+
+    if width == 0 || height == 0 {
+        return Err(HaldError::Corrupt { reason: "zero image dimension" })
+    }
+    if bit_depth != 8 && bit_depth != 16 {
+        return Err(HaldError::Unsupported { reason: "bit depth is not 8 or 16" })
+    }
+    if data[10] != 0 || data[11] != 0 {
+        return Err(HaldError::Unsupported { reason: "unknown compression or filter method" })
+    }
+    if data[12] != 0 {
+        return Err(HaldError::Unsupported { reason: "interlaced" })
+    }
+
+    Ok((width, height, bit_depth, channels))
+}
+
+fn unfilter(
+    filter: u8,
+    current: &mut [u8],
+    previous: &[u8],
+    bpp: usize,
+) -> Result<(), HaldError> {
+
+    for i in 0..current.len() {
+
+        let a = if i >= bpp { current[i - bpp] } else { 0 };
+        let b = previous[i];
+        let c = if i >= bpp { previous[i - bpp] } else { 0 };
+
+        current[i] = current[i].wrapping_add(match filter {
+            0 => 0,
+            1 => a,
+            2 => b,
+            3 => ((a as u16 + b as u16) / 2) as u8,
+            4 => paeth(a, b, c),
+            _ => return Err(HaldError::Corrupt { reason: "unknown filter type" }),
+        });
+    }
+
+    Ok(())
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+
+    let p = a as i16 + b as i16 - c as i16;
+    let pa = (p - a as i16).abs();
+    let pb = (p - b as i16).abs();
+    let pc = (p - c as i16).abs();
+
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+//
+// Inflate (RFC 1950 and RFC 1951)
+//
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+// Inflating stops with an error as soon as the output would grow past limit bytes.
+pub(super) fn zlib_inflate(stream: &[u8], limit: usize) -> Result<Vec<u8>, HaldError> {
+
+    if stream.len() < 6 {
+        return Err(HaldError::Corrupt { reason: "truncated zlib stream" })
+    }
+    if stream[0] & 0x0F != 8 || !(stream[0] as u16 * 256 + stream[1] as u16).is_multiple_of(31) {
+        return Err(HaldError::Corrupt { reason: "malformed zlib header" })
+    }
+    if stream[1] & 0x20 != 0 {
+        return Err(HaldError::Unsupported { reason: "preset zlib dictionary" })
+    }
+
+    let mut reader = BitReader { data: &stream[2..], position: 0, buffer: 0, count: 0 };
+    let mut output = Vec::new();
+
+    loop {
+
+        let last = reader.bits(1)? == 1;
+
+        match reader.bits(2)? {
+            0 => inflate_stored(&mut reader, &mut output, limit)?,
+            1 => {
+                let (literals, distances) = (fixed_literals(), fixed_distances());
+                inflate_block(&mut reader, &mut output, limit, &literals, &distances)?
+            }
+            2 => {
+                let (literals, distances) = dynamic_tables(&mut reader)?;
+                inflate_block(&mut reader, &mut output, limit, &literals, &distances)?
+            }
+            _ => return Err(HaldError::Corrupt { reason: "invalid deflate block type" }),
+        }
+
+        if last {
+            break
+        }
+    }
+
+    let trailer = reader.position;
+
+    if stream.len() < 2 + trailer + 4 {
+        return Err(HaldError::Corrupt { reason: "missing Adler-32 checksum" })
+    }
+
+    let checksum = &stream[2 + trailer..2 + trailer + 4];
+
+    if adler32(&output) != u32::from_be_bytes([checksum[0], checksum[1], checksum[2], checksum[3]])
+    {
+        return Err(HaldError::Corrupt { reason: "Adler-32 mismatch" })
+    }
+
+    Ok(output)
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitReader<'_> {
+
+    fn bits(&mut self, count: u32) -> Result<u32, HaldError> {
+
+        while self.count < count {
+            let byte = *self.data
+                .get(self.position)
+                .ok_or(HaldError::Corrupt { reason: "truncated deflate stream" })?;
+            self.buffer |= (byte as u32) << self.count;
+            self.position += 1;
+            self.count += 8;
+        }
+
+        let value = self.buffer & ((1_u64 << count) - 1) as u32;
+
+        self.buffer >>= count;
+        self.count -= count;
+
+        Ok(value)
+    }
+
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+
+    fn new(lengths: &[u8]) -> Self {
+
+        let mut counts = [0_u16; 16];
+
+        for length in lengths.iter() {
+            counts[*length as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0_u16; 16];
+
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + counts[length];
+        }
+
+        let mut symbols = vec![0_u16; lengths.len()];
+
+        for (symbol, length) in lengths.iter().enumerate() {
+            if *length != 0 {
+                symbols[offsets[*length as usize] as usize] = symbol as u16;
+                offsets[*length as usize] += 1;
+            }
+        }
+
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, HaldError> {
+
+        //
+        // Canonical codes of each length are consecutive, so a code can be decoded one bit at a
+        // time by tracking the first code and symbol index of the current length.
+        //
+
+        let mut code = 0_i32;
+        let mut first = 0_i32;
+        let mut index = 0_i32;
+
+        for length in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize])
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+
+        Err(HaldError::Corrupt { reason: "invalid Huffman code" })
+    }
+}
+
+fn fixed_literals() -> Huffman {
+
+    let mut lengths = [0_u8; 288];
+
+    lengths[0..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..288].fill(8);
+
+    Huffman::new(&lengths)
+}
+
+fn fixed_distances() -> Huffman {
+    Huffman::new(&[5; 30])
+}
+
+fn dynamic_tables(reader: &mut BitReader) -> Result<(Huffman, Huffman), HaldError> {
+
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    let mut code_lengths = [0_u8; 19];
+
+    for index in CODE_LENGTH_ORDER.iter().take(code_length_count) {
+        code_lengths[*index] = reader.bits(3)? as u8;
+    }
+
+    let code_length_codes = Huffman::new(&code_lengths);
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+
+    while lengths.len() < literal_count + distance_count {
+        let symbol = code_length_codes.decode(reader)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or(HaldError::Corrupt { reason: "repeat with no previous length" })?;
+                (previous, 3 + reader.bits(2)? as usize)
+            }
+            17 => (0, 3 + reader.bits(3)? as usize),
+            _ => (0, 11 + reader.bits(7)? as usize),
+        };
+        if lengths.len() + repeat > literal_count + distance_count {
+            return Err(HaldError::Corrupt { reason: "too many code lengths" })
+        }
+        lengths.extend(std::iter::repeat_n(value, repeat));
+    }
+
+    Ok((
+        Huffman::new(&lengths[..literal_count]),
+        Huffman::new(&lengths[literal_count..]),
+    ))
+}
+
+fn inflate_stored(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    limit: usize,
+) -> Result<(), HaldError> {
+
+    reader.align();
+
+    let length = reader.bits(16)?;
+    let complement = reader.bits(16)?;
+
+    if length != !complement & 0xFFFF {
+        return Err(HaldError::Corrupt { reason: "stored block length mismatch" })
+    }
+
+    if output.len() + length as usize > limit {
+        return Err(HaldError::Corrupt { reason: "image data exceeds dimensions" })
+    }
+
+    let start = reader.position;
+    let end = start + length as usize;
+    let block = reader.data
+        .get(start..end)
+        .ok_or(HaldError::Corrupt { reason: "truncated stored block" })?;
+
+    output.extend_from_slice(block);
+    reader.position = end;
+
+    Ok(())
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    limit: usize,
+    literals: &Huffman,
+    distances: &Huffman,
+) -> Result<(), HaldError> {
+
+    loop {
+
+        let symbol = literals.decode(reader)? as usize;
+
+        match symbol {
+            0..=255 => {
+                if output.len() >= limit {
+                    return Err(HaldError::Corrupt { reason: "image data exceeds dimensions" })
+                }
+                output.push(symbol as u8);
+            }
+            256 => {
+                return Ok(())
+            }
+            257..=285 => {
+                let index = symbol - 257;
+                let length = LENGTH_BASE[index] as usize
+                    + reader.bits(LENGTH_EXTRA[index] as u32)? as usize;
+                let index = distances.decode(reader)? as usize;
+                if index >= DISTANCE_BASE.len() {
+                    return Err(HaldError::Corrupt { reason: "invalid distance code" })
+                }
+                let distance = DISTANCE_BASE[index] as usize
+                    + reader.bits(DISTANCE_EXTRA[index] as u32)? as usize;
+                if distance > output.len() {
+                    return Err(HaldError::Corrupt { reason: "distance reaches before output" })
+                }
+                if output.len() + length > limit {
+                    return Err(HaldError::Corrupt { reason: "image data exceeds dimensions" })
+                }
+                let start = output.len() - distance;
+                for offset in 0..length {
+                    output.push(output[start + offset]);
+                }
+            }
+            _ => {
+                return Err(HaldError::Corrupt { reason: "invalid literal/length code" })
+            }
+        }
+    }
+}
+
+//
+// Checksums
+//
+
+struct Crc32 {
+    table: [u32; 256],
+    value: u32,
+}
+
+impl Crc32 {
+
+    fn new() -> Self {
+
+        let mut table = [0_u32; 256];
+
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+            for _ in 0..8 {
+                c = if c & 1 == 1 { 0xEDB8_8320 ^ (c >> 1) } else { c >> 1 };
+            }
+            *entry = c;
+        }
+
+        Self { table, value: 0xFFFF_FFFF }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for byte in data.iter() {
+            self.value = self.table[((self.value ^ *byte as u32) & 0xFF) as usize]
+                ^ (self.value >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        self.value ^ 0xFFFF_FFFF
+    }
+}
+
+pub(super) fn adler32(data: &[u8]) -> u32 {
+
+    let mut a = 1_u32;
+    let mut b = 0_u32;
+
+    // 5,552 is the largest run for which the sums cannot overflow before reduction.
+    for block in data.chunks(5_552) {
+        for byte in block.iter() {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65_521;
+        b %= 65_521;
+    }
+
+    (b << 16) | a
+}
@@ -0,0 +1,265 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use crate::{Mapper, PqHlgMapper, tm::ToneMapMethod};
+use assert_approx_eq::assert_approx_eq;
+
+// A level 2 identity Hald CLUT saved as 8-bit RGBA with every filter type and a tEXt chunk.
+const EXTERNAL_HALD: [u8; 171] = [
+    0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00, 0x00, 0x0D, 0x49, 0x48, 0x44, 0x52,
+    0x00, 0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x08, 0x08, 0x06, 0x00, 0x00, 0x00, 0xC4, 0x0F, 0xBE,
+    0x8B, 0x00, 0x00, 0x00, 0x0C, 0x74, 0x45, 0x58, 0x74, 0x43, 0x6F, 0x6D, 0x6D, 0x65, 0x6E, 0x74,
+    0x00, 0x74, 0x65, 0x73, 0x74, 0x57, 0x61, 0x2B, 0xE9, 0x00, 0x00, 0x00, 0x5A, 0x49, 0x44, 0x41,
+    0x54, 0x78, 0xDA, 0x7D, 0xCE, 0x81, 0x0D, 0x80, 0x20, 0x0C, 0x44, 0xD1, 0x43, 0xDD, 0xC6, 0x79,
+    0xEA, 0x3C, 0xB8, 0xCE, 0x2D, 0x59, 0x3F, 0x24, 0x04, 0x63, 0x22, 0x90, 0x07, 0x05, 0x1A, 0x72,
+    0x92, 0x94, 0x01, 0x23, 0xA1, 0xE0, 0x0C, 0x83, 0x87, 0x2C, 0x72, 0x6F, 0xD0, 0x50, 0x62, 0xD6,
+    0xCD, 0xA6, 0x8B, 0x6D, 0x61, 0x97, 0xCF, 0x7A, 0x52, 0x0F, 0x77, 0x9D, 0x75, 0x73, 0xF4, 0xCE,
+    0xF7, 0xF8, 0x1C, 0x25, 0x3B, 0x03, 0x06, 0x4B, 0x12, 0x2F, 0x03, 0x6D, 0x72, 0x41, 0xC8, 0x1E,
+    0x76, 0x15, 0xD2, 0xED, 0x97, 0x7F, 0x0F, 0x7B, 0xBF, 0x3E, 0x8B, 0x09, 0xFD, 0xBC, 0x06, 0x00,
+    0x00, 0x00, 0x00, 0x49, 0x45, 0x4E, 0x44, 0xAE, 0x42, 0x60, 0x82,
+];
+
+// The lines produced by `dynamic_text` compressed by zlib with dynamic Huffman codes.
+const DYNAMIC_ZLIB: [u8; 181] = [
+    0x78, 0xDA, 0x95, 0x94, 0xD9, 0x0D, 0x02, 0x31, 0x0C, 0x44, 0x5B, 0x71, 0x05, 0x28, 0x4E, 0x9C,
+    0xAB, 0x8F, 0x6D, 0x80, 0x63, 0xB9, 0x21, 0xB0, 0xCB, 0x72, 0x55, 0x0F, 0x05, 0x20, 0xA4, 0xF7,
+    0x3F, 0x8A, 0xE3, 0x79, 0x9E, 0xE9, 0xB6, 0xBD, 0x5C, 0xA7, 0xDD, 0xF2, 0x20, 0x8B, 0xA1, 0x3D,
+    0xCE, 0xB2, 0x6E, 0x4F, 0x71, 0xB2, 0x9F, 0x4E, 0x97, 0x51, 0xDA, 0xBD, 0x1F, 0xE4, 0xF6, 0x15,
+    0x1C, 0xE7, 0xEF, 0x97, 0xAC, 0xDA, 0x66, 0x26, 0xDD, 0x0F, 0xB9, 0x32, 0xB9, 0x31, 0x79, 0x85,
+    0x9F, 0x49, 0x4C, 0xEF, 0x23, 0xD3, 0x07, 0xF8, 0xBE, 0xC1, 0xFF, 0x27, 0x68, 0x4F, 0x81, 0xEE,
+    0xAB, 0xA3, 0x74, 0x3D, 0x9D, 0x60, 0x46, 0x91, 0x51, 0xC6, 0x95, 0x42, 0xA6, 0x94, 0x7D, 0xA4,
+    0x13, 0x0A, 0xDC, 0x21, 0x78, 0xA3, 0x87, 0x47, 0x73, 0x46, 0x49, 0x9B, 0xD1, 0x09, 0x05, 0xEE,
+    0x10, 0x3D, 0x74, 0x29, 0x66, 0xC8, 0x21, 0x51, 0xD2, 0x89, 0x4E, 0xC8, 0x74, 0x87, 0x4C, 0x5D,
+    0x2A, 0x94, 0x43, 0xA5, 0xA4, 0x6B, 0xC2, 0xAD, 0x41, 0xCF, 0x55, 0x1D, 0x4D, 0x84, 0x2A, 0x0D,
+    0x9D, 0xE2, 0x5C, 0xAB, 0xA7, 0xD5, 0xA1, 0x01, 0xB7, 0x93, 0xE1, 0x02, 0x8C, 0xFF, 0x3A, 0xF6,
+    0x03, 0xA0, 0x27, 0xA2, 0x5B,
+];
+
+fn dynamic_text() -> Vec<u8> {
+    (0..40)
+        .flat_map(|i| {
+            format!("The quick brown fox {} jumps over the lazy dog. ", i * i).into_bytes()
+        })
+        .collect()
+}
+
+#[test]
+fn test_adler32() {
+    assert_eq!(png::adler32(b""), 1);
+    assert_eq!(png::adler32(b"Wikipedia"), 0x11E6_0398);
+}
+
+#[test]
+fn test_inflate_dynamic() {
+    assert_eq!(png::zlib_inflate(&DYNAMIC_ZLIB, dynamic_text().len()).unwrap(), dynamic_text());
+}
+
+#[test]
+fn test_inflate_limit() {
+    assert!(matches!(
+        png::zlib_inflate(&DYNAMIC_ZLIB, dynamic_text().len() - 1),
+        Err(HaldError::Corrupt { reason: "image data exceeds dimensions" }),
+    ));
+}
+
+#[test]
+fn test_inflate_bad_checksum() {
+
+    let mut stream = DYNAMIC_ZLIB;
+
+    stream[180] ^= 1;
+
+    assert!(matches!(
+        png::zlib_inflate(&stream, dynamic_text().len()),
+        Err(HaldError::Corrupt { reason: "Adler-32 mismatch" }),
+    ));
+}
+
+#[test]
+fn test_write_hald_structure() {
+
+    let mut output = Vec::new();
+
+    Lut3d::from_fn(4, |pixel| pixel).write_hald(&mut output).unwrap();
+
+    assert_eq!(&output[0..8], b"\x89PNG\r\n\x1a\n");
+    assert_eq!(&output[12..16], b"IHDR");
+    // width, height, bit depth, and color type
+    assert_eq!(&output[16..26], &[0, 0, 0, 8, 0, 0, 0, 8, 16, 2]);
+    assert_eq!(&output[output.len() - 12..], &[
+        0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82,
+    ]);
+}
+
+#[test]
+fn test_hald_round_trip() {
+
+    let mapper = PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb);
+
+    for level in [2, 3, 8] {
+
+        let lut = Lut3d::from_mapper(&mapper, level * level);
+        let mut output = Vec::new();
+
+        lut.write_hald(&mut output).unwrap();
+
+        let read = read_hald(&mut output.as_slice()).unwrap();
+
+        assert_eq!(read.size(), level * level);
+
+        for (expected, actual) in lut.table().iter().zip(read.table().iter()) {
+            assert_approx_eq!(actual.red, expected.red, 0.5 / 65_535.0);
+            assert_approx_eq!(actual.green, expected.green, 0.5 / 65_535.0);
+            assert_approx_eq!(actual.blue, expected.blue, 0.5 / 65_535.0);
+        }
+    }
+}
+
+#[test]
+fn test_read_external_hald() {
+
+    let lut = read_hald(&mut EXTERNAL_HALD.as_slice()).unwrap();
+    let identity = Lut3d::from_fn(4, |pixel| pixel);
+
+    assert_eq!(lut.size(), 4);
+
+    for (expected, actual) in identity.table().iter().zip(lut.table().iter()) {
+        assert_approx_eq!(actual.red, expected.red, 0.5 / 255.0);
+        assert_approx_eq!(actual.green, expected.green, 0.5 / 255.0);
+        assert_approx_eq!(actual.blue, expected.blue, 0.5 / 255.0);
+    }
+
+    let pixel = lut.map(RgbPixel::new_rgb(0.2, 0.5, 0.9));
+
+    assert_approx_eq!(pixel.red, 0.2, 0.01);
+    assert_approx_eq!(pixel.green, 0.5, 0.01);
+    assert_approx_eq!(pixel.blue, 0.9, 0.01);
+}
+
+#[test]
+fn test_read_hald_not_png() {
+    assert!(matches!(read_hald(&mut &b"P6\n8 8\n255\n"[..]), Err(HaldError::NotPng)));
+    assert!(matches!(read_hald(&mut &b""[..]), Err(HaldError::NotPng)));
+}
+
+#[test]
+fn test_read_hald_bad_crc() {
+
+    let mut image = EXTERNAL_HALD;
+
+    image[20] ^= 1;
+
+    assert!(matches!(
+        read_hald(&mut image.as_slice()),
+        Err(HaldError::Corrupt { reason: "chunk CRC mismatch" }),
+    ));
+}
+
+#[test]
+fn test_read_hald_truncated() {
+    assert!(matches!(
+        read_hald(&mut &EXTERNAL_HALD[..100]),
+        Err(HaldError::Corrupt { reason: "truncated chunk" }),
+    ));
+}
+
+#[test]
+fn test_read_hald_not_hald() {
+
+    let mut output = Vec::new();
+
+    png::write_png(&mut output, 4, 2, &[0; 24]).unwrap();
+
+    assert!(matches!(
+        read_hald(&mut output.as_slice()),
+        Err(HaldError::NotHald { width: 4, height: 2 }),
+    ));
+}
+
+#[test]
+fn test_read_hald_single_pixel() {
+
+    let mut output = Vec::new();
+
+    png::write_png(&mut output, 1, 1, &[0; 3]).unwrap();
+
+    assert!(matches!(
+        read_hald(&mut output.as_slice()),
+        Err(HaldError::NotHald { width: 1, height: 1 }),
+    ));
+}
+
+#[test]
+fn test_read_hald_huge_dimensions() {
+
+    let mut image = EXTERNAL_HALD[..8].to_vec();
+
+    // Neither dimension fits any Hald level, so nothing is inflated or allocated for them.
+    png::write_chunk(&mut image, b"IHDR", &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF,
+        16, 2, 0, 0, 0]).unwrap();
+    png::write_chunk(&mut image, b"IDAT", &[0x78, 0x01, 1, 0, 0, 0xFF, 0xFF, 0, 0, 0, 1])
+        .unwrap();
+    png::write_chunk(&mut image, b"IEND", &[]).unwrap();
+
+    assert!(matches!(
+        read_hald(&mut image.as_slice()),
+        Err(HaldError::NotHald { width: 0xFFFF_FFFF, height: 0xFFFF_FFFF }),
+    ));
+}
+
+#[test]
+fn test_read_hald_excess_data() {
+
+    let mut image = Vec::new();
+    let mut oversized = Vec::new();
+
+    // A level 2 image holds 8 rows of a filter byte and 8 pixels of 6 bytes.
+    png::write_png(&mut oversized, 8, 9, &[0; 8 * 9 * 3]).unwrap();
+    png::write_png(&mut image, 8, 8, &[0; 8 * 8 * 3]).unwrap();
+
+    // Swapping in the IDAT of the taller image overflows the expected image data.
+    image.truncate(33);
+    image.extend_from_slice(&oversized[33..]);
+
+    assert!(matches!(
+        read_hald(&mut image.as_slice()),
+        Err(HaldError::Corrupt { reason: "image data exceeds dimensions" }),
+    ));
+}
+
+#[test]
+fn test_read_hald_chunk_length() {
+
+    let mut image = EXTERNAL_HALD[..33].to_vec();
+
+    // The first chunk after IHDR claims to be nearly 2 GiB but the image ends right after it.
+    image.extend_from_slice(&[0x7F, 0xFF, 0xFF, 0xFF, b'I', b'D', b'A', b'T', 0, 0]);
+
+    assert!(matches!(
+        read_hald(&mut image.as_slice()),
+        Err(HaldError::Corrupt { reason: "truncated chunk" }),
+    ));
+
+    image[33] = 0x80;
+
+    assert!(matches!(
+        read_hald(&mut image.as_slice()),
+        Err(HaldError::Corrupt { reason: "chunk length out of range" }),
+    ));
+}
+
+#[test]
+#[should_panic]
+fn test_write_hald_not_square() {
+    Lut3d::from_fn(5, |pixel| pixel).write_hald(&mut Vec::new()).unwrap();
+}
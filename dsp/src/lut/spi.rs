@@ -0,0 +1,82 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{Lut1d, Lut3d};
+use std::io::{Result, Write};
+
+//
+// Neither Sony Imageworks format has room for a title or comments, and .spi3d has no notion of
+// a domain, so its input is always 0.0 to 1.0.
+//
+
+impl Lut1d {
+
+    pub fn write_spi1d(&self, output: &mut dyn Write) -> Result<()> {
+
+        let neutral = self.table()
+            .iter()
+            .all(|pixel| pixel.red == pixel.green && pixel.red == pixel.blue);
+
+        writeln!(output, "Version 1")?;
+        // The format has a single domain shared by all channels.
+        writeln!(output, "From {} {}", self.domain_min().red as f32, self.domain_max().red as f32)?;
+        writeln!(output, "Length {}", self.size())?;
+        writeln!(output, "Components {}", if neutral { 1 } else { 3 })?;
+        writeln!(output, "{{")?;
+
+        for pixel in self.table().iter() {
+            if neutral {
+                writeln!(output, "    {}", pixel.red as f32)?;
+            } else {
+                writeln!(output, "    {} {} {}",
+                    pixel.red as f32,
+                    pixel.green as f32,
+                    pixel.blue as f32,
+                )?;
+            }
+        }
+
+        writeln!(output, "}}")
+    }
+}
+
+impl Lut3d {
+
+    pub fn write_spi3d(&self, output: &mut dyn Write) -> Result<()> {
+
+        let size = self.size();
+
+        writeln!(output, "SPILUT 1.0")?;
+        writeln!(output, "3 3")?;
+        writeln!(output, "{} {} {}", size, size, size)?;
+
+        // Every entry carries its own indices, but readers expect the blue index to move fastest.
+        for r in 0..size {
+            for g in 0..size {
+                for b in 0..size {
+                    let pixel = self.get(r, g, b);
+                    writeln!(output, "{} {} {} {} {} {}",
+                        r,
+                        g,
+                        b,
+                        pixel.red as f32,
+                        pixel.green as f32,
+                        pixel.blue as f32,
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
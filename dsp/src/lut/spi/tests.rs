@@ -0,0 +1,88 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use crate::{pixel::RgbPixel, tf::pq_ieotf};
+use assert_approx_eq::assert_approx_eq;
+
+#[test]
+fn test_write_spi1d_neutral() {
+
+    let lut = Lut1d::from_fn(3, |x| x * x);
+    let mut output = Vec::new();
+
+    lut.write_spi1d(&mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "Version 1\n\
+        From 0 1\n\
+        Length 3\n\
+        Components 1\n\
+        {\n    \
+        0\n    \
+        0.25\n    \
+        1\n\
+        }\n",
+    );
+}
+
+#[test]
+fn test_write_spi1d_color() {
+
+    let lut = Lut1d::new(vec![RgbPixel::new_rgb(0.0, 0.5, 1.0), RgbPixel::new_y(1.0)]);
+    let mut output = Vec::new();
+
+    lut.write_spi1d(&mut output).unwrap();
+
+    let text = String::from_utf8(output).unwrap();
+
+    assert!(text.contains("Components 3\n"));
+    assert!(text.contains("{\n    0 0.5 1\n    1 1 1\n}\n"));
+}
+
+#[test]
+fn test_write_spi1d_domain() {
+
+    let lut = Lut1d::from_fn_with_domain(5, 0.0, 0.1, pq_ieotf);
+    let mut output = Vec::new();
+
+    assert_approx_eq!(lut.get(4).red, pq_ieotf(0.1), 0.0000000001);
+
+    lut.write_spi1d(&mut output).unwrap();
+
+    assert!(String::from_utf8(output).unwrap().starts_with("Version 1\nFrom 0 0.1\nLength 5\n"));
+}
+
+#[test]
+fn test_write_spi3d() {
+
+    let lut = Lut3d::from_fn(2, |pixel| RgbPixel::new_rgb(pixel.red * 0.5, pixel.green, 0.25));
+    let mut output = Vec::new();
+
+    lut.write_spi3d(&mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "SPILUT 1.0\n\
+        3 3\n\
+        2 2 2\n\
+        0 0 0 0 0 0.25\n\
+        0 0 1 0 0 0.25\n\
+        0 1 0 0 1 0.25\n\
+        0 1 1 0 1 0.25\n\
+        1 0 0 0.5 0 0.25\n\
+        1 0 1 0.5 0 0.25\n\
+        1 1 0 0.5 1 0.25\n\
+        1 1 1 0.5 1 0.25\n",
+    );
+}
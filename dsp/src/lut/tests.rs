@@ -0,0 +1,324 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use crate::{HlgPqMapper, PqHlgMapper, tm::ToneMapMethod};
+use accuracy::OutputSignal;
+use assert_approx_eq::assert_approx_eq;
+use more_asserts::{assert_gt, assert_lt};
+
+const DIFF: f64 = 0.0000000001;
+
+#[test]
+fn test_lut_3d_identity_lattice() {
+
+    const SIZE: usize = 17;
+
+    let lut = Lut3d::from_fn(SIZE, |pixel| pixel);
+
+    assert_eq!(lut.size(), SIZE);
+    assert_eq!(lut.table().len(), SIZE * SIZE * SIZE);
+
+    for b in 0..SIZE {
+        for g in 0..SIZE {
+            for r in 0..SIZE {
+
+                let pixel = lut.get(r, g, b);
+
+                assert_approx_eq!(pixel.red, r as f64 / (SIZE - 1) as f64, DIFF);
+                assert_approx_eq!(pixel.green, g as f64 / (SIZE - 1) as f64, DIFF);
+                assert_approx_eq!(pixel.blue, b as f64 / (SIZE - 1) as f64, DIFF);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_lut_3d_red_fastest() {
+
+    let lut = Lut3d::from_fn(2, |pixel| pixel);
+
+    assert_eq!(lut.table()[1], RgbPixel::new_rgb(1.0, 0.0, 0.0));
+    assert_eq!(lut.table()[2], RgbPixel::new_rgb(0.0, 1.0, 0.0));
+    assert_eq!(lut.table()[4], RgbPixel::new_rgb(0.0, 0.0, 1.0));
+}
+
+#[test]
+fn test_lut_3d_from_mapper() {
+
+    const SIZE: usize = 9;
+
+    let mapper = PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb);
+    let lut = Lut3d::from_mapper(&mapper, SIZE);
+
+    for b in 0..SIZE {
+        for g in 0..SIZE {
+            for r in 0..SIZE {
+
+                let expected = mapper.map(RgbPixel {
+                    red: r as f64 / (SIZE - 1) as f64,
+                    green: g as f64 / (SIZE - 1) as f64,
+                    blue: b as f64 / (SIZE - 1) as f64,
+                });
+
+                assert_eq!(lut.get(r, g, b), expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_lut_3d_from_dyn_mapper() {
+
+    let mapper: Box<dyn Mapper> = Box::new(HlgPqMapper::new(1_000.0));
+    let lut = Lut3d::from_mapper(mapper.as_ref(), 2);
+
+    assert_eq!(lut.get(0, 0, 0), mapper.map(RgbPixel::new_y(0.0)));
+    assert_eq!(lut.get(1, 1, 1), mapper.map(RgbPixel::new_y(1.0)));
+}
+
+#[test]
+fn test_lut_1d_from_fn() {
+
+    let lut = Lut1d::from_fn(5, |x| x * x);
+
+    assert_eq!(lut.size(), 5);
+    assert_eq!(lut.get(0), RgbPixel::new_y(0.0));
+    assert_eq!(lut.get(2), RgbPixel::new_y(0.25));
+    assert_eq!(lut.get(4), RgbPixel::new_y(1.0));
+}
+
+#[test]
+#[should_panic]
+fn test_lut_3d_table_mismatch() {
+    Lut3d::new(3, vec![RgbPixel::new_y(0.0); 26]);
+}
+
+#[test]
+fn test_lut_3d_interpolation_identity() {
+
+    let mut lut = Lut3d::from_fn(5, |pixel| pixel);
+
+    for method in [InterpolationMethod::Trilinear, InterpolationMethod::Tetrahedral] {
+
+        lut.interpolation = method;
+
+        for b in 0..=20 {
+            for g in 0..=20 {
+                for r in 0..=20 {
+
+                    let in_pixel = RgbPixel {
+                        red: r as f64 / 20.0,
+                        green: (g as f64 / 20.0).powf(2.0),
+                        blue: (b as f64 / 20.0).sqrt(),
+                    };
+                    let out_pixel = lut.map(in_pixel);
+
+                    assert_approx_eq!(out_pixel.red, in_pixel.red, DIFF);
+                    assert_approx_eq!(out_pixel.green, in_pixel.green, DIFF);
+                    assert_approx_eq!(out_pixel.blue, in_pixel.blue, DIFF);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_lut_3d_interpolation_lattice() {
+
+    let mut lut = Lut3d::from_fn(4, |pixel| pixel.with_each_channel(|x| x.powf(2.4)));
+
+    for method in [InterpolationMethod::Trilinear, InterpolationMethod::Tetrahedral] {
+
+        lut.interpolation = method;
+
+        for b in 0..4 {
+            for g in 0..4 {
+                for r in 0..4 {
+
+                    let out_pixel = lut.map(RgbPixel {
+                        red: r as f64 / 3.0,
+                        green: g as f64 / 3.0,
+                        blue: b as f64 / 3.0,
+                    });
+                    let table_pixel = lut.get(r, g, b);
+
+                    assert_approx_eq!(out_pixel.red, table_pixel.red, DIFF);
+                    assert_approx_eq!(out_pixel.green, table_pixel.green, DIFF);
+                    assert_approx_eq!(out_pixel.blue, table_pixel.blue, DIFF);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_lut_3d_trilinear_multilinear() {
+
+    let mut lut = Lut3d::from_fn(2, |pixel| RgbPixel::new_y(pixel.red * pixel.green));
+
+    lut.interpolation = InterpolationMethod::Trilinear;
+
+    assert_approx_eq!(lut.map(RgbPixel::new_rgb(0.5, 0.25, 0.0)).red, 0.125, DIFF);
+    assert_approx_eq!(lut.map(RgbPixel::new_rgb(0.3, 0.7, 0.9)).red, 0.21, DIFF);
+}
+
+#[test]
+fn test_lut_3d_tetrahedral_simplex() {
+
+    let lut = Lut3d::from_fn(2, |pixel| RgbPixel::new_y(pixel.red * pixel.green));
+
+    assert_eq!(lut.interpolation, InterpolationMethod::Tetrahedral);
+
+    // (0.5, 0.25, 0.0) falls in the tetrahedron spanned by black, red, yellow, and white.
+    assert_approx_eq!(lut.map(RgbPixel::new_rgb(0.5, 0.25, 0.0)).red, 0.25, DIFF);
+
+    // (0.25, 0.5, 0.75) falls in the tetrahedron spanned by black, blue, cyan, and white.
+    assert_approx_eq!(lut.map(RgbPixel::new_rgb(0.25, 0.5, 0.75)).red, 0.25, DIFF);
+}
+
+#[test]
+fn test_lut_3d_interpolation_domain() {
+
+    let mut lut = Lut3d::from_fn(3, |pixel| pixel);
+
+    lut.set_domain(RgbPixel::new_y(0.0), RgbPixel::new_y(2.0));
+
+    assert_approx_eq!(lut.map(RgbPixel::new_y(1.0)).red, 0.5, DIFF);
+    assert_approx_eq!(lut.map(RgbPixel::new_y(3.0)).green, 1.0, DIFF);
+    assert_approx_eq!(lut.map(RgbPixel::new_y(-1.0)).blue, 0.0, DIFF);
+}
+
+#[test]
+#[should_panic]
+fn test_lut_3d_empty_domain() {
+    Lut3d::from_fn(3, |pixel| pixel)
+        .set_domain(RgbPixel::new_y(0.0), RgbPixel::new_rgb(1.0, 0.0, 1.0));
+}
+
+#[test]
+fn test_lut_3d_pq_hlg_neutral_accuracy() {
+
+    let mapper = PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb);
+    let mut lut = Lut3d::from_mapper(&mapper, 64);
+
+    for (method, max_error) in [
+        (InterpolationMethod::Trilinear, 0.01),
+        (InterpolationMethod::Tetrahedral, 0.0015),
+    ] {
+
+        lut.interpolation = method;
+
+        for i in 0..1_000 {
+
+            let in_pixel = RgbPixel::new_y((i as f64 + 0.37) / 1_000.0);
+            let expected = mapper.map(in_pixel);
+            let actual = lut.map(in_pixel);
+
+            assert_approx_eq!(actual.red, expected.red, max_error);
+            assert_approx_eq!(actual.green, expected.green, max_error);
+            assert_approx_eq!(actual.blue, expected.blue, max_error);
+        }
+    }
+}
+
+#[test]
+fn test_lut_1d_linear() {
+
+    let mut lut = Lut1d::from_fn(3, |x| x * x);
+
+    assert_approx_eq!(lut.map(RgbPixel::new_y(0.25)).red, 0.125, DIFF);
+    assert_approx_eq!(lut.map(RgbPixel::new_y(0.75)).green, 0.625, DIFF);
+    assert_approx_eq!(lut.map(RgbPixel::new_y(1.5)).blue, 1.0, DIFF);
+
+    lut.set_domain(RgbPixel::new_y(0.0), RgbPixel::new_y(2.0));
+
+    assert_approx_eq!(lut.map(RgbPixel::new_y(1.5)).blue, 0.625, DIFF);
+}
+
+#[test]
+#[should_panic]
+fn test_lut_1d_inverted_domain() {
+    Lut1d::from_fn(3, |x| x).set_domain(RgbPixel::new_y(1.0), RgbPixel::new_y(0.0));
+}
+
+#[test]
+fn test_shaped_lut_shaper() {
+
+    let mapper = PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb);
+    let lut = ShapedLut::from_mapper(&mapper, 1_024, 17);
+    let table = lut.shaper.table();
+
+    assert_eq!(lut.shaper.size(), 1_024);
+    assert_approx_eq!(table[0].red, 0.0, DIFF);
+    assert_approx_eq!(table[1_023].red, 1.0, DIFF);
+
+    for i in 1..1_024 {
+        assert_gt!(table[i].red, table[i - 1].red);
+    }
+
+    // The HLG OETF bends hardest in the shadows, so they should receive extra lattice points.
+    assert_gt!(lut.shaper.map(RgbPixel::new_y(0.1)).red, 0.1);
+}
+
+#[test]
+fn test_shaped_lut_linear_mapper() {
+
+    let lut = ShapedLut::from_mapper(&Lut3d::from_fn(2, |pixel| pixel), 256, 9);
+
+    for i in 0..=100 {
+
+        let in_pixel = RgbPixel::new_rgb(i as f64 / 100.0, 1.0 - i as f64 / 100.0, 0.5);
+        let out_pixel = lut.map(in_pixel);
+
+        assert_approx_eq!(out_pixel.red, in_pixel.red, DIFF);
+        assert_approx_eq!(out_pixel.green, in_pixel.green, DIFF);
+        assert_approx_eq!(out_pixel.blue, in_pixel.blue, DIFF);
+    }
+}
+
+#[test]
+fn test_shaped_lut_pq_hlg_neutral_accuracy() {
+
+    let mapper = PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb);
+    let to_ictcp = |pixel| OutputSignal::Hlg.to_display_linear(pixel).bt2020_to_ictcp();
+
+    for size in [17, 33, 64] {
+
+        let plain = Lut3d::from_mapper(&mapper, size);
+        let shaped = ShapedLut::from_mapper(&mapper, 1_024, size);
+        let mut plain_error = 0.0_f64;
+        let mut shaped_error = 0.0_f64;
+
+        for i in 0..1_000 {
+
+            let in_pixel = RgbPixel::new_y((i as f64 + 0.5) / 1_000.0);
+            let expected = to_ictcp(mapper.map(in_pixel));
+
+            plain_error = plain_error.max(expected.delta_e_itp(&to_ictcp(plain.map(in_pixel))));
+            shaped_error = shaped_error.max(expected.delta_e_itp(&to_ictcp(shaped.map(in_pixel))));
+        }
+
+        assert_lt!(shaped_error, plain_error);
+    }
+}
+
+#[test]
+fn test_lut_1d_from_fn_with_domain() {
+
+    let lut = Lut1d::from_fn_with_domain(5, 1.0, 3.0, |x| x * x);
+
+    assert_eq!(lut.domain_min(), RgbPixel::new_y(1.0));
+    assert_eq!(lut.domain_max(), RgbPixel::new_y(3.0));
+    assert_eq!(lut.get(1), RgbPixel::new_y(2.25));
+    assert_approx_eq!(lut.map(RgbPixel::new_y(2.0)).red, 4.0, DIFF);
+}
@@ -0,0 +1,70 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::Lut3d;
+use std::io::{Result, Write};
+
+pub const BIT_DEPTHS: [u32; 3] = [10, 12, 16];
+
+//
+// Input code values in the mesh header line are always 10-bit, as that is what both Autodesk
+// and DaVinci Resolve expect regardless of the output bit depth.
+//
+
+const INPUT_BIT_DEPTH: u32 = 10;
+
+impl Lut3d {
+
+    pub fn write_3dl(&self, output: &mut dyn Write, bit_depth: u32) -> Result<()> {
+
+        assert!(BIT_DEPTHS.contains(&bit_depth), "Unsupported 3DL bit depth: {}", bit_depth);
+
+        let size = self.size();
+        let last = (size - 1) as f64;
+        let input_max = ((1 << INPUT_BIT_DEPTH) - 1) as f64;
+        let output_max = ((1_u32 << bit_depth) - 1) as f64;
+        let quantize = |x: f64| (x.clamp(0.0, 1.0) * output_max).round() as u32;
+
+        for comment in self.comments.iter() {
+            writeln!(output, "# {}", comment)?;
+        }
+
+        // Lustre only understands meshes with a power of two plus one points per dimension.
+        if (size - 1).is_power_of_two() {
+            writeln!(output, "3DMESH")?;
+            writeln!(output, "Mesh {} {}", (size - 1).trailing_zeros(), bit_depth)?;
+        }
+
+        let mesh = (0..size)
+            .map(|i| ((i as f64 / last * input_max).round() as u32).to_string())
+            .collect::<Vec<String>>();
+
+        writeln!(output, "{}", mesh.join(" "))?;
+
+        // Unlike the Cube format, the blue index moves fastest.
+        for r in 0..size {
+            for g in 0..size {
+                for b in 0..size {
+                    let pixel = self.get(r, g, b);
+                    writeln!(output, "{} {} {}",
+                        quantize(pixel.red),
+                        quantize(pixel.green),
+                        quantize(pixel.blue),
+                    )?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use crate::pixel::RgbPixel;
+
+fn write(lut: &Lut3d, bit_depth: u32) -> String {
+
+    let mut output = vec![0_u8; 0];
+
+    lut.write_3dl(&mut output, bit_depth).unwrap();
+
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_write_3dl_12_bit() {
+
+    let mut lut = Lut3d::from_fn(2, |pixel| pixel * 0.5);
+
+    lut.comments.push(String::from("Generated by test"));
+
+    assert_eq!(
+        write(&lut, 12),
+        "# Generated by test\n\
+        3DMESH\n\
+        Mesh 0 12\n\
+        0 1023\n\
+        0 0 0\n\
+        0 0 2048\n\
+        0 2048 0\n\
+        0 2048 2048\n\
+        2048 0 0\n\
+        2048 0 2048\n\
+        2048 2048 0\n\
+        2048 2048 2048\n",
+    );
+}
+
+#[test]
+fn test_write_3dl_bit_depths() {
+
+    let lut = Lut3d::from_fn(2, |pixel| pixel);
+
+    assert!(write(&lut, 10).ends_with("1023 1023 1023\n"));
+    assert!(write(&lut, 12).ends_with("4095 4095 4095\n"));
+    assert!(write(&lut, 16).ends_with("65535 65535 65535\n"));
+}
+
+#[test]
+fn test_write_3dl_mesh() {
+
+    let lut = Lut3d::from_fn(33, |pixel| pixel);
+    let text = write(&lut, 12);
+    let mut lines = text.lines();
+
+    assert_eq!(lines.next(), Some("3DMESH"));
+    assert_eq!(lines.next(), Some("Mesh 5 12"));
+    assert!(lines.next().unwrap().starts_with("0 32 64 96 128 "));
+    assert_eq!(lines.count(), 33 * 33 * 33);
+}
+
+#[test]
+fn test_write_3dl_arbitrary_size() {
+
+    let lut = Lut3d::from_fn(4, |pixel| pixel);
+    let text = write(&lut, 10);
+    let mut lines = text.lines();
+
+    assert_eq!(lines.next(), Some("0 341 682 1023"));
+    assert_eq!(lines.next(), Some("0 0 0"));
+    assert_eq!(lines.next(), Some("0 0 341"));
+    assert_eq!(lines.count(), 4 * 4 * 4 - 2);
+}
+
+#[test]
+fn test_write_3dl_clamps() {
+
+    let lut = Lut3d::from_fn(2, |pixel| pixel * 2.0 - RgbPixel::new_y(0.5));
+
+    assert!(write(&lut, 10).contains("\n0 0 0\n"));
+    assert!(write(&lut, 10).ends_with("1023 1023 1023\n"));
+}
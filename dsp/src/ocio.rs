@@ -0,0 +1,218 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{clf::rgb_matrix, pixel::RgbPixel};
+use std::io::{Result, Write};
+
+pub const DISPLAY_REFERENCE: &str = "CIE-XYZ-D65";
+
+//
+// The display reference is CIE XYZ with 1.0 representing 100 nits, which is the convention
+// used by the OCIO built-in configs. PQ display light from `pq_eotf` is normalized such that
+// 1.0 represents 10,000 nits, so it is scaled up by 100 on the way in.
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OcioSpace {
+    Pq,
+    Hlg,
+    Sdr,
+}
+
+impl OcioSpace {
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OcioSpace::Pq => "BT.2100 PQ",
+            OcioSpace::Hlg => "BT.2100 HLG",
+            OcioSpace::Sdr => "BT.709 SDR",
+        }
+    }
+
+    fn display(&self) -> &'static str {
+        match self {
+            OcioSpace::Pq => "HDR PQ",
+            OcioSpace::Hlg => "HDR HLG",
+            OcioSpace::Sdr => "SDR",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcioLook {
+    pub name: String,
+    pub description: String,
+    pub process_space: OcioSpace,
+    pub output_space: OcioSpace,
+    pub file: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OcioConfig {
+    pub description: String,
+    pub search_path: String,
+    // 1D LUT of `pq_eotf` over 0.0 to 1.0
+    pub pq_eotf_file: String,
+    // HLG to PQ conversion for a 1,000 nit display
+    pub hlg_pq_file: String,
+    pub looks: Vec<OcioLook>,
+}
+
+impl OcioConfig {
+
+    pub fn write_config(&self, output: &mut dyn Write) -> Result<()> {
+
+        writeln!(output, "ocio_profile_version: 2")?;
+        writeln!(output)?;
+        writeln!(output, "description: {}", quote(&self.description))?;
+        writeln!(output, "search_path: {}", quote(&self.search_path))?;
+        writeln!(output, "strictparsing: true")?;
+        writeln!(output, "luma: [{}, {}, {}]",
+            RgbPixel::new_rgb(1.0, 0.0, 0.0).y_bt2020(),
+            RgbPixel::new_rgb(0.0, 1.0, 0.0).y_bt2020(),
+            RgbPixel::new_rgb(0.0, 0.0, 1.0).y_bt2020(),
+        )?;
+        writeln!(output)?;
+        writeln!(output, "roles:")?;
+        writeln!(output, "  default: Raw")?;
+        writeln!(output)?;
+        writeln!(output, "file_rules:")?;
+        writeln!(output, "  - !<Rule> {{name: Default, colorspace: default}}")?;
+        writeln!(output)?;
+
+        writeln!(output, "displays:")?;
+        for space in [OcioSpace::Pq, OcioSpace::Hlg, OcioSpace::Sdr] {
+            writeln!(output, "  {}:", quote(space.display()))?;
+            writeln!(output, "    - !<View> {{name: Standard, colorspace: {}}}",
+                quote(space.name()))?;
+            for look in self.looks.iter().filter(|look| look.process_space == space) {
+                writeln!(output, "    - !<View> {{name: {}, colorspace: {}, looks: {}}}",
+                    quote(&look.name),
+                    quote(space.name()),
+                    quote(&look.name),
+                )?;
+            }
+        }
+        writeln!(output)?;
+        writeln!(output, "active_displays: []")?;
+        writeln!(output, "active_views: []")?;
+        writeln!(output)?;
+
+        //
+        // A look must map its process space back onto itself, so each conversion is followed by
+        // the transform from its output space back into its process space. This shows what the
+        // converted video will look like on the original display.
+        //
+
+        writeln!(output, "looks:")?;
+        for look in self.looks.iter() {
+            writeln!(output, "  - !<Look>")?;
+            writeln!(output, "    name: {}", quote(&look.name))?;
+            writeln!(output, "    process_space: {}", quote(look.process_space.name()))?;
+            writeln!(output, "    description: {}", quote(&look.description))?;
+            writeln!(output, "    transform: !<GroupTransform>")?;
+            writeln!(output, "      children:")?;
+            writeln!(output,
+                "        - !<FileTransform> {{src: {}, interpolation: tetrahedral}}",
+                quote(&look.file),
+            )?;
+            writeln!(output, "        - !<ColorSpaceTransform> {{src: {}, dst: {}}}",
+                quote(look.output_space.name()),
+                quote(look.process_space.name()),
+            )?;
+        }
+        writeln!(output)?;
+
+        writeln!(output, "display_colorspaces:")?;
+        writeln!(output, "  - !<ColorSpace>")?;
+        writeln!(output, "    name: {}", DISPLAY_REFERENCE)?;
+        writeln!(output, "    description: \"Display reference; 1.0 is 100 nits\"")?;
+        writeln!(output, "    isdata: false")?;
+        writeln!(output, "    encoding: display-linear")?;
+        writeln!(output)?;
+
+        let bt2020_to_xyz = rgb_matrix(|pixel| {
+            let xyz = pixel.bt2020_to_xyz();
+            RgbPixel::new_rgb(xyz.x, xyz.y, xyz.z) * 100.0
+        });
+        let bt709_to_xyz = rgb_matrix(|pixel| {
+            let xyz = pixel.bt709_to_xyz();
+            RgbPixel::new_rgb(xyz.x, xyz.y, xyz.z)
+        });
+
+        write_space_header(output, OcioSpace::Pq, "ITU-R BT.2100 PQ (ST 2084) with BT.2020 \
+            primaries")?;
+        writeln!(output, "        - !<FileTransform> {{src: {}, interpolation: linear}}",
+            quote(&self.pq_eotf_file))?;
+        write_matrix(output, &bt2020_to_xyz)?;
+        writeln!(output)?;
+
+        write_space_header(output, OcioSpace::Hlg, "ITU-R BT.2100 HLG on a 1,000 nit display \
+            with BT.2020 primaries")?;
+        writeln!(output, "        - !<FileTransform> {{src: {}, interpolation: tetrahedral}}",
+            quote(&self.hlg_pq_file))?;
+        writeln!(output, "        - !<FileTransform> {{src: {}, interpolation: linear}}",
+            quote(&self.pq_eotf_file))?;
+        write_matrix(output, &bt2020_to_xyz)?;
+        writeln!(output)?;
+
+        write_space_header(output, OcioSpace::Sdr, "ITU-R BT.709 primaries with the BT.1886 \
+            EOTF on a 100 nit display")?;
+        writeln!(output, "        - !<ExponentTransform> {{value: [2.4, 2.4, 2.4, 1], \
+            style: pass_thru}}")?;
+        write_matrix(output, &bt709_to_xyz)?;
+        writeln!(output)?;
+
+        writeln!(output, "colorspaces:")?;
+        writeln!(output, "  - !<ColorSpace>")?;
+        writeln!(output, "    name: Raw")?;
+        writeln!(output, "    description: \"Non-color data\"")?;
+        writeln!(output, "    isdata: true")?;
+        writeln!(output, "    encoding: data")
+    }
+}
+
+fn write_space_header(
+    output: &mut dyn Write,
+    space: OcioSpace,
+    description: &str,
+) -> Result<()> {
+
+    writeln!(output, "  - !<ColorSpace>")?;
+    writeln!(output, "    name: {}", quote(space.name()))?;
+    writeln!(output, "    description: {}", quote(description))?;
+    writeln!(output, "    isdata: false")?;
+    writeln!(output, "    encoding: {}", match space {
+        OcioSpace::Pq | OcioSpace::Hlg => "hdr-video",
+        OcioSpace::Sdr => "sdr-video",
+    })?;
+    writeln!(output, "    to_display_reference: !<GroupTransform>")?;
+    writeln!(output, "      children:")
+}
+
+fn write_matrix(output: &mut dyn Write, matrix: &[[f64; 3]; 3]) -> Result<()> {
+
+    // OCIO matrices are 4x4 and row major.
+    let values = matrix
+        .iter()
+        .flat_map(|row| [row[0], row[1], row[2], 0.0])
+        .chain([0.0, 0.0, 0.0, 1.0])
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>();
+
+    writeln!(output, "        - !<MatrixTransform> {{matrix: [{}]}}", values.join(", "))
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
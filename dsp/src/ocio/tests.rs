@@ -0,0 +1,113 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+
+fn config() -> OcioConfig {
+    OcioConfig {
+        description: String::from("Test \"config\""),
+        search_path: String::from("luts"),
+        pq_eotf_file: String::from("pq_eotf.spi1d"),
+        hlg_pq_file: String::from("hlg_1000_nits.clf"),
+        looks: vec![
+            OcioLook {
+                name: String::from("PQ to HLG (max-cll 4000)"),
+                description: String::from("Test look"),
+                process_space: OcioSpace::Pq,
+                output_space: OcioSpace::Hlg,
+                file: String::from("pq_hlg.clf"),
+            },
+        ],
+    }
+}
+
+fn write(config: &OcioConfig) -> String {
+
+    let mut output = Vec::new();
+
+    config.write_config(&mut output).unwrap();
+
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_write_config_header() {
+
+    let text = write(&config());
+
+    assert!(text.starts_with("ocio_profile_version: 2\n\ndescription: \"Test \\\"config\\\"\"\n"));
+    assert!(text.contains("\nsearch_path: \"luts\"\n"));
+    assert!(text.contains("\nluma: [0.2627, 0.678, 0.0593]\n"));
+    assert!(text.contains("\nroles:\n  default: Raw\n"));
+}
+
+#[test]
+fn test_write_config_displays() {
+
+    let text = write(&config());
+
+    assert!(text.contains(
+        "  \"HDR PQ\":\n\
+        \x20   - !<View> {name: Standard, colorspace: \"BT.2100 PQ\"}\n\
+        \x20   - !<View> {name: \"PQ to HLG (max-cll 4000)\", colorspace: \"BT.2100 PQ\", \
+            looks: \"PQ to HLG (max-cll 4000)\"}\n\
+        \x20 \"HDR HLG\":\n\
+        \x20   - !<View> {name: Standard, colorspace: \"BT.2100 HLG\"}\n"
+    ));
+}
+
+#[test]
+fn test_write_config_looks() {
+
+    let text = write(&config());
+
+    assert!(text.contains(
+        "  - !<Look>\n\
+        \x20   name: \"PQ to HLG (max-cll 4000)\"\n\
+        \x20   process_space: \"BT.2100 PQ\"\n\
+        \x20   description: \"Test look\"\n\
+        \x20   transform: !<GroupTransform>\n\
+        \x20     children:\n\
+        \x20       - !<FileTransform> {src: \"pq_hlg.clf\", interpolation: tetrahedral}\n\
+        \x20       - !<ColorSpaceTransform> {src: \"BT.2100 HLG\", dst: \"BT.2100 PQ\"}\n"
+    ));
+}
+
+#[test]
+fn test_write_config_color_spaces() {
+
+    let text = write(&config());
+
+    for name in ["\"BT.2100 PQ\"", "\"BT.2100 HLG\"", "\"BT.709 SDR\"", "CIE-XYZ-D65", "Raw"] {
+        assert!(text.contains(&format!("    name: {}\n", name)), "{} is missing", name);
+    }
+
+    assert_eq!(text.matches("{src: \"pq_eotf.spi1d\", interpolation: linear}").count(), 2);
+    assert_eq!(
+        text.matches("{src: \"hlg_1000_nits.clf\", interpolation: tetrahedral}").count(),
+        1,
+    );
+    assert_eq!(text.matches("!<MatrixTransform>").count(), 3);
+}
+
+#[test]
+fn test_write_matrix() {
+
+    let mut output = Vec::new();
+
+    write_matrix(&mut output, &[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]]).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "        - !<MatrixTransform> {matrix: [1, 2, 3, 0, 4, 5, 6, 0, 7, 8, 9, 0, 0, 0, 0, 1]}\n",
+    );
+}
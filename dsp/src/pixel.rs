@@ -11,7 +11,8 @@
 #[cfg(test)]
 mod tests;
 
-use std::ops::{Mul, MulAssign};
+use super::tf::{bt2020_ioetf, bt2020_oetf, pq_ieotf};
+use std::ops::{Add, Mul, MulAssign, Sub};
 
 pub const RED_FACTOR_2020: f64 = 0.2627;
 pub const GREEN_FACTOR_2020: f64 = 0.6780;
@@ -19,6 +20,9 @@ pub const BLUE_FACTOR_2020: f64 = 0.0593;
 pub const RED_FACTOR_709: f64 = 0.2126;
 pub const GREEN_FACTOR_709: f64 = 0.7152;
 pub const BLUE_FACTOR_709: f64 = 0.0722;
+pub const RED_FACTOR_601: f64 = 0.299;
+pub const GREEN_FACTOR_601: f64 = 0.587;
+pub const BLUE_FACTOR_601: f64 = 0.114;
 
 //
 // RGB
@@ -87,6 +91,70 @@ impl RgbPixel {
         }
     }
 
+    pub fn bt2020_to_ictcp(&self) -> IctcpPixel {
+
+        //
+        // ITU-R BT.2100-2
+        // Table 6
+        //
+        // Input is display light normalized such that 1.0 represents 10,000 nits.
+        //
+
+        let l = pq_ieotf(
+            (1688.0 * self.red + 2146.0 * self.green + 262.0 * self.blue) / 4096.0
+        );
+        let m = pq_ieotf(
+            (683.0 * self.red + 2951.0 * self.green + 462.0 * self.blue) / 4096.0
+        );
+        let s = pq_ieotf(
+            (99.0 * self.red + 309.0 * self.green + 3688.0 * self.blue) / 4096.0
+        );
+
+        IctcpPixel {
+            i: 0.5 * l + 0.5 * m,
+            ct: (6610.0 * l - 13613.0 * m + 7003.0 * s) / 4096.0,
+            cp: (17933.0 * l - 17390.0 * m - 543.0 * s) / 4096.0,
+        }
+    }
+
+    pub fn to_ycbcr(&self, matrix: YcbcrMatrix) -> YcbcrPixel {
+        match matrix {
+            YcbcrMatrix::Bt2020Cl => {
+
+                //
+                // ITU-R BT.2020-2
+                // Page 4
+                // Table 4
+                //
+
+                let y = bt2020_oetf(
+                    RED_FACTOR_2020 * bt2020_ioetf(self.red)
+                        + GREEN_FACTOR_2020 * bt2020_ioetf(self.green)
+                        + BLUE_FACTOR_2020 * bt2020_ioetf(self.blue)
+                );
+                let b_y = self.blue - y;
+                let r_y = self.red - y;
+
+                YcbcrPixel {
+                    y,
+                    cb: if b_y <= 0.0 { b_y / 1.9404 } else { b_y / 1.5816 },
+                    cr: if r_y <= 0.0 { r_y / 1.7184 } else { r_y / 0.9936 },
+                }
+            }
+            _ => {
+
+                let (kr, kg, kb) = matrix.factors();
+                let y = kr * self.red + kg * self.green + kb * self.blue;
+
+                YcbcrPixel {
+                    y,
+                    cb: (self.blue - y) / (2.0 * (1.0 - kb)),
+                    cr: (self.red - y) / (2.0 * (1.0 - kr)),
+                }
+            }
+        }
+    }
+
     pub fn bt709_to_xyz(&self) -> XyzPixel {
         XyzPixel {
             x: 0.4124564 * self.red
@@ -102,6 +170,32 @@ impl RgbPixel {
     }
 }
 
+impl Add for RgbPixel {
+
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            red: self.red + rhs.red,
+            green: self.green + rhs.green,
+            blue: self.blue + rhs.blue,
+        }
+    }
+}
+
+impl Sub for RgbPixel {
+
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            red: self.red - rhs.red,
+            green: self.green - rhs.green,
+            blue: self.blue - rhs.blue,
+        }
+    }
+}
+
 impl Mul<f64> for RgbPixel {
 
     type Output = Self;
@@ -118,6 +212,87 @@ impl MulAssign<f64> for RgbPixel {
     }
 }
 
+//
+// YCbCr
+//
+// Y' runs from 0.0 to 1.0 while Cb and Cr run from -0.5 to 0.5, leaving quantization to the
+// caller. The constant luminance matrix derives its luma from linear light through the BT.2020
+// transfer function, so it is only meaningful for signals encoded with that function.
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum YcbcrMatrix {
+    Bt2020Ncl,
+    Bt2020Cl,
+    Bt709,
+    Bt601,
+}
+
+impl YcbcrMatrix {
+
+    // red, green, and blue luma coefficients
+    pub fn factors(&self) -> (f64, f64, f64) {
+        match self {
+            YcbcrMatrix::Bt2020Ncl | YcbcrMatrix::Bt2020Cl => {
+                (RED_FACTOR_2020, GREEN_FACTOR_2020, BLUE_FACTOR_2020)
+            }
+            YcbcrMatrix::Bt709 => {
+                (RED_FACTOR_709, GREEN_FACTOR_709, BLUE_FACTOR_709)
+            }
+            YcbcrMatrix::Bt601 => {
+                (RED_FACTOR_601, GREEN_FACTOR_601, BLUE_FACTOR_601)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct YcbcrPixel {
+    pub y: f64,
+    pub cb: f64,
+    pub cr: f64,
+}
+
+impl YcbcrPixel {
+
+    pub fn new(y: f64, cb: f64, cr: f64) -> Self {
+        Self { y, cb, cr }
+    }
+
+    pub fn to_rgb(&self, matrix: YcbcrMatrix) -> RgbPixel {
+        match matrix {
+            YcbcrMatrix::Bt2020Cl => {
+
+                let blue = self.y + if self.cb <= 0.0 { 1.9404 } else { 1.5816 } * self.cb;
+                let red = self.y + if self.cr <= 0.0 { 1.7184 } else { 0.9936 } * self.cr;
+                let green = (
+                    bt2020_ioetf(self.y)
+                        - RED_FACTOR_2020 * bt2020_ioetf(red)
+                        - BLUE_FACTOR_2020 * bt2020_ioetf(blue)
+                ) / GREEN_FACTOR_2020;
+
+                RgbPixel {
+                    red,
+                    green: bt2020_oetf(green),
+                    blue,
+                }
+            }
+            _ => {
+
+                let (kr, kg, kb) = matrix.factors();
+                let red = self.y + 2.0 * (1.0 - kr) * self.cr;
+                let blue = self.y + 2.0 * (1.0 - kb) * self.cb;
+
+                RgbPixel {
+                    red,
+                    green: (self.y - kr * red - kb * blue) / kg,
+                    blue,
+                }
+            }
+        }
+    }
+}
+
 //
 // XYZ
 //
@@ -198,6 +373,7 @@ impl OklabPixel {
         }
     }
 
+    #[allow(clippy::excessive_precision)]
     pub fn to_xyz(&self) -> XyzPixel {
 
         let l_ =
@@ -245,3 +421,31 @@ impl Mul<f64> for OklabPixel {
         }
     }
 }
+
+//
+// ICtCp
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IctcpPixel {
+    pub i: f64,
+    pub ct: f64,
+    pub cp: f64,
+}
+
+impl IctcpPixel {
+
+    pub fn delta_e_itp(&self, other: &Self) -> f64 {
+
+        //
+        // ITU-R BT.2124-0
+        // Equation 1
+        //
+
+        let i = self.i - other.i;
+        let t = 0.5 * (self.ct - other.ct);
+        let p = self.cp - other.cp;
+
+        720.0 * (i * i + t * t + p * p).sqrt()
+    }
+}
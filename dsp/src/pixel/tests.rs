@@ -11,6 +11,7 @@
  */
 
 use super::*;
+use crate::tf::bt2020_oetf;
 use assert_approx_eq::assert_approx_eq;
 
 const DIFF_2020: f64 = 0.00000000000001;
@@ -63,3 +64,111 @@ fn test_rgb_xyz_round_trip_bt709() {
         }
     }
 }
+
+#[test]
+fn test_ictcp_white() {
+
+    let ictcp = RgbPixel::new_y(1.0).bt2020_to_ictcp();
+
+    assert_approx_eq!(ictcp.i, 1.0, DIFF_2020);
+    assert_approx_eq!(ictcp.ct, 0.0, DIFF_2020);
+    assert_approx_eq!(ictcp.cp, 0.0, DIFF_2020);
+}
+
+#[test]
+fn test_delta_e_itp() {
+
+    let a = IctcpPixel { i: 0.5, ct: 0.0, cp: 0.0 };
+    let b = IctcpPixel { i: 0.5, ct: 0.02, cp: 0.0 };
+    let c = IctcpPixel { i: 0.5, ct: 0.0, cp: 0.01 };
+
+    assert_approx_eq!(a.delta_e_itp(&a), 0.0, DIFF_2020);
+    assert_approx_eq!(a.delta_e_itp(&b), 7.2, DIFF_2020);
+    assert_approx_eq!(a.delta_e_itp(&c), 7.2, DIFF_2020);
+    assert_approx_eq!(RgbPixel::new_y(1.0).bt2020_to_ictcp().delta_e_itp(&a), 360.0, DIFF_709);
+}
+
+#[test]
+fn test_ycbcr_round_trip() {
+
+    const SIZE: usize = 32;
+
+    for matrix in [
+        YcbcrMatrix::Bt2020Ncl, YcbcrMatrix::Bt2020Cl, YcbcrMatrix::Bt709, YcbcrMatrix::Bt601,
+    ] {
+        for b in 0..=SIZE {
+            for g in 0..=SIZE {
+                for r in 0..=SIZE {
+
+                    let in_pixel = RgbPixel::new_rgb(
+                        r as f64 / SIZE as f64,
+                        g as f64 / SIZE as f64,
+                        b as f64 / SIZE as f64,
+                    );
+                    let ycbcr = in_pixel.to_ycbcr(matrix);
+                    let out_pixel = ycbcr.to_rgb(matrix);
+
+                    assert!((0.0..=1.0).contains(&ycbcr.y));
+                    assert!((-0.5002..=0.5002).contains(&ycbcr.cb));
+                    assert!((-0.5002..=0.5002).contains(&ycbcr.cr));
+                    assert_approx_eq!(out_pixel.red, in_pixel.red, DIFF_2020);
+                    assert_approx_eq!(out_pixel.green, in_pixel.green, DIFF_2020);
+                    assert_approx_eq!(out_pixel.blue, in_pixel.blue, DIFF_2020);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_ycbcr_neutral() {
+    for matrix in [
+        YcbcrMatrix::Bt2020Ncl, YcbcrMatrix::Bt2020Cl, YcbcrMatrix::Bt709, YcbcrMatrix::Bt601,
+    ] {
+        for y in [0.0, 0.25, 1.0] {
+
+            let ycbcr = RgbPixel::new_y(y).to_ycbcr(matrix);
+
+            assert_approx_eq!(ycbcr.y, y, DIFF_2020);
+            assert_approx_eq!(ycbcr.cb, 0.0, DIFF_2020);
+            assert_approx_eq!(ycbcr.cr, 0.0, DIFF_2020);
+        }
+    }
+}
+
+#[test]
+fn test_ycbcr_primaries() {
+
+    let red_709 = RgbPixel::new_rgb(1.0, 0.0, 0.0).to_ycbcr(YcbcrMatrix::Bt709);
+    let blue_601 = RgbPixel::new_rgb(0.0, 0.0, 1.0).to_ycbcr(YcbcrMatrix::Bt601);
+    let green_2020 = RgbPixel::new_rgb(0.0, 1.0, 0.0).to_ycbcr(YcbcrMatrix::Bt2020Ncl);
+
+    assert_approx_eq!(red_709.y, 0.2126, DIFF_2020);
+    assert_approx_eq!(red_709.cb, -0.11457210605733996, DIFF_2020);
+    assert_approx_eq!(red_709.cr, 0.5, DIFF_2020);
+    assert_approx_eq!(blue_601.y, 0.114, DIFF_2020);
+    assert_approx_eq!(blue_601.cb, 0.5, DIFF_2020);
+    assert_approx_eq!(blue_601.cr, -0.08131241084165478, DIFF_2020);
+    assert_approx_eq!(green_2020.y, 0.678, DIFF_2020);
+    assert_approx_eq!(green_2020.cb, -0.3603699372807484, DIFF_2020);
+    assert_approx_eq!(green_2020.cr, -0.45978570459785706, DIFF_2020);
+}
+
+#[test]
+fn test_ycbcr_constant_luminance() {
+
+    let red = RgbPixel::new_rgb(1.0, 0.0, 0.0).to_ycbcr(YcbcrMatrix::Bt2020Cl);
+    let blue = RgbPixel::new_rgb(0.0, 0.0, 1.0).to_ycbcr(YcbcrMatrix::Bt2020Cl);
+    let yellow = RgbPixel::new_rgb(1.0, 1.0, 0.0).to_ycbcr(YcbcrMatrix::Bt2020Cl);
+
+    // Luma follows linear luminance rather than a weighted sum of the encoded channels.
+    assert_approx_eq!(red.y, bt2020_oetf(RED_FACTOR_2020), DIFF_2020);
+    assert_approx_eq!(blue.y, bt2020_oetf(BLUE_FACTOR_2020), DIFF_2020);
+    assert_approx_eq!(red.cr, (1.0 - red.y) / 0.9936, DIFF_2020);
+    assert_approx_eq!(blue.cb, (1.0 - blue.y) / 1.5816, DIFF_2020);
+    assert_approx_eq!(yellow.cb, -yellow.y / 1.9404, DIFF_2020);
+
+    // The rounded divisors of BT.2020 let the extremes overshoot 0.5 slightly.
+    assert_approx_eq!(blue.cb, 0.5, 0.001);
+    assert_approx_eq!(red.cr, 0.5, 0.001);
+}
@@ -0,0 +1,135 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{Mapper, pixel::RgbPixel};
+
+//
+// Quantization
+//
+// Signal values are normalized code values, meaning that 1.0 is the highest code value at the
+// given bit depth. In limited (narrow) range, black and nominal white sit at 16 and 235 scaled
+// up to the bit depth, which is 64 and 940 at 10 bits. Codes above nominal white remain legal up
+// to the last code below those reserved for timing references, which is 1019 at 10 bits.
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignalRange {
+    Full,
+    Limited,
+}
+
+pub fn max_code(bit_depth: u32) -> f64 {
+
+    assert!((8..=16).contains(&bit_depth), "Bit depth must be between 8 and 16");
+
+    ((1_u32 << bit_depth) - 1) as f64
+}
+
+pub fn limited_black(bit_depth: u32) -> f64 {
+    (16 << (bit_depth - 8)) as f64
+}
+
+pub fn limited_white(bit_depth: u32) -> f64 {
+    (235 << (bit_depth - 8)) as f64
+}
+
+pub fn limited_peak(bit_depth: u32) -> f64 {
+    max_code(bit_depth) - (1 << (bit_depth - 8)) as f64
+}
+
+pub fn quantize(value: f64, bit_depth: u32) -> u16 {
+    (value.clamp(0.0, 1.0) * max_code(bit_depth)).round() as u16
+}
+
+pub fn dequantize(code: u16, bit_depth: u32) -> f64 {
+    code as f64 / max_code(bit_depth)
+}
+
+pub fn limited_to_full(value: f64, bit_depth: u32) -> f64 {
+
+    let black = limited_black(bit_depth);
+    let white = limited_white(bit_depth);
+
+    (value * max_code(bit_depth) - black) / (white - black)
+}
+
+pub fn full_to_limited(value: f64, bit_depth: u32) -> f64 {
+
+    let black = limited_black(bit_depth);
+    let white = limited_white(bit_depth);
+
+    (black + value * (white - black)) / max_code(bit_depth)
+}
+
+//
+// Range Mapper
+//
+
+pub struct RangeMapper<'a> {
+    mapper: &'a dyn Mapper,
+    input: SignalRange,
+    output: SignalRange,
+    bit_depth: u32,
+    ceiling: f64,
+}
+
+impl<'a> RangeMapper<'a> {
+
+    pub fn new(
+        mapper: &'a dyn Mapper,
+        input: SignalRange,
+        output: SignalRange,
+        bit_depth: u32,
+        super_white: bool,
+    ) -> Self {
+
+        //
+        // Limited range input is otherwise clipped at nominal white. HLG, however, reserves
+        // the codes above it for highlights, so those are handed to the mapper as signal
+        // values beyond 1.0 instead.
+        //
+
+        let ceiling = if super_white {
+            limited_to_full(limited_peak(bit_depth) / max_code(bit_depth), bit_depth)
+        } else {
+            1.0
+        };
+
+        Self { mapper, input, output, bit_depth, ceiling }
+    }
+
+    pub fn map(&self, input: RgbPixel) -> RgbPixel {
+
+        let pixel = match self.input {
+            SignalRange::Full => input,
+            SignalRange::Limited => input.with_each_channel(|x| {
+                limited_to_full(x, self.bit_depth).clamp(0.0, self.ceiling)
+            }),
+        };
+        let output = self.mapper.map(pixel);
+
+        match self.output {
+            SignalRange::Full => output,
+            SignalRange::Limited => output.with_each_channel(|x| {
+                full_to_limited(x, self.bit_depth)
+            }),
+        }
+    }
+}
+
+impl Mapper for RangeMapper<'_> {
+
+    fn map(&self, input: RgbPixel) -> RgbPixel {
+        self.map(input)
+    }
+}
@@ -0,0 +1,133 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use crate::HlgPqMapper;
+use assert_approx_eq::assert_approx_eq;
+
+const DIFF: f64 = 0.0000000001;
+
+struct Identity;
+
+impl Mapper for Identity {
+
+    fn map(&self, input: RgbPixel) -> RgbPixel {
+        input
+    }
+}
+
+#[test]
+fn test_limited_levels() {
+
+    assert_eq!((limited_black(8), limited_white(8), limited_peak(8)), (16.0, 235.0, 254.0));
+    assert_eq!((limited_black(10), limited_white(10), limited_peak(10)), (64.0, 940.0, 1019.0));
+    assert_eq!((limited_black(12), limited_white(12), limited_peak(12)), (256.0, 3760.0, 4079.0));
+    assert_eq!(
+        (limited_black(16), limited_white(16), limited_peak(16)),
+        (4096.0, 60160.0, 65279.0),
+    );
+}
+
+#[test]
+fn test_limited_to_full() {
+    for bit_depth in [8, 10, 12, 16] {
+        let max = max_code(bit_depth);
+        assert_approx_eq!(limited_to_full(limited_black(bit_depth) / max, bit_depth), 0.0, DIFF);
+        assert_approx_eq!(limited_to_full(limited_white(bit_depth) / max, bit_depth), 1.0, DIFF);
+        assert!(limited_to_full(0.0, bit_depth) < 0.0);
+        assert!(limited_to_full(1.0, bit_depth) > 1.0);
+    }
+}
+
+#[test]
+fn test_range_cycle() {
+    for bit_depth in [8, 10, 12, 16] {
+        for i in 0..=100 {
+            let value = i as f64 / 100.0;
+            assert_approx_eq!(
+                limited_to_full(full_to_limited(value, bit_depth), bit_depth),
+                value,
+                DIFF
+            );
+        }
+    }
+}
+
+#[test]
+fn test_quantize() {
+
+    assert_eq!(quantize(full_to_limited(0.0, 10), 10), 64);
+    assert_eq!(quantize(full_to_limited(1.0, 10), 10), 940);
+    assert_eq!(quantize(full_to_limited(0.5, 8), 8), 126);
+    assert_eq!(quantize(full_to_limited(1.0, 16), 16), 60160);
+    assert_eq!(quantize(-0.5, 12), 0);
+    assert_eq!(quantize(1.5, 12), 4095);
+
+    for code in 0..1024 {
+        assert_eq!(quantize(dequantize(code, 10), 10), code);
+    }
+}
+
+#[test]
+fn test_range_mapper_full() {
+
+    let mapper = RangeMapper::new(&Identity, SignalRange::Full, SignalRange::Full, 10, true);
+    let pixel = RgbPixel::new_rgb(0.0, 0.5, 1.0);
+
+    assert_eq!(mapper.map(pixel), pixel);
+}
+
+#[test]
+fn test_range_mapper_limited() {
+
+    let mapper = RangeMapper::new(
+        &Identity, SignalRange::Limited, SignalRange::Limited, 10, false,
+    );
+
+    for code in 64..=940 {
+        assert_approx_eq!(mapper.map(RgbPixel::new_y(dequantize(code, 10))).red,
+            dequantize(code, 10), DIFF);
+    }
+
+    // Sub-black and super-white are clipped to the nominal levels.
+    assert_eq!(quantize(mapper.map(RgbPixel::new_y(0.0)).red, 10), 64);
+    assert_eq!(quantize(mapper.map(RgbPixel::new_y(1.0)).red, 10), 940);
+}
+
+#[test]
+fn test_range_mapper_super_white() {
+
+    let mapper = RangeMapper::new(&Identity, SignalRange::Limited, SignalRange::Full, 10, true);
+
+    assert_approx_eq!(mapper.map(RgbPixel::new_y(dequantize(940, 10))).red, 1.0, DIFF);
+    assert_approx_eq!(mapper.map(RgbPixel::new_y(dequantize(1019, 10))).red, 955.0 / 876.0, DIFF);
+    assert_approx_eq!(mapper.map(RgbPixel::new_y(1.0)).red, 955.0 / 876.0, DIFF);
+}
+
+#[test]
+fn test_range_mapper_hlg_super_white() {
+
+    //
+    // HLG super-white above code 940 must keep rising in brightness rather than being clipped
+    // to the nominal peak.
+    //
+
+    let hlg_pq = HlgPqMapper::new(1_000.0);
+    let clipped = RangeMapper::new(&hlg_pq, SignalRange::Limited, SignalRange::Full, 10, false);
+    let extended = RangeMapper::new(&hlg_pq, SignalRange::Limited, SignalRange::Full, 10, true);
+    let white = RgbPixel::new_y(dequantize(940, 10));
+    let super_white = RgbPixel::new_y(dequantize(1000, 10));
+
+    assert_approx_eq!(extended.map(white).red, hlg_pq.map(RgbPixel::new_y(1.0)).red, DIFF);
+    assert_approx_eq!(clipped.map(super_white).red, clipped.map(white).red, DIFF);
+    assert!(extended.map(super_white).red > extended.map(white).red);
+}
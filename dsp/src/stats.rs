@@ -0,0 +1,139 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::tf::pq_eotf;
+use std::sync::OnceLock;
+
+//
+// PQ Histogram
+//
+// Values are counted by their 10-bit PQ code value, which is as fine as HDR10 delivery ever gets
+// and keeps a histogram small enough to build one per frame and per thread.
+//
+
+pub const HISTOGRAM_BINS: usize = 1_024;
+
+pub const PERCENTILES: [f64; 5] = [50.0, 90.0, 99.0, 99.9, 99.99];
+
+// Histograms are compared in coarser bins of 16 code values each, so that a slight change in
+// brightness between two frames of the same shot only shifts counts within a bin.
+const COMPARISON_BINS: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PqHistogram {
+    counts: Vec<u64>,
+    total: u64,
+}
+
+impl PqHistogram {
+
+    pub fn new() -> Self {
+        Self { counts: vec![0; HISTOGRAM_BINS], total: 0 }
+    }
+
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    // Counts a PQ signal value from 0.0 to 1.0.
+    pub fn add_signal(&mut self, signal: f64) {
+        self.add_bin((signal.clamp(0.0, 1.0) * (HISTOGRAM_BINS - 1) as f64).round() as usize)
+    }
+
+    // Counts a display linear value from 0.0 to 1.0 (10,000 nits), which is binned against
+    // precomputed bin edges rather than running it through the PQ inverse EOTF.
+    pub fn add_linear(&mut self, linear: f64) {
+        self.add_bin(bin_edges().partition_point(|edge| *edge < linear))
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+
+        for (count, other_count) in self.counts.iter_mut().zip(other.counts.iter()) {
+            *count += other_count;
+        }
+
+        self.total += other.total;
+    }
+
+    // Returns the PQ signal value below which the given percentage of everything counted falls,
+    // or 0.0 if nothing has been counted.
+    pub fn percentile(&self, percentile: f64) -> f64 {
+
+        // The epsilon keeps something like 99.9% of 1,000 from rounding up to 1,000.
+        let threshold = (percentile / 100.0 * self.total as f64 - 1e-9).ceil().max(1.0) as u64;
+        let mut cumulative = 0;
+
+        for (bin, count) in self.counts.iter().enumerate() {
+            cumulative += count;
+            if cumulative >= threshold {
+                return bin_signal(bin)
+            }
+        }
+
+        0.0
+    }
+
+    // Returns the total variation distance between the two normalized histograms, which ranges
+    // from 0.0 when they match to 1.0 when they have nothing in common.
+    pub fn difference(&self, other: &Self) -> f64 {
+
+        if self.total == 0 || other.total == 0 {
+            return if self.total == other.total { 0.0 } else { 1.0 }
+        }
+
+        let width = HISTOGRAM_BINS / COMPARISON_BINS;
+        let share = |counts: &[u64], total: u64| counts.iter().sum::<u64>() as f64 / total as f64;
+
+        self.counts
+            .chunks(width)
+            .zip(other.counts.chunks(width))
+            .map(|(a, b)| (share(a, self.total) - share(b, other.total)).abs())
+            .sum::<f64>() / 2.0
+    }
+
+    pub fn percentile_nits(&self, percentile: f64) -> f64 {
+        pq_eotf(self.percentile(percentile)) * 10_000.0
+    }
+
+    fn add_bin(&mut self, bin: usize) {
+        self.counts[bin] += 1;
+        self.total += 1;
+    }
+}
+
+impl Default for PqHistogram {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn bin_signal(bin: usize) -> f64 {
+    bin as f64 / (HISTOGRAM_BINS - 1) as f64
+}
+
+// display linear values halfway between the PQ signal values of neighboring bins
+fn bin_edges() -> &'static [f64] {
+
+    static EDGES: OnceLock<Vec<f64>> = OnceLock::new();
+
+    EDGES.get_or_init(|| {
+        (0..(HISTOGRAM_BINS - 1))
+            .map(|bin| pq_eotf((bin as f64 + 0.5) / (HISTOGRAM_BINS - 1) as f64))
+            .collect()
+    })
+}
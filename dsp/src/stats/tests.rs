@@ -0,0 +1,148 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use crate::tf::pq_ieotf;
+use assert_approx_eq::assert_approx_eq;
+
+const DIFF: f64 = 0.0000000001;
+
+#[test]
+fn test_histogram_empty() {
+
+    let histogram = PqHistogram::new();
+
+    assert_eq!(histogram.total(), 0);
+    assert_eq!(histogram.counts().len(), HISTOGRAM_BINS);
+    assert_eq!(histogram.percentile(50.0), 0.0);
+}
+
+#[test]
+fn test_histogram_add_signal() {
+
+    let mut histogram = PqHistogram::new();
+
+    histogram.add_signal(0.0);
+    histogram.add_signal(1.0);
+    histogram.add_signal(1.5);
+    histogram.add_signal(bin_signal(512));
+
+    assert_eq!(histogram.total(), 4);
+    assert_eq!(histogram.counts()[0], 1);
+    assert_eq!(histogram.counts()[512], 1);
+    assert_eq!(histogram.counts()[HISTOGRAM_BINS - 1], 2);
+}
+
+#[test]
+fn test_histogram_add_linear() {
+
+    //
+    // Binning display linear values must agree with binning their PQ signal values, both near
+    // the center of a bin and close to either of its edges.
+    //
+
+    let mut linear = PqHistogram::new();
+    let mut signal = PqHistogram::new();
+
+    for bin in 0..HISTOGRAM_BINS {
+        for offset in [-0.45, -0.2, 0.0, 0.2, 0.45] {
+            let value = ((bin as f64 + offset) / (HISTOGRAM_BINS - 1) as f64).clamp(0.0, 1.0);
+            linear.add_linear(pq_eotf(value));
+            signal.add_signal(value);
+        }
+    }
+
+    assert_eq!(linear, signal);
+}
+
+#[test]
+fn test_histogram_percentile() {
+
+    let mut histogram = PqHistogram::new();
+
+    for bin in 0..1_000 {
+        histogram.add_signal(bin_signal(bin));
+    }
+
+    assert_approx_eq!(histogram.percentile(50.0), bin_signal(499), DIFF);
+    assert_approx_eq!(histogram.percentile(90.0), bin_signal(899), DIFF);
+    assert_approx_eq!(histogram.percentile(99.9), bin_signal(998), DIFF);
+    assert_approx_eq!(histogram.percentile(99.99), bin_signal(999), DIFF);
+    assert_approx_eq!(histogram.percentile(100.0), bin_signal(999), DIFF);
+    assert_approx_eq!(histogram.percentile(0.0), bin_signal(0), DIFF);
+}
+
+#[test]
+fn test_histogram_outlier() {
+
+    //
+    // A single specular pixel sets the maximum, but not the 99.99th percentile.
+    //
+
+    let mut histogram = PqHistogram::new();
+
+    for _ in 0..100_000 {
+        histogram.add_linear(0.0203);
+    }
+    histogram.add_linear(0.4);
+
+    assert_approx_eq!(histogram.percentile_nits(99.99), 203.0, 2.0);
+    assert_approx_eq!(histogram.percentile_nits(100.0), 4_000.0, 40.0);
+    assert_approx_eq!(histogram.percentile(100.0), pq_ieotf(0.4), 0.001);
+}
+
+#[test]
+fn test_histogram_merge() {
+
+    let mut a = PqHistogram::new();
+    let mut b = PqHistogram::new();
+    let mut both = PqHistogram::new();
+
+    for i in 0..500 {
+        a.add_signal(i as f64 / 700.0);
+        both.add_signal(i as f64 / 700.0);
+        b.add_signal(i as f64 / 300.0);
+        both.add_signal(i as f64 / 300.0);
+    }
+
+    a.merge(&b);
+
+    assert_eq!(a, both);
+    assert_eq!(a.total(), 1_000);
+}
+
+#[test]
+fn test_histogram_difference() {
+
+    let mut dark = PqHistogram::new();
+    let mut darker = PqHistogram::new();
+    let mut bright = PqHistogram::new();
+    let mut mixed = PqHistogram::new();
+
+    for _ in 0..1_000 {
+        dark.add_signal(bin_signal(100));
+        darker.add_signal(bin_signal(97));
+        bright.add_signal(bin_signal(700));
+    }
+    for _ in 0..500 {
+        mixed.add_signal(bin_signal(100));
+        mixed.add_signal(bin_signal(700));
+    }
+
+    assert_eq!(dark.difference(&dark), 0.0);
+    assert_eq!(dark.difference(&darker), 0.0);
+    assert_approx_eq!(dark.difference(&bright), 1.0, DIFF);
+    assert_approx_eq!(dark.difference(&mixed), 0.5, DIFF);
+    assert_approx_eq!(mixed.difference(&bright), 0.5, DIFF);
+    assert_eq!(PqHistogram::new().difference(&PqHistogram::new()), 0.0);
+    assert_eq!(PqHistogram::new().difference(&dark), 1.0);
+}
@@ -36,7 +36,7 @@ const SDR_REF_WHITE: f64 = 0.9112149320796772;
 #[test]
 fn test_pq_hlg_map_rw_100_peak_492() {
 
-    let mut frame = vec![
+    let mut frame = [
         RgbPixel { red: PQ_BLACK, green: PQ_BLACK, blue: PQ_BLACK },
         RgbPixel { red: PQ_100_NITS, green: PQ_100_NITS, blue: PQ_100_NITS },
         RgbPixel { red: PQ_492_NITS, green: PQ_492_NITS, blue: PQ_492_NITS },
@@ -74,7 +74,7 @@ fn test_pq_hlg_map_rw_100_peak_492() {
 #[test]
 fn test_pq_hlg_map_rw_100_peak_1_970() {
 
-    let mut frame = vec![
+    let mut frame = [
         RgbPixel { red: PQ_BLACK, green: PQ_BLACK, blue: PQ_BLACK },
         RgbPixel { red: PQ_100_NITS, green: PQ_100_NITS, blue: PQ_100_NITS },
         RgbPixel { red: PQ_1970_NITS, green: PQ_1970_NITS, blue: PQ_1970_NITS },
@@ -112,7 +112,7 @@ fn test_pq_hlg_map_rw_100_peak_1_970() {
 #[test]
 fn test_pq_hlg_map_rw_100_peak_4_926() {
 
-    let mut frame = vec![
+    let mut frame = [
         RgbPixel { red: PQ_BLACK, green: PQ_BLACK, blue: PQ_BLACK },
         RgbPixel { red: PQ_100_NITS, green: PQ_100_NITS, blue: PQ_100_NITS },
         RgbPixel { red: PQ_4926_NITS, green: PQ_4926_NITS, blue: PQ_4926_NITS },
@@ -144,7 +144,7 @@ fn test_pq_hlg_map_rw_100_peak_4_926() {
 #[test]
 fn test_pq_hlg_map_rw_203_peak_500() {
 
-    let mut frame = vec![
+    let mut frame = [
         RgbPixel { red: PQ_BLACK, green: PQ_BLACK, blue: PQ_BLACK },
         RgbPixel { red: PQ_REF_WHITE, green: PQ_REF_WHITE, blue: PQ_REF_WHITE },
         RgbPixel { red: PQ_1000_NITS, green: PQ_1000_NITS, blue: PQ_1000_NITS },
@@ -181,7 +181,7 @@ fn test_pq_hlg_map_rw_203_peak_500() {
 #[test]
 fn test_pq_hlg_map_rw_203_peak_1_000() {
 
-    let mut frame = vec![
+    let mut frame = [
         RgbPixel { red: PQ_BLACK, green: PQ_BLACK, blue: PQ_BLACK },
         RgbPixel { red: PQ_REF_WHITE, green: PQ_REF_WHITE, blue: PQ_REF_WHITE },
         RgbPixel { red: PQ_1000_NITS, green: PQ_1000_NITS, blue: PQ_1000_NITS },
@@ -218,7 +218,7 @@ fn test_pq_hlg_map_rw_203_peak_1_000() {
 #[test]
 fn test_pq_hlg_map_rw_203_peak_4_000() {
 
-    let mut frame = vec![
+    let mut frame = [
         RgbPixel { red: PQ_BLACK, green: PQ_BLACK, blue: PQ_BLACK },
         RgbPixel { red: PQ_REF_WHITE, green: PQ_REF_WHITE, blue: PQ_REF_WHITE },
         RgbPixel { red: PQ_4000_NITS, green: PQ_4000_NITS, blue: PQ_4000_NITS },
@@ -250,7 +250,7 @@ fn test_pq_hlg_map_rw_203_peak_4_000() {
 #[test]
 fn test_pq_hlg_map_rw_203_peak_10_000() {
 
-    let mut frame = vec![
+    let mut frame = [
         RgbPixel { red: PQ_BLACK, green: PQ_BLACK, blue: PQ_BLACK },
         RgbPixel { red: PQ_REF_WHITE, green: PQ_REF_WHITE, blue: PQ_REF_WHITE },
         RgbPixel { red: PQ_10000_NITS, green: PQ_10000_NITS, blue: PQ_10000_NITS },
@@ -277,7 +277,7 @@ fn test_pq_hlg_map_rw_203_peak_10_000() {
 #[test]
 fn test_preview_map() {
 
-    let mut frame = vec![
+    let mut frame = [
         RgbPixel { red: PQ_BLACK, green: PQ_BLACK, blue: PQ_BLACK },
         RgbPixel { red: PQ_REF_WHITE, green: PQ_REF_WHITE, blue: PQ_REF_WHITE },
     ];
@@ -299,7 +299,7 @@ fn test_preview_map() {
 #[test]
 fn test_hlg_pq_map_1_000() {
 
-    let mut frame = vec![
+    let mut frame = [
         RgbPixel { red: HLG_BLACK, green: HLG_BLACK, blue: HLG_BLACK },
         RgbPixel { red: HLG_REF_WHITE, green: HLG_REF_WHITE, blue: HLG_REF_WHITE },
         RgbPixel { red: HLG_MAX_WHITE, green: HLG_MAX_WHITE, blue: HLG_MAX_WHITE },
@@ -1779,3 +1779,44 @@ fn test_pq_all_valid_range_100000() {
         }
     }
 }
+
+#[test]
+fn test_ref_white_factor() {
+
+    assert_approx_eq!(ref_white_factor(203.0), 1.0, HDR_DIFF);
+    assert!(ref_white_factor(100.0) > 1.0);
+    assert!(ref_white_factor(400.0) < 1.0);
+
+    for ref_white in [100.0, 150.0, 300.0] {
+
+        let by_ref_white = PqHlgMapper::new_by_ref_white(ref_white, 1_000.0, ToneMapMethod::MaxRgb);
+        let by_factor = PqHlgMapper::new_by_factor(
+            ref_white_factor(ref_white), 1_000.0, ToneMapMethod::MaxRgb
+        );
+        let white = RgbPixel::new_y(pq_ieotf(ref_white / 10_000.0));
+
+        assert_approx_eq!(by_ref_white.map(white).red, by_factor.map(white).red, HDR_DIFF);
+        assert_approx_eq!(by_factor.map(white).red, HLG_REF_WHITE, SDR_DIFF);
+    }
+}
+
+#[test]
+fn test_sdr_mono_map() {
+
+    // Gray lands slightly off of the Oklab neutral axis, so dropping chroma shifts every
+    // channel a little.
+    for gray in [0.0, 0.25, 0.5, 1.0] {
+
+        let mono = SdrMonoMapper.map(RgbPixel::new_y(gray));
+
+        assert_approx_eq!(mono.red, gray, 0.001);
+        assert_approx_eq!(mono.green, gray, 0.001);
+        assert_approx_eq!(mono.blue, gray, 0.001);
+    }
+
+    let mono = SdrMonoMapper.map(RgbPixel::new_rgb(0.9, 0.2, 0.1));
+
+    assert_approx_eq!(mono.red, mono.green, 0.001);
+    assert_approx_eq!(mono.green, mono.blue, 0.001);
+    assert!(mono.red > 0.2 && mono.red < 0.9);
+}
@@ -53,7 +53,7 @@ pub fn hlg_eotf(pixel: RgbPixel, gamma: f64) -> RgbPixel {
     // Table 5
     //
 
-    hlg_ootf(pixel.with_each_channel(|x| hlg_ioetf(x)), gamma)
+    hlg_ootf(pixel.with_each_channel(hlg_ioetf), gamma)
 }
 
 pub fn hlg_oetf(o: f64) -> f64 {
@@ -114,6 +114,29 @@ pub fn hlg_iootf(pixel: RgbPixel) -> RgbPixel {
     pixel * pixel.y_bt2020().powf(-0.16666666666666663).min(f64::MAX)
 }
 
+pub fn bt2020_oetf(o: f64) -> f64 {
+
+    //
+    // ITU-R BT.2020-2
+    // Page 4
+    // Table 4
+    //
+
+    if o < 0.018053968510807 {
+        4.5 * o
+    } else {
+        1.09929682680944 * o.powf(0.45) - 0.09929682680944
+    }
+}
+
+pub fn bt2020_ioetf(e: f64) -> f64 {
+    if e < 0.08124285829863151 {
+        e / 4.5
+    } else {
+        ((e + 0.09929682680944) / 1.09929682680944).powf(2.2222222222222223)
+    }
+}
+
 pub fn sdr_e_to_o(o: f64) -> f64 {
     o.powf(2.4).clamp(0.0, 1.0)
 }
@@ -164,3 +164,19 @@ fn test_hlg_oetf_cycle() {
         assert_approx_eq!(hlg_oetf(hlg_ioetf(x)), x, DIFF);
     }
 }
+
+#[test]
+fn test_bt2020_oetf_cycle() {
+
+    const SIZE: usize = 1_024;
+
+    for i in 0..SIZE {
+
+        let x = i as f64 / (SIZE - 1) as f64;
+
+        assert_approx_eq!(bt2020_oetf(bt2020_ioetf(x)), x, DIFF);
+    }
+
+    assert_approx_eq!(bt2020_oetf(0.018053968510807), 0.08124285829863151, DIFF);
+    assert_approx_eq!(bt2020_oetf(1.0), 1.0, DIFF);
+}
@@ -16,17 +16,18 @@ use super::{
     tf::{pq_eotf, pq_ieotf},
 };
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ToneMapMethod {
     Rgb,
     MaxRgb,
 }
 
 pub struct Bt2408ToneMapper {
-    peak: f64,
-    target: f64,
-    lwp: f64,
-    ml: f64,
-    ks: f64,
+    pub(crate) peak: f64,
+    pub(crate) target: f64,
+    pub(crate) lwp: f64,
+    pub(crate) ml: f64,
+    pub(crate) ks: f64,
     method: ToneMapMethod,
 }
 
@@ -41,6 +42,10 @@ impl Bt2408ToneMapper {
         Self { peak, target, lwp, ml, ks, method }
     }
 
+    pub fn method(&self) -> ToneMapMethod {
+        self.method
+    }
+
     pub fn map(&self, pixel: RgbPixel) -> RgbPixel {
         if self.peak > self.target {
             match self.method {
@@ -88,7 +93,7 @@ impl Bt2408ToneMapper {
         if m1 > 0.0 {
             let m2 = pq_eotf(self.eetf(pq_ieotf(m1)));
             let factor = m2 / m1;
-            pixel.with_each_channel(|x| (factor * x))
+            pixel.with_each_channel(|x| factor * x)
         } else {
             pixel
         }
@@ -147,7 +152,7 @@ pub fn sdn_tone_map(o: f64) -> f64 {
 
     if o < 0.0 {
         0.0
-    } else if 0.0 <= o && o <= 0.203 {
+    } else if (0.0..=0.203).contains(&o) {
         (RgbPixel::new_y(o).bt709_to_xyz().to_oklab() * factor)
             .to_xyz().to_rgb_bt709().y_bt709() * 10.0
     } else if 0.203 < o && o <= 1.0 {
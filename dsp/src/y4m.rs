@@ -0,0 +1,522 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{
+    pixel::RgbPixel,
+    range::SignalRange,
+    yuv::{ChromaSiting, YuvDecoder, YuvFormat, YuvFrame},
+};
+use std::{
+    error::Error,
+    fmt::{self, Display, Formatter},
+    io::{self, Cursor, Read},
+};
+
+pub const Y4M_MAGIC: &[u8] = b"YUV4MPEG2";
+
+// Longest header or frame line that will be accepted, which only X extensions can approach.
+const MAX_LINE: usize = 4_096;
+
+//
+// YUV4MPEG2 streams consist of a single header line followed by frames, each of which is a
+// FRAME line and then the planar Y, Cb, and Cr samples. Samples wider than eight bits occupy two
+// bytes in little endian order. Streams are assumed to carry BT.2020 colorimetry, as Y4M has no
+// tags for primaries or transfer functions.
+//
+
+#[derive(Debug)]
+pub enum Y4mError {
+    Io(io::Error),
+    NotY4m,
+    BadHeader { reason: &'static str },
+    Unsupported { tag: String },
+    BadFrame { reason: &'static str },
+}
+
+impl Display for Y4mError {
+
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Y4mError::Io(err) => {
+                write!(f, "I/O error: {}", err)
+            }
+            Y4mError::NotY4m => {
+                write!(f, "not a YUV4MPEG2 stream")
+            }
+            Y4mError::BadHeader { reason } => {
+                write!(f, "bad YUV4MPEG2 header: {}", reason)
+            }
+            Y4mError::Unsupported { tag } => {
+                write!(f, "unsupported YUV4MPEG2 tag: {}", tag)
+            }
+            Y4mError::BadFrame { reason } => {
+                write!(f, "bad YUV4MPEG2 frame: {}", reason)
+            }
+        }
+    }
+}
+
+impl Error for Y4mError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Y4mError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Y4mError {
+
+    fn from(err: io::Error) -> Self {
+        Y4mError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Y4mSiting {
+    // 420jpeg: chroma centered between luma samples
+    Center,
+    // 420mpeg2: chroma cosited horizontally and centered vertically, which is also assumed for
+    // 4:2:0 without a siting as it is what FFmpeg and most 4:2:0 content use
+    Left,
+    // 420paldv: Cr cosited with the top left luma sample and Cb below it
+    PalDv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Y4mChroma {
+    Mono,
+    C420(Y4mSiting),
+    C422,
+    C444,
+}
+
+impl Y4mChroma {
+
+    // horizontal and vertical subsampling shifts
+    pub fn shifts(&self) -> (u32, u32) {
+        match self {
+            Y4mChroma::Mono | Y4mChroma::C444 => (0, 0),
+            Y4mChroma::C420(_) => (1, 1),
+            Y4mChroma::C422 => (1, 0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Y4mRange {
+    Limited,
+    Full,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Y4mHeader {
+    pub width: usize,
+    pub height: usize,
+    pub frame_rate: Option<(u32, u32)>,
+    pub interlacing: Option<char>,
+    pub aspect: Option<(u32, u32)>,
+    pub chroma: Y4mChroma,
+    pub bit_depth: u32,
+    // taken from XCOLORRANGE, as written by FFmpeg
+    pub range: Option<Y4mRange>,
+    // every X tag without its leading X
+    pub extensions: Vec<String>,
+}
+
+impl Y4mHeader {
+
+    pub fn parse(line: &str) -> Result<Self, Y4mError> {
+
+        let mut tags = line.split(' ').filter(|tag| !tag.is_empty());
+
+        if tags.next().map(str::as_bytes) != Some(Y4M_MAGIC) {
+            return Err(Y4mError::NotY4m)
+        }
+
+        let mut width = None;
+        let mut height = None;
+        let mut header = Y4mHeader {
+            width: 0,
+            height: 0,
+            frame_rate: None,
+            interlacing: None,
+            aspect: None,
+            chroma: Y4mChroma::C420(Y4mSiting::Left),
+            bit_depth: 8,
+            range: None,
+            extensions: Vec::new(),
+        };
+
+        for tag in tags {
+
+            let key = tag.chars().next().unwrap();
+            let value = &tag[key.len_utf8()..];
+
+            match key {
+                'W' => {
+                    width = Some(parse_dimension(value)?);
+                }
+                'H' => {
+                    height = Some(parse_dimension(value)?);
+                }
+                'F' => {
+                    header.frame_rate = Some(parse_ratio(value, "bad frame rate")?);
+                }
+                'I' => {
+                    header.interlacing = value.chars().next();
+                }
+                'A' => {
+                    header.aspect = Some(parse_ratio(value, "bad pixel aspect ratio")?);
+                }
+                'C' => {
+                    (header.chroma, header.bit_depth) = parse_chroma(value)?;
+                }
+                'X' => {
+                    if let Some(range) = value.strip_prefix("COLORRANGE=") {
+                        header.range = match range {
+                            "LIMITED" => Some(Y4mRange::Limited),
+                            "FULL" => Some(Y4mRange::Full),
+                            _ => None,
+                        };
+                    }
+                    header.extensions.push(String::from(value));
+                }
+                _ => {
+                    return Err(Y4mError::Unsupported { tag: String::from(tag) })
+                }
+            }
+        }
+
+        header.width = width.ok_or(Y4mError::BadHeader { reason: "missing width" })?;
+        header.height = height.ok_or(Y4mError::BadHeader { reason: "missing height" })?;
+
+        Ok(header)
+    }
+
+    pub fn chroma_size(&self) -> (usize, usize) {
+        match self.chroma {
+            Y4mChroma::Mono => (0, 0),
+            chroma => {
+                let (x_shift, y_shift) = chroma.shifts();
+                (self.width.div_ceil(1 << x_shift), self.height.div_ceil(1 << y_shift))
+            }
+        }
+    }
+
+    pub fn sample_size(&self) -> usize {
+        if self.bit_depth > 8 { 2 } else { 1 }
+    }
+
+    pub fn frame_size(&self) -> usize {
+
+        let (chroma_width, chroma_height) = self.chroma_size();
+
+        (self.width * self.height + 2 * chroma_width * chroma_height) * self.sample_size()
+    }
+
+    //
+    // Returns a decoder for the frames of the stream, which assumes limited range unless
+    // XCOLORRANGE says otherwise. PAL DV siting has Cb and Cr in different places, which is
+    // approximated by cositing both with the top left luma sample. Monochrome frames are read
+    // with neutral chroma for every luma sample.
+    //
+
+    pub fn decoder(&self) -> YuvDecoder {
+
+        let (x_shift, y_shift) = self.chroma.shifts();
+
+        YuvDecoder {
+            range: match self.range {
+                Some(Y4mRange::Full) => SignalRange::Full,
+                _ => SignalRange::Limited,
+            },
+            siting: match self.chroma {
+                Y4mChroma::C420(Y4mSiting::Center) => ChromaSiting::Center,
+                Y4mChroma::C420(Y4mSiting::PalDv) => ChromaSiting::TopLeft,
+                _ => ChromaSiting::Left,
+            },
+            ..YuvDecoder::new(
+                YuvFormat::Planar { bit_depth: self.bit_depth, x_shift, y_shift },
+                self.width,
+                self.height,
+            )
+        }
+    }
+}
+
+fn parse_dimension(value: &str) -> Result<usize, Y4mError> {
+    match value.parse::<usize>() {
+        Ok(dimension) if dimension > 0 => Ok(dimension),
+        _ => Err(Y4mError::BadHeader { reason: "bad dimension" }),
+    }
+}
+
+fn parse_ratio(value: &str, reason: &'static str) -> Result<(u32, u32), Y4mError> {
+    value
+        .split_once(':')
+        .and_then(|(n, d)| Some((n.parse::<u32>().ok()?, d.parse::<u32>().ok()?)))
+        .ok_or(Y4mError::BadHeader { reason })
+}
+
+fn parse_chroma(value: &str) -> Result<(Y4mChroma, u32), Y4mError> {
+
+    let unsupported = || Y4mError::Unsupported { tag: format!("C{}", value) };
+    let (chroma, rest) = if let Some(rest) = value.strip_prefix("mono") {
+        (Y4mChroma::Mono, rest)
+    } else if let Some(rest) = value.strip_prefix("420") {
+        match rest {
+            "mpeg2" => return Ok((Y4mChroma::C420(Y4mSiting::Left), 8)),
+            "paldv" => return Ok((Y4mChroma::C420(Y4mSiting::PalDv), 8)),
+            "jpeg" => return Ok((Y4mChroma::C420(Y4mSiting::Center), 8)),
+            _ => (Y4mChroma::C420(Y4mSiting::Left), rest),
+        }
+    } else if let Some(rest) = value.strip_prefix("422") {
+        (Y4mChroma::C422, rest)
+    } else if let Some(rest) = value.strip_prefix("444") {
+        (Y4mChroma::C444, rest)
+    } else {
+        return Err(unsupported())
+    };
+
+    // Mono bit depths follow directly (mono16) while the others have a p (420p10).
+    let depth = match chroma {
+        Y4mChroma::Mono => rest,
+        _ => rest.strip_prefix('p').unwrap_or(rest),
+    };
+    let bit_depth = if depth.is_empty() && rest.is_empty() {
+        8
+    } else {
+        depth.parse::<u32>().map_err(|_| unsupported())?
+    };
+
+    if !(8..=16).contains(&bit_depth) {
+        return Err(unsupported())
+    }
+
+    Ok((chroma, bit_depth))
+}
+
+//
+// Reader
+//
+
+// Reads until the magic is complete or the input ends, since a pipe may deliver even the start
+// of a stream in pieces, and returns whatever was read so that it can be put back.
+pub fn read_magic(input: &mut dyn Read) -> io::Result<Vec<u8>> {
+
+    let mut magic = Vec::with_capacity(Y4M_MAGIC.len());
+
+    input.take(Y4M_MAGIC.len() as u64).read_to_end(&mut magic)?;
+
+    Ok(magic)
+}
+
+pub struct Y4mReader<'a> {
+    input: Box<dyn Read + 'a>,
+    header: Y4mHeader,
+    decoder: YuvDecoder,
+}
+
+impl<'a> Y4mReader<'a> {
+
+    pub fn new(input: impl Read + 'a) -> Result<Self, Y4mError> {
+
+        let mut input: Box<dyn Read + 'a> = Box::new(input);
+        let line = read_line(&mut input)?.ok_or(Y4mError::NotY4m)?;
+        let header = Y4mHeader::parse(
+            std::str::from_utf8(&line).map_err(|_| Y4mError::NotY4m)?
+        )?;
+        let decoder = header.decoder();
+
+        Ok(Self { input, header, decoder })
+    }
+
+    pub fn header(&self) -> &Y4mHeader {
+        &self.header
+    }
+
+    // Returns `None` once the stream ends cleanly before a FRAME line, while a stream that ends
+    // anywhere within a frame is reported as a truncated frame.
+    pub fn read_frame(&mut self) -> Result<Option<YuvFrame>, Y4mError> {
+
+        let line = match read_line(&mut self.input).map_err(truncated)? {
+            Some(line) => line,
+            None => return Ok(None),
+        };
+
+        if !line.starts_with(b"FRAME") {
+            return Err(Y4mError::BadFrame { reason: "missing FRAME marker" })
+        }
+
+        let luma_count = self.header.width * self.header.height;
+        let luma = self.read_plane(luma_count)?;
+
+        if self.header.chroma == Y4mChroma::Mono {
+            let neutral = vec![1 << (self.header.bit_depth - 1); luma_count];
+            return Ok(Some(YuvFrame { luma, cb: neutral.clone(), cr: neutral }))
+        }
+
+        let (chroma_width, chroma_height) = self.header.chroma_size();
+
+        Ok(Some(YuvFrame {
+            luma,
+            cb: self.read_plane(chroma_width * chroma_height)?,
+            cr: self.read_plane(chroma_width * chroma_height)?,
+        }))
+    }
+
+    // Reads and decodes the next frame, failing with UnexpectedEof once the stream ends just as
+    // reading a raw frame does, and with InvalidData for a truncated or malformed frame.
+    pub fn read_rgb_frame(&mut self) -> io::Result<Vec<RgbPixel>> {
+        match self.read_frame() {
+            Ok(Some(frame)) => Ok(self.decoder.decode(&frame)),
+            Ok(None) => Err(io::ErrorKind::UnexpectedEof.into()),
+            Err(Y4mError::Io(err)) => Err(err),
+            Err(err) => Err(io::Error::new(io::ErrorKind::InvalidData, err)),
+        }
+    }
+
+    fn read_plane(&mut self, count: usize) -> Result<Vec<u16>, Y4mError> {
+
+        let mut bytes = vec![0_u8; count * self.header.sample_size()];
+
+        self.input.read_exact(&mut bytes).map_err(|err| truncated(err.into()))?;
+
+        Ok(if self.header.sample_size() == 2 {
+            bytes
+                .chunks_exact(2)
+                .map(|sample| u16::from_le_bytes([sample[0], sample[1]]))
+                .collect()
+        } else {
+            bytes.into_iter().map(u16::from).collect()
+        })
+    }
+}
+
+fn truncated(err: Y4mError) -> Y4mError {
+    match err {
+        Y4mError::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            Y4mError::BadFrame { reason: "truncated frame" }
+        }
+        err => err,
+    }
+}
+
+// Returns `None` if the input ends before the line starts.
+fn read_line(input: &mut dyn Read) -> Result<Option<Vec<u8>>, Y4mError> {
+
+    let mut line = Vec::new();
+    let mut byte = [0_u8];
+
+    loop {
+        if input.read(&mut byte)? == 0 {
+            return if line.is_empty() {
+                Ok(None)
+            } else {
+                Err(Y4mError::Io(io::ErrorKind::UnexpectedEof.into()))
+            }
+        }
+        if byte[0] == b'\n' {
+            return Ok(Some(line))
+        }
+        if line.len() == MAX_LINE {
+            return Err(Y4mError::BadHeader { reason: "line too long" })
+        }
+        line.push(byte[0]);
+    }
+}
+
+//
+// Frame Input
+//
+// Every frame tool reads either raw frames, whose dimensions have to be given, or a Y4M stream,
+// whose header carries them. Y4M frames are decoded straight to RGB using BT.2020 coefficients
+// without being quantized along the way.
+//
+
+#[derive(Debug)]
+pub enum OpenFramesError {
+    // raw input without dimensions
+    MissingGeometry,
+    // Y4M input along with dimensions
+    ConflictingGeometry,
+    Y4m(Y4mError),
+}
+
+impl Display for OpenFramesError {
+
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            OpenFramesError::MissingGeometry => {
+                write!(f, "dimensions are required unless the input is Y4M")
+            }
+            OpenFramesError::ConflictingGeometry => {
+                write!(f, "dimensions cannot be given for Y4M input")
+            }
+            OpenFramesError::Y4m(err) => {
+                write!(f, "could not read Y4M header: {}", err)
+            }
+        }
+    }
+}
+
+impl Error for OpenFramesError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            OpenFramesError::Y4m(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+pub enum RgbFrames<'a> {
+    // raw frames, which are RGB48LE unless a tool is told otherwise
+    Raw { input: Box<dyn Read + 'a>, width: usize, height: usize },
+    Y4m(Y4mReader<'a>),
+}
+
+impl RgbFrames<'_> {
+
+    pub fn size(&self) -> (usize, usize) {
+        match self {
+            RgbFrames::Raw { width, height, .. } => (*width, *height),
+            RgbFrames::Y4m(reader) => (reader.header().width, reader.header().height),
+        }
+    }
+}
+
+pub fn open_rgb_frames(
+    input: &mut dyn Read,
+    geometry: Option<(usize, usize)>,
+) -> Result<RgbFrames<'_>, OpenFramesError> {
+
+    let magic = read_magic(input).map_err(|err| OpenFramesError::Y4m(Y4mError::Io(err)))?;
+    let is_y4m = magic == Y4M_MAGIC;
+    let input = Cursor::new(magic).chain(input);
+
+    if !is_y4m {
+        return match geometry {
+            Some((width, height)) => Ok(RgbFrames::Raw { input: Box::new(input), width, height }),
+            None => Err(OpenFramesError::MissingGeometry),
+        }
+    }
+
+    if geometry.is_some() {
+        return Err(OpenFramesError::ConflictingGeometry)
+    }
+
+    Ok(RgbFrames::Y4m(Y4mReader::new(input).map_err(OpenFramesError::Y4m)?))
+}
@@ -0,0 +1,346 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use std::io::{BufReader, Cursor};
+
+fn samples_10(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|value| value.to_le_bytes()).collect()
+}
+
+#[test]
+fn test_parse_header() {
+
+    let header = Y4mHeader::parse(
+        "YUV4MPEG2 W3840 H2160 F24000:1001 Ip A1:1 C420p10 XYSCSS=420P10 XCOLORRANGE=LIMITED",
+    ).unwrap();
+
+    assert_eq!(header, Y4mHeader {
+        width: 3_840,
+        height: 2_160,
+        frame_rate: Some((24_000, 1_001)),
+        interlacing: Some('p'),
+        aspect: Some((1, 1)),
+        chroma: Y4mChroma::C420(Y4mSiting::Left),
+        bit_depth: 10,
+        range: Some(Y4mRange::Limited),
+        extensions: vec![String::from("YSCSS=420P10"), String::from("COLORRANGE=LIMITED")],
+    });
+    assert_eq!(header.chroma_size(), (1_920, 1_080));
+    assert_eq!(header.frame_size(), 3_840 * 2_160 * 3);
+}
+
+#[test]
+fn test_parse_header_defaults() {
+
+    let header = Y4mHeader::parse("YUV4MPEG2 W5 H3").unwrap();
+
+    assert_eq!(header.chroma, Y4mChroma::C420(Y4mSiting::Left));
+    assert_eq!(header.bit_depth, 8);
+    assert_eq!(header.range, None);
+    assert_eq!(header.frame_rate, None);
+    assert_eq!(header.chroma_size(), (3, 2));
+    assert_eq!(header.frame_size(), 15 + 12);
+}
+
+#[test]
+fn test_parse_chroma() {
+
+    let cases = [
+        ("420jpeg", Y4mChroma::C420(Y4mSiting::Center), 8),
+        ("420mpeg2", Y4mChroma::C420(Y4mSiting::Left), 8),
+        ("420paldv", Y4mChroma::C420(Y4mSiting::PalDv), 8),
+        ("420", Y4mChroma::C420(Y4mSiting::Left), 8),
+        ("420p12", Y4mChroma::C420(Y4mSiting::Left), 12),
+        ("422", Y4mChroma::C422, 8),
+        ("422p10", Y4mChroma::C422, 10),
+        ("444", Y4mChroma::C444, 8),
+        ("444p16", Y4mChroma::C444, 16),
+        ("mono", Y4mChroma::Mono, 8),
+        ("mono16", Y4mChroma::Mono, 16),
+    ];
+
+    for (value, chroma, bit_depth) in cases {
+        assert_eq!(parse_chroma(value).unwrap(), (chroma, bit_depth), "C{}", value);
+    }
+    for value in ["444alpha", "420p", "420p7", "420p17", "411", "p10"] {
+        assert!(matches!(parse_chroma(value), Err(Y4mError::Unsupported { .. })), "C{}", value);
+    }
+}
+
+#[test]
+fn test_parse_header_errors() {
+    assert!(matches!(Y4mHeader::parse("YUV4MPEG W1 H1"), Err(Y4mError::NotY4m)));
+    assert!(matches!(
+        Y4mHeader::parse("YUV4MPEG2 W1"),
+        Err(Y4mError::BadHeader { reason: "missing height" }),
+    ));
+    assert!(matches!(
+        Y4mHeader::parse("YUV4MPEG2 W0 H1"),
+        Err(Y4mError::BadHeader { reason: "bad dimension" }),
+    ));
+    assert!(matches!(
+        Y4mHeader::parse("YUV4MPEG2 W1 H1 F30"),
+        Err(Y4mError::BadHeader { reason: "bad frame rate" }),
+    ));
+    assert!(matches!(
+        Y4mHeader::parse("YUV4MPEG2 W1 H1 Z9"),
+        Err(Y4mError::Unsupported { .. }),
+    ));
+}
+
+#[test]
+fn test_read_frames_10_bit() {
+
+    let mut stream = b"YUV4MPEG2 W2 H2 F25:1 C420p10\n".to_vec();
+
+    stream.extend_from_slice(b"FRAME\n");
+    stream.extend(samples_10(&[64, 940, 502, 64, 512, 512]));
+    stream.extend_from_slice(b"FRAME Ixyz\n");
+    stream.extend(samples_10(&[940, 940, 940, 940, 960, 512]));
+
+    let mut input = Cursor::new(stream);
+    let mut reader = Y4mReader::new(&mut input).unwrap();
+
+    assert_eq!(reader.header().width, 2);
+    assert_eq!(reader.read_frame().unwrap().unwrap(), YuvFrame {
+        luma: vec![64, 940, 502, 64],
+        cb: vec![512],
+        cr: vec![512],
+    });
+    assert_eq!(reader.read_frame().unwrap().unwrap().cb, vec![960]);
+    assert!(reader.read_frame().unwrap().is_none());
+}
+
+#[test]
+fn test_read_frame_truncated() {
+
+    let mut stream = b"YUV4MPEG2 W2 H2 C444\nFRAME\n".to_vec();
+
+    stream.extend_from_slice(&[0; 11]);
+
+    let mut input = Cursor::new(stream);
+    let mut reader = Y4mReader::new(&mut input).unwrap();
+
+    assert!(matches!(
+        reader.read_frame(),
+        Err(Y4mError::BadFrame { reason: "truncated frame" }),
+    ));
+}
+
+#[test]
+fn test_read_frame_truncated_marker() {
+
+    let mut input = Cursor::new(b"YUV4MPEG2 W1 H1 Cmono
+FRA".to_vec());
+    let mut reader = Y4mReader::new(&mut input).unwrap();
+
+    assert!(matches!(
+        reader.read_frame(),
+        Err(Y4mError::BadFrame { reason: "truncated frame" }),
+    ));
+}
+
+#[test]
+fn test_read_frame_bad_marker() {
+
+    let mut input = Cursor::new(b"YUV4MPEG2 W1 H1 Cmono\nFRAMF\n\x10".to_vec());
+    let mut reader = Y4mReader::new(&mut input).unwrap();
+
+    assert!(matches!(
+        reader.read_frame(),
+        Err(Y4mError::BadFrame { reason: "missing FRAME marker" }),
+    ));
+}
+
+fn decoded(header: &str, frame: YuvFrame) -> Vec<RgbPixel> {
+    Y4mHeader::parse(header).unwrap().decoder().decode(&frame)
+}
+
+#[test]
+fn test_decode_limited() {
+
+    let pixels = decoded(
+        "YUV4MPEG2 W2 H1 C422p10",
+        YuvFrame { luma: vec![64, 940], cb: vec![512], cr: vec![512] },
+    );
+
+    assert_eq!(pixels, vec![RgbPixel::new_y(0.0), RgbPixel::new_y(1.0)]);
+}
+
+#[test]
+fn test_decode_full() {
+
+    let header = "YUV4MPEG2 W1 H1 C444 XCOLORRANGE=FULL";
+    let gray = decoded(header, YuvFrame { luma: vec![255], cb: vec![128], cr: vec![128] });
+    let red = decoded(header, YuvFrame { luma: vec![67], cb: vec![92], cr: vec![255] })[0];
+
+    assert_eq!(gray, vec![RgbPixel::new_y(1.0)]);
+    assert!(red.red > 0.99);
+    assert!(red.green < 0.015);
+    assert!(red.blue < 0.015);
+}
+
+#[test]
+fn test_decoder_siting() {
+
+    let decoder = |header: &str| Y4mHeader::parse(header).unwrap().decoder();
+
+    assert_eq!(decoder("YUV4MPEG2 W4 H4").siting, ChromaSiting::Left);
+    assert_eq!(decoder("YUV4MPEG2 W4 H4 C420jpeg").siting, ChromaSiting::Center);
+    assert_eq!(decoder("YUV4MPEG2 W4 H4 C420mpeg2").siting, ChromaSiting::Left);
+    assert_eq!(decoder("YUV4MPEG2 W4 H4 C420paldv").siting, ChromaSiting::TopLeft);
+    assert_eq!(decoder("YUV4MPEG2 W4 H4 C422p10").siting, ChromaSiting::Left);
+    assert_eq!(decoder("YUV4MPEG2 W4 H4 C420p10").range, SignalRange::Limited);
+    assert_eq!(
+        decoder("YUV4MPEG2 W4 H4 C420p10 XCOLORRANGE=FULL").range,
+        SignalRange::Full,
+    );
+    assert_eq!(
+        decoder("YUV4MPEG2 W4 H4 C422p12").format,
+        YuvFormat::Planar { bit_depth: 12, x_shift: 1, y_shift: 0 },
+    );
+}
+
+#[test]
+fn test_decode_interpolates_chroma() {
+
+    // Center siting puts each chroma sample between two luma samples, so the middle columns
+    // blend the two chroma columns while the outer ones take the nearest chroma column.
+    let pixels = decoded("YUV4MPEG2 W4 H2 C420jpeg", YuvFrame {
+        luma: vec![126; 8],
+        cb: vec![128, 160],
+        cr: vec![128, 128],
+    });
+
+    assert_eq!(pixels[0].red, pixels[0].blue);
+    assert!(pixels[1].blue > pixels[0].blue);
+    assert!(pixels[2].blue > pixels[1].blue);
+    assert!(pixels[3].blue > pixels[2].blue);
+    assert_eq!(pixels[4..8], pixels[0..4]);
+}
+
+#[test]
+fn test_read_rgb_frames() {
+
+    let mut stream = b"YUV4MPEG2 W2 H1 Cmono16 XCOLORRANGE=FULL\n".to_vec();
+
+    stream.extend_from_slice(b"FRAME\n\x00\x00\xFF\xFF");
+    stream.extend_from_slice(b"FRAME\n\xFF\xFF\x00\x00");
+
+    let mut input = BufReader::new(Cursor::new(stream));
+    let mut reader = Y4mReader::new(&mut input).unwrap();
+
+    assert_eq!(reader.header().bit_depth, 16);
+    assert_eq!(
+        reader.read_rgb_frame().unwrap(),
+        vec![RgbPixel::new_y(0.0), RgbPixel::new_y(1.0)],
+    );
+    assert_eq!(
+        reader.read_rgb_frame().unwrap(),
+        vec![RgbPixel::new_y(1.0), RgbPixel::new_y(0.0)],
+    );
+    assert_eq!(reader.read_rgb_frame().unwrap_err().kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_read_rgb_frame_bad_marker() {
+
+    let mut input = Cursor::new(b"YUV4MPEG2 W1 H1 Cmono\nFRAMF\n\x10".to_vec());
+    let mut reader = Y4mReader::new(&mut input).unwrap();
+
+    assert_eq!(reader.read_rgb_frame().unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_read_rgb_frame_truncated() {
+
+    let mut input = Cursor::new(b"YUV4MPEG2 W2 H1 Cmono
+FRAME
+\x10".to_vec());
+    let mut reader = Y4mReader::new(&mut input).unwrap();
+
+    assert_eq!(reader.read_rgb_frame().unwrap_err().kind(), io::ErrorKind::InvalidData);
+}
+
+// Hands out a single byte per read, as a pipe might at the start of a stream.
+struct Trickle(Cursor<Vec<u8>>);
+
+impl Read for Trickle {
+
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = buf.len().min(1);
+        self.0.read(&mut buf[..length])
+    }
+}
+
+#[test]
+fn test_read_magic() {
+    assert_eq!(read_magic(&mut Cursor::new(b"YUV4MPEG2 W1".to_vec())).unwrap(), Y4M_MAGIC);
+    assert_eq!(read_magic(&mut Trickle(Cursor::new(b"YUV4MPEG2 W1".to_vec()))).unwrap(), Y4M_MAGIC);
+    assert_eq!(read_magic(&mut Cursor::new(b"YUV".to_vec())).unwrap(), b"YUV");
+    assert!(read_magic(&mut Cursor::new(Vec::new())).unwrap().is_empty());
+}
+
+#[test]
+fn test_open_rgb_frames_trickle() {
+
+    let mut input = Trickle(Cursor::new(b"YUV4MPEG2 W3 H2 C444p10\n".to_vec()));
+    let frames = open_rgb_frames(&mut input, None).unwrap();
+
+    assert_eq!(frames.size(), (3, 2));
+}
+
+#[test]
+fn test_open_rgb_frames_raw() {
+
+    let mut input = BufReader::new(Cursor::new(vec![7_u8; 12]));
+    let frames = open_rgb_frames(&mut input, Some((2, 1))).unwrap();
+
+    assert_eq!(frames.size(), (2, 1));
+
+    let RgbFrames::Raw { mut input, .. } = frames else { panic!("raw input opened as Y4M") };
+    let mut output = Vec::new();
+
+    input.read_to_end(&mut output).unwrap();
+
+    assert_eq!(output, vec![7_u8; 12]);
+}
+
+#[test]
+fn test_open_rgb_frames_y4m() {
+
+    let mut input = BufReader::new(Cursor::new(b"YUV4MPEG2 W3 H2 C444p10\n".to_vec()));
+    let frames = open_rgb_frames(&mut input, None).unwrap();
+
+    assert_eq!(frames.size(), (3, 2));
+    assert!(matches!(frames, RgbFrames::Y4m(_)));
+}
+
+#[test]
+fn test_open_rgb_frames_errors() {
+
+    let open = |bytes: &[u8], geometry| {
+        let mut input = BufReader::new(Cursor::new(bytes.to_vec()));
+        open_rgb_frames(&mut input, geometry).map(|_| ()).unwrap_err()
+    };
+
+    assert!(matches!(open(&[0; 6], None), OpenFramesError::MissingGeometry));
+    assert!(matches!(
+        open(b"YUV4MPEG2 W1 H1\n", Some((1, 1))),
+        OpenFramesError::ConflictingGeometry,
+    ));
+    assert!(matches!(
+        open(b"YUV4MPEG2 W1\n", None),
+        OpenFramesError::Y4m(Y4mError::BadHeader { .. }),
+    ));
+}
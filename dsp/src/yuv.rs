@@ -0,0 +1,253 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use super::{
+    pixel::{RgbPixel, YcbcrMatrix, YcbcrPixel},
+    range::SignalRange,
+};
+use std::io::{Read, Result};
+
+//
+// Raw YUV frames as written by FFmpeg. Samples wider than eight bits occupy two bytes in little
+// endian order and narrower ones a single byte. The planar formats keep the sample in the low
+// bits, while P010 keeps it in the high bits and interleaves Cb and Cr into a single plane. Any
+// other planar layout, such as one described by a Y4M header, is given by its bit depth and
+// subsampling shifts.
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum YuvFormat {
+    Yuv420p10le,
+    Yuv422p10le,
+    Yuv444p12le,
+    P010,
+    Planar { bit_depth: u32, x_shift: u32, y_shift: u32 },
+}
+
+impl YuvFormat {
+
+    pub fn bit_depth(&self) -> u32 {
+        match self {
+            YuvFormat::Yuv444p12le => 12,
+            YuvFormat::Planar { bit_depth, .. } => *bit_depth,
+            _ => 10,
+        }
+    }
+
+    pub fn sample_size(&self) -> usize {
+        if self.bit_depth() > 8 { 2 } else { 1 }
+    }
+
+    // horizontal and vertical subsampling shifts
+    pub fn shifts(&self) -> (u32, u32) {
+        match self {
+            YuvFormat::Yuv420p10le | YuvFormat::P010 => (1, 1),
+            YuvFormat::Yuv422p10le => (1, 0),
+            YuvFormat::Yuv444p12le => (0, 0),
+            YuvFormat::Planar { x_shift, y_shift, .. } => (*x_shift, *y_shift),
+        }
+    }
+
+    pub fn chroma_size(&self, width: usize, height: usize) -> (usize, usize) {
+
+        let (x_shift, y_shift) = self.shifts();
+
+        (width.div_ceil(1 << x_shift), height.div_ceil(1 << y_shift))
+    }
+
+    pub fn frame_size(&self, width: usize, height: usize) -> usize {
+
+        let (chroma_width, chroma_height) = self.chroma_size(width, height);
+
+        (width * height + 2 * chroma_width * chroma_height) * self.sample_size()
+    }
+}
+
+//
+// Chroma Siting
+//
+// Left is the MPEG-2 and HEVC default for 4:2:0, where chroma is cosited with even luma columns
+// and centered between rows. Center places chroma between luma samples in both directions, as
+// JPEG does. TopLeft cosites chroma with the top left luma sample, as some UHD encoders signal.
+//
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChromaSiting {
+    Left,
+    Center,
+    TopLeft,
+}
+
+impl ChromaSiting {
+
+    // position of the first chroma sample in luma samples, horizontally and vertically
+    fn offsets(&self) -> (f64, f64) {
+        match self {
+            ChromaSiting::Left => (0.0, 0.5),
+            ChromaSiting::Center => (0.5, 0.5),
+            ChromaSiting::TopLeft => (0.0, 0.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChromaUpsampling {
+    Nearest,
+    Bilinear,
+}
+
+//
+// Frames
+//
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct YuvFrame {
+    pub luma: Vec<u16>,
+    pub cb: Vec<u16>,
+    pub cr: Vec<u16>,
+}
+
+impl YuvFrame {
+
+    pub fn from_bytes(bytes: &[u8], format: YuvFormat, width: usize, height: usize) -> Self {
+
+        assert_eq!(bytes.len(), format.frame_size(width, height), "YUV frame size mismatch");
+
+        let samples = if format.sample_size() == 2 {
+            bytes
+                .chunks_exact(2)
+                .map(|sample| u16::from_le_bytes([sample[0], sample[1]]))
+                .collect::<Vec<u16>>()
+        } else {
+            bytes.iter().copied().map(u16::from).collect()
+        };
+        let (chroma_width, chroma_height) = format.chroma_size(width, height);
+        let (luma, chroma) = samples.split_at(width * height);
+        let chroma_count = chroma_width * chroma_height;
+
+        match format {
+            YuvFormat::P010 => Self {
+                luma: luma.iter().map(|sample| sample >> 6).collect(),
+                cb: chroma.iter().step_by(2).map(|sample| sample >> 6).collect(),
+                cr: chroma.iter().skip(1).step_by(2).map(|sample| sample >> 6).collect(),
+            },
+            _ => Self {
+                luma: luma.to_vec(),
+                cb: chroma[..chroma_count].to_vec(),
+                cr: chroma[chroma_count..].to_vec(),
+            },
+        }
+    }
+}
+
+//
+// Decoder
+//
+
+pub struct YuvDecoder {
+    pub format: YuvFormat,
+    pub width: usize,
+    pub height: usize,
+    pub range: SignalRange,
+    pub siting: ChromaSiting,
+    pub upsampling: ChromaUpsampling,
+}
+
+impl YuvDecoder {
+
+    pub fn new(format: YuvFormat, width: usize, height: usize) -> Self {
+        Self {
+            format,
+            width,
+            height,
+            range: SignalRange::Limited,
+            siting: ChromaSiting::Left,
+            upsampling: ChromaUpsampling::Bilinear,
+        }
+    }
+
+    pub fn read_frame(&self, input: &mut dyn Read) -> Result<Vec<RgbPixel>> {
+
+        let mut bytes = vec![0_u8; self.format.frame_size(self.width, self.height)];
+
+        input.read_exact(&mut bytes)?;
+
+        Ok(self.decode(&YuvFrame::from_bytes(&bytes, self.format, self.width, self.height)))
+    }
+
+    //
+    // Chroma is upsampled to every luma sample before the BT.2020 matrix is applied. Signal
+    // values are clamped to 0.0 through 1.0, just as they would be once quantized to RGB48LE.
+    //
+
+    pub fn decode(&self, frame: &YuvFrame) -> Vec<RgbPixel> {
+
+        let bit_depth = self.format.bit_depth();
+        let scale = (1 << (bit_depth - 8)) as f64;
+        let max = ((1 << bit_depth) - 1) as f64;
+        let (luma_offset, luma_range, chroma_offset, chroma_range) = match self.range {
+            SignalRange::Full => (0.0, max, (1 << (bit_depth - 1)) as f64, max),
+            SignalRange::Limited => (16.0 * scale, 219.0 * scale, 128.0 * scale, 224.0 * scale),
+        };
+        let (x_shift, y_shift) = self.format.shifts();
+        let (x_offset, y_offset) = self.siting.offsets();
+        let (chroma_width, chroma_height) = self.format.chroma_size(self.width, self.height);
+        let x_taps = (0..self.width)
+            .map(|x| self.taps(x, x_shift, x_offset, chroma_width))
+            .collect::<Vec<_>>();
+        let mut output = Vec::with_capacity(self.width * self.height);
+
+        for y in 0..self.height {
+
+            let (y0, y1, ty) = self.taps(y, y_shift, y_offset, chroma_height);
+
+            for (x, (x0, x1, tx)) in x_taps.iter().copied().enumerate() {
+
+                let sample = |plane: &[u16]| {
+                    let at = |cx: usize, cy: usize| plane[cy * chroma_width + cx] as f64;
+                    let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * tx;
+                    let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * tx;
+                    top + (bottom - top) * ty
+                };
+                let ycbcr = YcbcrPixel::new(
+                    (frame.luma[y * self.width + x] as f64 - luma_offset) / luma_range,
+                    (sample(&frame.cb) - chroma_offset) / chroma_range,
+                    (sample(&frame.cr) - chroma_offset) / chroma_range,
+                );
+
+                output.push(ycbcr.to_rgb(YcbcrMatrix::Bt2020Ncl).clamp(0.0, 1.0));
+            }
+        }
+
+        output
+    }
+
+    // neighboring chroma samples and the weight of the second one for a luma position
+    fn taps(&self, index: usize, shift: u32, offset: f64, size: usize) -> (usize, usize, f64) {
+
+        let last = size - 1;
+        let offset = if shift == 0 { 0.0 } else { offset };
+        let position = ((index as f64 - offset) / (1 << shift) as f64).clamp(0.0, last as f64);
+
+        match self.upsampling {
+            ChromaUpsampling::Nearest => {
+                let nearest = position.round() as usize;
+                (nearest, nearest, 0.0)
+            }
+            ChromaUpsampling::Bilinear => {
+                let prev = position as usize;
+                (prev, (prev + 1).min(last), position - prev as f64)
+            }
+        }
+    }
+}
@@ -0,0 +1,183 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use assert_approx_eq::assert_approx_eq;
+use std::io::{Cursor, ErrorKind};
+
+const DIFF: f64 = 0.0000000001;
+
+fn bytes(samples: &[u16]) -> Vec<u8> {
+    samples.iter().flat_map(|sample| sample.to_le_bytes()).collect()
+}
+
+#[test]
+fn test_frame_size() {
+
+    assert_eq!(YuvFormat::Yuv420p10le.frame_size(3_840, 2_160), 3_840 * 2_160 * 3);
+    assert_eq!(YuvFormat::P010.frame_size(3_840, 2_160), 3_840 * 2_160 * 3);
+    assert_eq!(YuvFormat::Yuv422p10le.frame_size(3_840, 2_160), 3_840 * 2_160 * 4);
+    assert_eq!(YuvFormat::Yuv444p12le.frame_size(3_840, 2_160), 3_840 * 2_160 * 6);
+    assert_eq!(YuvFormat::Yuv420p10le.chroma_size(5, 3), (3, 2));
+    assert_eq!(YuvFormat::Yuv422p10le.chroma_size(5, 3), (3, 3));
+}
+
+#[test]
+fn test_planar_8_bit() {
+
+    let format = YuvFormat::Planar { bit_depth: 8, x_shift: 1, y_shift: 1 };
+
+    assert_eq!(format.frame_size(3_840, 2_160), 3_840 * 2_160 * 3 / 2);
+    assert_eq!(YuvFrame::from_bytes(&[16, 235, 128, 16, 90, 240], format, 2, 2), YuvFrame {
+        luma: vec![16, 235, 128, 16],
+        cb: vec![90],
+        cr: vec![240],
+    });
+}
+
+#[test]
+fn test_p010_matches_planar() {
+
+    let luma = [64_u16, 502, 940, 700];
+    let planar = [&luma[..], &[600], &[400]].concat();
+    let semi_planar = luma
+        .iter()
+        .chain([600, 400].iter())
+        .map(|sample| sample << 6)
+        .collect::<Vec<u16>>();
+
+    assert_eq!(
+        YuvFrame::from_bytes(&bytes(&planar), YuvFormat::Yuv420p10le, 2, 2),
+        YuvFrame::from_bytes(&bytes(&semi_planar), YuvFormat::P010, 2, 2),
+    );
+}
+
+#[test]
+fn test_decode_neutral() {
+
+    let decoder = YuvDecoder::new(YuvFormat::Yuv444p12le, 3, 1);
+    let frame = YuvFrame {
+        luma: vec![256, 2_008, 3_760],
+        cb: vec![2_048; 3],
+        cr: vec![2_048; 3],
+    };
+    let pixels = decoder.decode(&frame);
+
+    for (pixel, expected) in pixels.iter().zip([0.0, 0.5, 1.0]) {
+        assert_approx_eq!(pixel.red, expected, DIFF);
+        assert_approx_eq!(pixel.green, expected, DIFF);
+        assert_approx_eq!(pixel.blue, expected, DIFF);
+    }
+}
+
+#[test]
+fn test_decode_full_range() {
+
+    let mut decoder = YuvDecoder::new(YuvFormat::Yuv420p10le, 2, 2);
+
+    decoder.range = SignalRange::Full;
+
+    let frame = YuvFrame { luma: vec![0, 1_023, 0, 1_023], cb: vec![512], cr: vec![512] };
+    let pixels = decoder.decode(&frame);
+
+    assert_eq!(pixels[0], RgbPixel::new_y(0.0));
+    assert_approx_eq!(pixels[1].green, 1.0, DIFF);
+}
+
+#[test]
+fn test_decode_matches_bt2020() {
+
+    let expected = RgbPixel::new_rgb(0.75, 0.25, 0.5);
+    let ycbcr = expected.to_ycbcr(YcbcrMatrix::Bt2020Ncl);
+    let decoder = YuvDecoder::new(YuvFormat::Yuv444p12le, 1, 1);
+    let frame = YuvFrame {
+        luma: vec![(256.0 + ycbcr.y * 3_504.0).round() as u16],
+        cb: vec![(2_048.0 + ycbcr.cb * 3_584.0).round() as u16],
+        cr: vec![(2_048.0 + ycbcr.cr * 3_584.0).round() as u16],
+    };
+    let pixel = decoder.decode(&frame)[0];
+
+    assert_approx_eq!(pixel.red, expected.red, 0.001);
+    assert_approx_eq!(pixel.green, expected.green, 0.001);
+    assert_approx_eq!(pixel.blue, expected.blue, 0.001);
+}
+
+#[test]
+fn test_upsampling_horizontal() {
+
+    //
+    // Two chroma samples across four luma columns, where only the second is reddish. Cosited
+    // chroma lands on columns 0 and 2, while centered chroma lands between 0/1 and 2/3.
+    //
+
+    let frame = YuvFrame {
+        luma: vec![502; 4],
+        cb: vec![512, 512],
+        cr: vec![512, 600],
+    };
+    let reds = |siting, upsampling| {
+        let mut decoder = YuvDecoder::new(YuvFormat::Yuv422p10le, 4, 1);
+        decoder.siting = siting;
+        decoder.upsampling = upsampling;
+        decoder.decode(&frame).iter().map(|pixel| pixel.red).collect::<Vec<f64>>()
+    };
+
+    let nearest = reds(ChromaSiting::Left, ChromaUpsampling::Nearest);
+    let left = reds(ChromaSiting::Left, ChromaUpsampling::Bilinear);
+    let center = reds(ChromaSiting::Center, ChromaUpsampling::Bilinear);
+
+    assert_approx_eq!(left[0], 0.5, DIFF);
+    assert_approx_eq!(left[1], (left[0] + left[2]) / 2.0, DIFF);
+    assert_approx_eq!(left[3], left[2], DIFF);
+    assert_approx_eq!(center[0], 0.5, DIFF);
+    assert_approx_eq!(center[2] - center[1], 2.0 * (center[1] - 0.5), DIFF);
+    assert_approx_eq!(center[3], left[2], DIFF);
+    assert!(center[1] < left[1]);
+    assert_approx_eq!(nearest[0], 0.5, DIFF);
+    assert_approx_eq!(nearest[2], left[2], DIFF);
+    assert_approx_eq!(nearest[3], left[2], DIFF);
+}
+
+#[test]
+fn test_upsampling_vertical() {
+
+    let frame = YuvFrame {
+        luma: vec![502; 8],
+        cb: vec![512; 2],
+        cr: vec![512, 600],
+    };
+    let reds = |siting| {
+        let mut decoder = YuvDecoder::new(YuvFormat::Yuv420p10le, 2, 4);
+        decoder.siting = siting;
+        decoder.decode(&frame).iter().step_by(2).map(|pixel| pixel.red).collect::<Vec<f64>>()
+    };
+
+    let top_left = reds(ChromaSiting::TopLeft);
+    let left = reds(ChromaSiting::Left);
+
+    assert_approx_eq!(top_left[0], 0.5, DIFF);
+    assert_approx_eq!(left[0], 0.5, DIFF);
+    assert!(left[1] > 0.5);
+    assert!(top_left[1] > left[1]);
+    assert!(left[2] > top_left[1]);
+    assert_approx_eq!(left[3], top_left[3], DIFF);
+}
+
+#[test]
+fn test_read_frame() {
+
+    let decoder = YuvDecoder::new(YuvFormat::Yuv420p10le, 2, 2);
+    let mut input = Cursor::new(bytes(&[940, 940, 940, 940, 512, 512, 64, 64]));
+
+    assert_eq!(decoder.read_frame(&mut input).unwrap().len(), 4);
+    assert_eq!(decoder.read_frame(&mut input).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
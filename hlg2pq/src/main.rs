@@ -14,13 +14,23 @@ use std::{
 };
 use dsp::{
     HlgPqMapper,
-    pixel::RgbPixel,
+    clf::hlg_pq_process_list,
+    lut::{
+        InterpolationMethod,
+        Lut3d,
+        accuracy::{accuracy_report, OutputSignal},
+    },
+    range::{RangeMapper, SignalRange},
+};
+use clap::{
+    app_from_crate, crate_authors, crate_description, crate_name, crate_version, AppSettings,
+    Arg, Error, ErrorKind, SubCommand,
 };
-use clap::{app_from_crate, crate_authors, crate_description, crate_name, crate_version, Arg};
 
 fn main() {
 
     let matches = app_from_crate!()
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(Arg::with_name("title")
             .long("title")
             .short("t")
@@ -78,20 +88,118 @@ fn main() {
                     return Err("Must be an unsigned integer value".to_string())
                 }
                 let size_value = size.unwrap();
-                if size_value < 2 || size_value > 256 {
+                if !(2..=256).contains(&size_value) {
                     return Err("Must be between 2 and 256".to_string())
                 }
                 Ok(())
             })
         )
+        .arg(Arg::with_name("format")
+            .long("format")
+            .short("f")
+            .help("File format of the generated LUT")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["cube", "3dl", "clf", "hald", "spi3d"])
+            .default_value("cube")
+        )
+        .arg(Arg::with_name("bit-depth")
+            .long("bit-depth")
+            .short("b")
+            .value_name("BITS")
+            .help("Bit depth of the output code values in integer formats such as 3DL")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["10", "12", "16"])
+            .default_value("12")
+        )
+        .arg(Arg::with_name("hald-level")
+            .long("hald-level")
+            .value_name("LEVEL")
+            .help("Level of a Hald CLUT image, which replaces --size with the level squared")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["8", "12", "16"])
+            .default_value("8")
+        )
+        .arg(Arg::with_name("input-range")
+            .long("input-range")
+            .value_name("RANGE")
+            .help("Quantization range of the input signal")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["full", "limited"])
+            .default_value("full")
+        )
+        .arg(Arg::with_name("output-range")
+            .long("output-range")
+            .value_name("RANGE")
+            .help("Quantization range of the output signal")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["full", "limited"])
+            .default_value("full")
+        )
+        .arg(Arg::with_name("range-depth")
+            .long("range-depth")
+            .value_name("BITS")
+            .help("Bit depth of the video whose limited range code values are targeted")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["8", "10", "12", "16"])
+            .default_value("10")
+        )
         .arg(Arg::with_name("output")
             .index(1)
             .value_name("OUTPUT-FILE")
-            .help("Output Cube LUT file; use - for STDOUT")
+            .help("Output LUT file; use - for STDOUT")
             .required(true)
         )
+        .subcommand(SubCommand::with_name("accuracy")
+            .about("Reports how closely a LUT of the selected size reproduces the exact \
+                conversion")
+            .arg(Arg::with_name("interpolation")
+                .long("interpolation")
+                .short("i")
+                .help("Interpolation method used to evaluate the LUT")
+                .takes_value(true)
+                .required(false)
+                .possible_values(&["trilinear", "tetrahedral"])
+                .default_value("tetrahedral")
+            )
+            .arg(Arg::with_name("samples")
+                .long("samples")
+                .short("n")
+                .value_name("COUNT")
+                .help("Number of off-lattice samples taken along each dimension")
+                .takes_value(true)
+                .required(false)
+                .default_value("64")
+                .validator(|value| {
+                    let samples = value.parse::<usize>();
+                    if samples.is_err() {
+                        return Err("Must be an unsigned integer value".to_string())
+                    }
+                    if samples.unwrap() < 1 {
+                        return Err("Must be at least 1".to_string())
+                    }
+                    Ok(())
+                })
+            )
+            .after_help("Densely samples the input cube between lattice points and compares the \
+                interpolated LUT against the exact conversion. Errors are reported in Delta E \
+                ITP (BT.2124) and in nits, both overall and broken out by the brightness of the \
+                exact output.")
+        )
         .after_help(format!("This utility follows the BT.2408 method for generating a \
             HLG-to-PQ conversion LUT according to the output brightness.\n\n\
+            With --input-range limited or --output-range limited, the LUT instead expects or \
+            produces limited range code values for the --range-depth bit depth, such as 64 to \
+            940 at 10 bits. HLG super-white input above nominal white, up to 1019 at 10 bits, \
+            continues to brighten the PQ output rather than being clipped.\n\n\
+            With --format clf, the conversion is written as an Academy Common LUT Format \
+            process list instead. The HLG and PQ curves appear as their own nodes, while the \
+            HLG OOTF is baked into a 3D LUT of the selected --size.\n\n\
             Copyright © 2024 William Swartzendruber\n\
             Licensed under the Mozilla Public License 2.0\n\
             <{}>", env!("CARGO_PKG_REPOSITORY")).as_str())
@@ -99,7 +207,56 @@ fn main() {
     let title = matches.value_of("title");
     let max_cll = matches.value_of("max-cll").unwrap().parse::<f64>().unwrap();
     let mapper = HlgPqMapper::new(max_cll);
-    let size = matches.value_of("size").unwrap().parse::<usize>().unwrap();
+    let format = matches.value_of("format").unwrap();
+    let size = if format == "hald" {
+        matches.value_of("hald-level").unwrap().parse::<usize>().unwrap().pow(2)
+    } else {
+        matches.value_of("size").unwrap().parse::<usize>().unwrap()
+    };
+    let bit_depth = matches.value_of("bit-depth").unwrap().parse::<u32>().unwrap();
+    let input_range = match matches.value_of("input-range").unwrap() {
+        "full" => SignalRange::Full,
+        "limited" => SignalRange::Limited,
+        _ => unreachable!("--input-range select is irrational"),
+    };
+    let output_range = match matches.value_of("output-range").unwrap() {
+        "full" => SignalRange::Full,
+        "limited" => SignalRange::Limited,
+        _ => unreachable!("--output-range select is irrational"),
+    };
+    let range_depth = matches.value_of("range-depth").unwrap().parse::<u32>().unwrap();
+    let limited = (input_range, output_range) != (SignalRange::Full, SignalRange::Full);
+
+    if limited && format == "clf" {
+        Error::with_description(
+            "--input-range and --output-range are not supported with --format clf",
+            ErrorKind::ArgumentConflict,
+        ).exit()
+    }
+
+    if let Some(accuracy_matches) = matches.subcommand_matches("accuracy") {
+
+        if limited {
+            Error::with_description(
+                "--input-range and --output-range are not supported with accuracy",
+                ErrorKind::ArgumentConflict,
+            ).exit()
+        }
+
+        let interpolation = match accuracy_matches.value_of("interpolation").unwrap() {
+            "trilinear" => InterpolationMethod::Trilinear,
+            "tetrahedral" => InterpolationMethod::Tetrahedral,
+            _ => unreachable!("--interpolation select is irrational"),
+        };
+        let samples = accuracy_matches.value_of("samples").unwrap().parse::<usize>().unwrap();
+
+        print!("{}", accuracy_report(
+            &mapper, size, None, interpolation, OutputSignal::Pq, samples,
+        ));
+
+        return
+    }
+
     let output_value = matches.value_of("output").unwrap();
     let (mut stdout_write, mut file_write);
     let mut output = BufWriter::<&mut dyn Write>::new(
@@ -113,29 +270,35 @@ fn main() {
         }
     );
 
-    writeln!(output, "# Generated by HLG2PQ {}", env!("CARGO_PKG_VERSION")).unwrap();
-    writeln!(output, "# max-cll: {}", max_cll).unwrap();
-    if title.is_some() {
-        writeln!(output, "TITLE \"{}\"", title.unwrap()).unwrap();
+    if format == "clf" {
+        let mut process_list = hlg_pq_process_list(&mapper, size);
+        if let Some(title) = title {
+            process_list.name = String::from(title);
+        }
+        process_list.generator = Some(format!("HLG2PQ {}", env!("CARGO_PKG_VERSION")));
+        process_list.parameters.push((String::from("max-cll"), max_cll.to_string()));
+        process_list.write_clf(&mut output).expect("Could not write to output file.");
+        return
     }
-    writeln!(output, "LUT_3D_SIZE {}", size).unwrap();
-
-    for b in 0..size {
-        for g in 0..size {
-            for r in 0..size {
 
-                let pixel = mapper.map(RgbPixel {
-                    red: r as f64 / (size - 1) as f64,
-                    green: g as f64 / (size - 1) as f64,
-                    blue: b as f64 / (size - 1) as f64,
-                }).clamp(0.0, 1.0);
+    let mut lut = Lut3d::from_mapper(
+        &RangeMapper::new(&mapper, input_range, output_range, range_depth, true),
+        size,
+    );
 
-                writeln!(output, "{} {} {}",
-                    pixel.red as f32,
-                    pixel.green as f32,
-                    pixel.blue as f32,
-                ).unwrap();
-            }
-        }
+    lut.title = title.map(String::from);
+    lut.comments.push(format!("Generated by HLG2PQ {}", env!("CARGO_PKG_VERSION")));
+    lut.comments.push(format!("max-cll: {}", max_cll));
+    if limited {
+        lut.comments.push(format!("input-range: {}", matches.value_of("input-range").unwrap()));
+        lut.comments.push(format!("output-range: {}", matches.value_of("output-range").unwrap()));
+        lut.comments.push(format!("range-depth: {}", range_depth));
     }
+    match format {
+        "cube" => lut.write_cube(&mut output),
+        "3dl" => lut.write_3dl(&mut output, bit_depth),
+        "hald" => lut.write_hald(&mut output),
+        "spi3d" => lut.write_spi3d(&mut output),
+        _ => unreachable!("--format select is irrational"),
+    }.expect("Could not write to output file.");
 }
@@ -0,0 +1,401 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use std::{
+    fs::File,
+    io::{stdin, stdout, BufReader, BufWriter, ErrorKind, Read, Result, Write},
+};
+use dsp::{
+    HlgPqMapper,
+    Mapper,
+    PqHlgMapper,
+    PqSdrMapper,
+    frame::{default_threads, map_bands, map_rgb48le, unit_to_code},
+    pixel::RgbPixel,
+    tm::ToneMapMethod,
+    y4m::{open_rgb_frames, OpenFramesError, RgbFrames},
+};
+use clap::{
+    app_from_crate, crate_authors, crate_description, crate_name, crate_version, Arg, Error,
+    ErrorKind as ClapErrorKind,
+};
+
+fn main() {
+
+    let matches = app_from_crate!()
+        .arg(Arg::with_name("width")
+            .long("width")
+            .short("w")
+            .value_name("PIXELS")
+            .help("Width of the input video stream; taken from the header of Y4M input")
+            .takes_value(true)
+            .required(false)
+            .validator(|value| {
+                if value.parse::<usize>().is_ok() {
+                    Ok(())
+                } else {
+                    Err("Must be an unsigned integer".to_string())
+                }
+            })
+        )
+        .arg(Arg::with_name("height")
+            .long("height")
+            .short("h")
+            .value_name("PIXELS")
+            .help("Height of the input video stream; taken from the header of Y4M input")
+            .takes_value(true)
+            .required(false)
+            .validator(|value| {
+                if value.parse::<usize>().is_ok() {
+                    Ok(())
+                } else {
+                    Err("Must be an unsigned integer".to_string())
+                }
+            })
+        )
+        .arg(Arg::with_name("threads")
+            .long("threads")
+            .short("j")
+            .value_name("COUNT")
+            .help("Number of threads converting each frame; defaults to the number of logical \
+                CPUs")
+            .takes_value(true)
+            .required(false)
+            .validator(|value| {
+                match value.parse::<usize>() {
+                    Ok(threads) if threads > 0 => Ok(()),
+                    _ => Err("Must be a positive integer".to_string()),
+                }
+            })
+        )
+        .arg(Arg::with_name("exposure")
+            .long("exposure")
+            .short("e")
+            .value_name("FACTOR")
+            .help("Scales the exposure of the input video by the specified factor using Oklab \
+                (power curve)")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("ref-white")
+            .validator(|value| {
+                let ref_white = value.parse::<f64>();
+                if ref_white.is_err() {
+                    return Err("Must be a floating point value".to_string())
+                }
+                let ref_white_value = ref_white.unwrap();
+                if !ref_white_value.is_normal() {
+                    return Err("Must be a normal number".to_string())
+                }
+                if !ref_white_value.is_sign_positive() {
+                    return Err("Must be a positive number".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("ref-white")
+            .long("ref-white")
+            .short("r")
+            .value_name("NITS")
+            .help("Brightness of the input video stream's reference white level")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("lum-scale")
+            .validator(|value| {
+                let ref_white = value.parse::<f64>();
+                if ref_white.is_err() {
+                    return Err("Must be a floating point value".to_string())
+                }
+                let ref_white_value = ref_white.unwrap();
+                if !ref_white_value.is_normal() {
+                    return Err("Must be a normal number".to_string())
+                }
+                if !ref_white_value.is_sign_positive() {
+                    return Err("Must be a positive number".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("lum-scale")
+            .long("lum-scale")
+            .short("l")
+            .value_name("FACTOR")
+            .help("Scales the exposure of the input video by the specified factor using Oklab \
+                (linear)")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("exposure")
+            .validator(|value| {
+                let ref_white = value.parse::<f64>();
+                if ref_white.is_err() {
+                    return Err("Must be a floating point value".to_string())
+                }
+                let ref_white_value = ref_white.unwrap();
+                if !ref_white_value.is_normal() {
+                    return Err("Must be a normal number".to_string())
+                }
+                if !ref_white_value.is_sign_positive() {
+                    return Err("Must be a positive number".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("max-cll")
+            .long("max-cll")
+            .short("m")
+            .value_name("NITS")
+            .help("MaxCLL value of the PQ input or the PQ output.")
+            .takes_value(true)
+            .required(false)
+            .default_value("1000")
+            .validator(|value| {
+                let max_cll = value.parse::<f64>();
+                if max_cll.is_err() {
+                    return Err("Must be a floating point value".to_string())
+                }
+                let max_cll_value = max_cll.unwrap();
+                if !max_cll_value.is_normal() {
+                    return Err("Must be a normal number".to_string())
+                }
+                if !max_cll_value.is_sign_positive() {
+                    return Err("Must be a positive number".to_string())
+                }
+                if max_cll_value > 10_000.0 {
+                    return Err("Must not exceed 10,000.0.".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("tone-map-method")
+            .long("tone-map-method")
+            .help("Tone mapping method to use.")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["rgb", "maxrgb"])
+            .default_value("maxrgb")
+        )
+        .arg(Arg::with_name("conversion")
+            .index(1)
+            .value_name("CONVERSION")
+            .help("Conversion to apply to each pixel")
+            .required(true)
+            .possible_values(&["pq2hlg", "pq2sdr", "hlg2pq"])
+        )
+        .arg(Arg::with_name("input")
+            .index(2)
+            .value_name("INPUT-FILE")
+            .help("Raw input video stream (BT.2020, RGB48LE) or Y4M stream; use - for STDIN")
+            .required(true)
+        )
+        .after_help(format!("This utility reads raw RGB48LE frames, converts every pixel \
+            directly with the same math used to generate the LUTs of PQ2HLG and HLG2PQ, and \
+            writes the converted RGB48LE frames to STDOUT. No interpolation is involved. Each \
+            frame is split into bands of rows that are converted in parallel. The \
+            pq2sdr conversion is the black and white preview of PQ2HLG --preview. Exposure \
+            options only apply to conversions from PQ.\n\n\
+            Y4M input is recognized automatically, in which case --width and --height are taken \
+            from its header and each frame is converted to RGB using BT.2020 coefficients, \
+            honoring any XCOLORRANGE extension and otherwise assuming limited range. Chroma \
+            is upsampled from the siting given by the C parameter of the header.\n\n\
+            Copyright © 2024 William Swartzendruber\n\
+            Licensed under the Mozilla Public License 2.0\n\
+            <{}>", env!("CARGO_PKG_REPOSITORY")).as_str())
+        .get_matches();
+    let geometry = matches
+        .value_of("width")
+        .zip(matches.value_of("height"))
+        .map(|(width, height)| (width.parse::<usize>().unwrap(), height.parse::<usize>().unwrap()));
+    let threads = matches
+        .value_of("threads")
+        .map(|value| value.parse::<usize>().unwrap())
+        .unwrap_or_else(default_threads);
+    let max_cll = matches.value_of("max-cll").unwrap().parse::<f64>().unwrap();
+    let tm_method = match matches.value_of("tone-map-method").unwrap() {
+        "rgb" => ToneMapMethod::Rgb,
+        "maxrgb" => ToneMapMethod::MaxRgb,
+        _ => unreachable!("--tone-map-method select is irrational"),
+    };
+    let conversion = matches.value_of("conversion").unwrap();
+    let exposure = matches.value_of("exposure").map(|value| value.parse::<f64>().unwrap());
+    let ref_white = matches.value_of("ref-white").map(|value| value.parse::<f64>().unwrap());
+    let lum_scale = matches.value_of("lum-scale").map(|value| value.parse::<f64>().unwrap());
+
+    if conversion == "hlg2pq"
+        && (exposure.is_some() || ref_white.is_some() || lum_scale.is_some()) {
+        Error::with_description(
+            "--exposure, --ref-white, and --lum-scale are not supported with hlg2pq",
+            ClapErrorKind::ArgumentConflict,
+        ).exit()
+    }
+
+    let mapper: Box<dyn Mapper + Sync> = match conversion {
+        "pq2hlg" => {
+            Box::new(match (exposure, ref_white, lum_scale) {
+                (None, None, None) => {
+                    PqHlgMapper::new(max_cll, tm_method)
+                }
+                (Some(exposure), None, None) => {
+                    PqHlgMapper::new_by_factor(exposure, max_cll, tm_method)
+                }
+                (None, Some(ref_white), None) => {
+                    PqHlgMapper::new_by_ref_white(ref_white, max_cll, tm_method)
+                }
+                (None, None, Some(lum_scale)) => {
+                    PqHlgMapper::new_by_lum_scale(lum_scale, max_cll, tm_method)
+                }
+                _ => {
+                    unreachable!("Two or more of --exposure, --ref-white, and --lum-scale were \
+                        somehow defined")
+                }
+            })
+        }
+        "pq2sdr" => {
+            Box::new(match (exposure, ref_white, lum_scale) {
+                (None, None, None) => {
+                    PqSdrMapper::new(max_cll, tm_method)
+                }
+                (Some(exposure), None, None) => {
+                    PqSdrMapper::new_by_factor(exposure, max_cll, tm_method)
+                }
+                (None, Some(ref_white), None) => {
+                    PqSdrMapper::new_by_ref_white(ref_white, max_cll, tm_method)
+                }
+                (None, None, Some(lum_scale)) => {
+                    PqSdrMapper::new_by_lum_scale(lum_scale, max_cll, tm_method)
+                }
+                _ => {
+                    unreachable!("Two or more of --exposure, --ref-white, and --lum-scale were \
+                        somehow defined")
+                }
+            })
+        }
+        "hlg2pq" => {
+            Box::new(HlgPqMapper::new(max_cll))
+        }
+        _ => unreachable!("CONVERSION select is irrational"),
+    };
+    let input_value = matches.value_of("input").unwrap();
+    let (mut stdin_read, mut file_read);
+    let mut input = BufReader::<&mut dyn Read>::new(
+        if input_value == "-" {
+            stdin_read = stdin();
+            &mut stdin_read
+        } else {
+            file_read = File::open(input_value)
+                .expect("Could not open input file for reading.");
+            &mut file_read
+        }
+    );
+    let mut frames_read = open_rgb_frames(&mut input, geometry)
+        .unwrap_or_else(|err| open_frames_error(err));
+    let mut stdout_write = stdout();
+    let mut output = BufWriter::<&mut dyn Write>::new(&mut stdout_write);
+
+    convert_frames(mapper.as_ref(), &mut frames_read, &mut output, threads)
+        .unwrap_or_else(|err| panic!("Could not convert frame from input stream: {:?}", err));
+    output.flush().expect("Could not write to output stream.");
+}
+
+// Converts frames until the input ends cleanly between them, returning how many there were.
+fn convert_frames(
+    mapper: &(dyn Mapper + Sync),
+    frames_read: &mut RgbFrames,
+    output: &mut dyn Write,
+    threads: usize,
+) -> Result<usize> {
+
+    let (width, height) = frames_read.size();
+    let mut count = 0;
+
+    loop {
+
+        let result = match frames_read {
+            RgbFrames::Raw { input, .. } => {
+                convert_frame(mapper, input.as_mut(), output, width, height, threads)
+            }
+            RgbFrames::Y4m(reader) => {
+                reader.read_rgb_frame().and_then(|frame| {
+                    convert_pixels(mapper, &frame, output, width, threads)
+                })
+            }
+        };
+
+        match result {
+            Ok(()) => count += 1,
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(count),
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+fn convert_frame(
+    mapper: &(dyn Mapper + Sync),
+    input: &mut dyn Read,
+    output: &mut dyn Write,
+    width: usize,
+    height: usize,
+    threads: usize,
+) -> Result<()> {
+
+    let mut frame = vec![0_u8; 6 * width * height];
+
+    input.read_exact(&mut frame)?;
+    map_rgb48le(mapper, &mut frame, width, threads);
+
+    output.write_all(&frame)
+}
+
+// Y4M frames are already decoded, so they are mapped straight from their RGB values.
+fn convert_pixels(
+    mapper: &(dyn Mapper + Sync),
+    frame: &[RgbPixel],
+    output: &mut dyn Write,
+    width: usize,
+    threads: usize,
+) -> Result<()> {
+
+    let bands = map_bands(frame, width, threads, |band| {
+        band.iter()
+            .flat_map(|pixel| {
+                let mapped = mapper.map(*pixel);
+                [mapped.red, mapped.green, mapped.blue]
+            })
+            .flat_map(|value| unit_to_code(value).to_le_bytes())
+            .collect::<Vec<u8>>()
+    });
+
+    for band in bands {
+        output.write_all(&band)?;
+    }
+
+    Ok(())
+}
+
+fn open_frames_error(err: OpenFramesError) -> ! {
+    match err {
+        OpenFramesError::MissingGeometry => {
+            Error::with_description(
+                "--width and --height are required unless the input is Y4M",
+                ClapErrorKind::MissingRequiredArgument,
+            ).exit()
+        }
+        OpenFramesError::ConflictingGeometry => {
+            Error::with_description(
+                "--width and --height cannot be used with Y4M input",
+                ClapErrorKind::ArgumentConflict,
+            ).exit()
+        }
+        err => {
+            Error::with_description(&err.to_string(), ClapErrorKind::InvalidValue).exit()
+        }
+    }
+}
@@ -0,0 +1,171 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use std::io::Cursor;
+use super::*;
+use dsp::{frame::{code_to_unit, unit_to_code}, pixel::RgbPixel};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+
+fn frame(codes: &[u16]) -> Vec<u8> {
+
+    let mut frame = vec![0_u8; 0];
+
+    for code in codes {
+        frame.write_u16::<LittleEndian>(*code).unwrap();
+    }
+
+    frame
+}
+
+fn codes(frame: &[u8]) -> Vec<u16> {
+
+    let mut cursor = Cursor::new(frame);
+    let mut codes = Vec::new();
+
+    while let Ok(code) = cursor.read_u16::<LittleEndian>() {
+        codes.push(code);
+    }
+
+    codes
+}
+
+#[test]
+fn test_convert_frame_matches_mapper() {
+
+    let mapper = PqHlgMapper::new(4_000.0, ToneMapMethod::MaxRgb);
+    let input = [0x0000, 0x0000, 0x0000, 0x8000, 0x4000, 0x2000, 0xFFFF, 0xFFFF, 0xFFFF];
+    let mut cursor = Cursor::new(frame(&input));
+    let mut output = Vec::new();
+
+    convert_frame(&mapper, &mut cursor, &mut output, 3, 1, 2).unwrap();
+
+    let output = codes(&output);
+
+    assert_eq!(output.len(), input.len());
+
+    for (pixel_in, pixel_out) in input.chunks(3).zip(output.chunks(3)) {
+
+        let expected = mapper.map(RgbPixel::new_rgb(
+            code_to_unit(pixel_in[0]),
+            code_to_unit(pixel_in[1]),
+            code_to_unit(pixel_in[2]),
+        ));
+
+        assert_eq!(pixel_out, [
+            unit_to_code(expected.red),
+            unit_to_code(expected.green),
+            unit_to_code(expected.blue),
+        ]);
+    }
+    assert_eq!(&output[0..3], &[0, 0, 0]);
+}
+
+#[test]
+fn test_convert_frame_sequence() {
+
+    let mapper = HlgPqMapper::new(1_000.0);
+    let mut cursor = Cursor::new(frame(&[0xFFFF; 12]));
+    let mut output = Vec::new();
+
+    convert_frame(&mapper, &mut cursor, &mut output, 1, 2, 2).unwrap();
+    convert_frame(&mapper, &mut cursor, &mut output, 1, 2, 2).unwrap();
+
+    let error = convert_frame(&mapper, &mut cursor, &mut output, 1, 2, 2).unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    assert_eq!(codes(&output).len(), 12);
+}
+
+#[test]
+fn test_convert_frame_truncated() {
+
+    let mapper = HlgPqMapper::new(1_000.0);
+    let mut cursor = Cursor::new(frame(&[0xFFFF; 5]));
+    let mut output = Vec::new();
+
+    let error = convert_frame(&mapper, &mut cursor, &mut output, 1, 2, 2).unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::UnexpectedEof);
+    assert!(output.is_empty());
+}
+
+#[test]
+fn test_convert_frames_raw() {
+
+    let mapper = PqHlgMapper::new(1_000.0, ToneMapMethod::MaxRgb);
+    let input = [0x1000, 0x2000, 0x3000, 0x9000, 0x8000, 0x7000, 0xC000, 0xC000, 0xC000, 0, 0, 0];
+    let mut cursor = Cursor::new(frame(&input));
+    let mut frames_read = open_rgb_frames(&mut cursor, Some((2, 1))).unwrap();
+    let mut output = Vec::new();
+
+    assert_eq!(convert_frames(&mapper, &mut frames_read, &mut output, 2).unwrap(), 2);
+
+    let expected = input
+        .chunks(3)
+        .flat_map(|pixel| {
+            let mapped = mapper.map(RgbPixel::new_rgb(
+                code_to_unit(pixel[0]),
+                code_to_unit(pixel[1]),
+                code_to_unit(pixel[2]),
+            ));
+            [unit_to_code(mapped.red), unit_to_code(mapped.green), unit_to_code(mapped.blue)]
+        })
+        .collect::<Vec<u16>>();
+
+    assert_eq!(codes(&output), expected);
+}
+
+#[test]
+fn test_convert_frames_y4m() {
+
+    let mapper = HlgPqMapper::new(1_000.0);
+    let mut stream = b"YUV4MPEG2 W3 H2 F25:1 C444p10 XCOLORRANGE=FULL\n".to_vec();
+
+    for _ in 0..2 {
+        stream.extend_from_slice(b"FRAME\n");
+        stream.extend(frame(&[0, 256, 512, 768, 1_023, 100, 512, 512, 512, 512, 512, 512]));
+        stream.extend(frame(&[512; 6]));
+    }
+
+    let mut cursor = Cursor::new(stream);
+    let mut frames_read = open_rgb_frames(&mut cursor, None).unwrap();
+    let mut output = Vec::new();
+
+    assert_eq!(frames_read.size(), (3, 2));
+    assert_eq!(convert_frames(&mapper, &mut frames_read, &mut output, 2).unwrap(), 2);
+
+    let output = codes(&output);
+
+    assert_eq!(output.len(), 2 * 3 * 2 * 3);
+    assert_eq!(&output[..3], &[0, 0, 0]);
+    assert_eq!(output[..18], output[18..]);
+
+    // Neutral chroma leaves every pixel gray, brighter as luma rises.
+    for pixel in output[..18].chunks(3) {
+        assert!(pixel[0] == pixel[1] && pixel[1] == pixel[2]);
+    }
+    assert!(output[3] < output[6] && output[6] < output[9] && output[9] < output[12]);
+}
+
+#[test]
+fn test_convert_frames_y4m_truncated() {
+
+    let mapper = HlgPqMapper::new(1_000.0);
+    let mut cursor = Cursor::new(b"YUV4MPEG2 W2 H1 Cmono\nFRAME\n\x10\x20FRAME\n\x10".to_vec());
+    let mut frames_read = open_rgb_frames(&mut cursor, None).unwrap();
+    let mut output = Vec::new();
+
+    let error = convert_frames(&mapper, &mut frames_read, &mut output, 2).unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert_eq!(codes(&output).len(), 6);
+}
@@ -13,8 +13,9 @@ use std::{
     io::{stdout, BufWriter, Write},
 };
 use dsp::{
-    tf::{sdr_e_to_o, sdr_o_to_e},
-    pixel::{OklabPixel, RgbPixel},
+    SdrMonoMapper,
+    lut::Lut3d,
+    range::{RangeMapper, SignalRange},
 };
 use clap::{app_from_crate, crate_authors, crate_description, crate_name, crate_version, Arg};
 
@@ -52,26 +53,101 @@ fn main() {
                     return Err("Must be an unsigned integer value".to_string())
                 }
                 let size_value = size.unwrap();
-                if size_value < 2 || size_value > 256 {
+                if !(2..=256).contains(&size_value) {
                     return Err("Must be between 2 and 256".to_string())
                 }
                 Ok(())
             })
         )
+        .arg(Arg::with_name("format")
+            .long("format")
+            .short("f")
+            .help("File format of the generated LUT")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["cube", "3dl", "hald", "spi3d"])
+            .default_value("cube")
+        )
+        .arg(Arg::with_name("bit-depth")
+            .long("bit-depth")
+            .short("b")
+            .value_name("BITS")
+            .help("Bit depth of the output code values in integer formats such as 3DL")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["10", "12", "16"])
+            .default_value("12")
+        )
+        .arg(Arg::with_name("hald-level")
+            .long("hald-level")
+            .value_name("LEVEL")
+            .help("Level of a Hald CLUT image, which replaces --size with the level squared")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["8", "12", "16"])
+            .default_value("8")
+        )
+        .arg(Arg::with_name("input-range")
+            .long("input-range")
+            .value_name("RANGE")
+            .help("Quantization range of the input signal")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["full", "limited"])
+            .default_value("full")
+        )
+        .arg(Arg::with_name("output-range")
+            .long("output-range")
+            .value_name("RANGE")
+            .help("Quantization range of the output signal")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["full", "limited"])
+            .default_value("full")
+        )
+        .arg(Arg::with_name("range-depth")
+            .long("range-depth")
+            .value_name("BITS")
+            .help("Bit depth of the video whose limited range code values are targeted")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["8", "10", "12", "16"])
+            .default_value("10")
+        )
         .arg(Arg::with_name("output")
             .index(1)
             .value_name("OUTPUT-FILE")
-            .help("Output Cube LUT file; use - for STDOUT")
+            .help("Output LUT file; use - for STDOUT")
             .required(true)
         )
         .after_help(format!("This utility uses Oklab to generate a 3D LUT that will monochrome \
             BT.709.\n\n\
+            With --input-range limited or --output-range limited, the LUT instead expects or \
+            produces limited range code values for the --range-depth bit depth, such as 16 to \
+            235 at 8 bits.\n\n\
             Copyright © 2024 William Swartzendruber\n\
             Licensed under the Mozilla Public License 2.0\n\
             <{}>", env!("CARGO_PKG_REPOSITORY")).as_str())
         .get_matches();
     let title = matches.value_of("title");
-    let size = matches.value_of("size").unwrap().parse::<usize>().unwrap();
+    let format = matches.value_of("format").unwrap();
+    let size = if format == "hald" {
+        matches.value_of("hald-level").unwrap().parse::<usize>().unwrap().pow(2)
+    } else {
+        matches.value_of("size").unwrap().parse::<usize>().unwrap()
+    };
+    let bit_depth = matches.value_of("bit-depth").unwrap().parse::<u32>().unwrap();
+    let input_range = match matches.value_of("input-range").unwrap() {
+        "full" => SignalRange::Full,
+        "limited" => SignalRange::Limited,
+        _ => unreachable!("--input-range select is irrational"),
+    };
+    let output_range = match matches.value_of("output-range").unwrap() {
+        "full" => SignalRange::Full,
+        "limited" => SignalRange::Limited,
+        _ => unreachable!("--output-range select is irrational"),
+    };
+    let range_depth = matches.value_of("range-depth").unwrap().parse::<u32>().unwrap();
     let output_value = matches.value_of("output").unwrap();
     let (mut stdout_write, mut file_write);
     let mut output = BufWriter::<&mut dyn Write>::new(
@@ -85,37 +161,23 @@ fn main() {
         }
     );
 
-    writeln!(output, "# Generated by Mono709 {}", env!("CARGO_PKG_VERSION")).unwrap();
-    if title.is_some() {
-        writeln!(output, "TITLE \"{}\"", title.unwrap()).unwrap();
-    }
-    writeln!(output, "LUT_3D_SIZE {}", size).unwrap();
-
-    for b in 0..size {
-        for g in 0..size {
-            for r in 0..size {
-
-                let rgb_1 = RgbPixel
-                {
-                    red: r as f64 / (size - 1) as f64,
-                    green: g as f64 / (size - 1) as f64,
-                    blue: b as f64 / (size - 1) as f64,
-                };
-                let rgb_2 = rgb_1.with_each_channel(|x| sdr_e_to_o(x));
-                let oklab = OklabPixel {
-                    l: rgb_2.bt709_to_xyz().to_oklab().l,
-                    a: 0.0,
-                    b: 0.0,
-                };
-                let rgb_3 = oklab.to_xyz().to_rgb_bt709();
-                let rgb_4 = rgb_3.with_each_channel(|x| sdr_o_to_e(x));
+    let mut lut = Lut3d::from_mapper(
+        &RangeMapper::new(&SdrMonoMapper, input_range, output_range, range_depth, false),
+        size,
+    );
 
-                writeln!(output, "{} {} {}",
-                    rgb_4.red as f32,
-                    rgb_4.green as f32,
-                    rgb_4.blue as f32,
-                ).unwrap();
-            }
-        }
+    lut.title = title.map(String::from);
+    lut.comments.push(format!("Generated by Mono709 {}", env!("CARGO_PKG_VERSION")));
+    if (input_range, output_range) != (SignalRange::Full, SignalRange::Full) {
+        lut.comments.push(format!("input-range: {}", matches.value_of("input-range").unwrap()));
+        lut.comments.push(format!("output-range: {}", matches.value_of("output-range").unwrap()));
+        lut.comments.push(format!("range-depth: {}", range_depth));
     }
+    match format {
+        "cube" => lut.write_cube(&mut output),
+        "3dl" => lut.write_3dl(&mut output, bit_depth),
+        "hald" => lut.write_hald(&mut output),
+        "spi3d" => lut.write_spi3d(&mut output),
+        _ => unreachable!("--format select is irrational"),
+    }.expect("Could not write to output file.");
 }
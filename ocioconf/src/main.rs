@@ -0,0 +1,320 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+use std::{
+    fs::{create_dir_all, File},
+    io::BufWriter,
+    path::Path,
+};
+use dsp::{
+    HlgPqMapper,
+    PqHlgMapper,
+    PqSdrMapper,
+    clf::{hlg_pq_process_list, pq_hlg_process_list, ProcessList},
+    lut::{Lut1d, Lut3d},
+    ocio::{OcioConfig, OcioLook, OcioSpace},
+    tf::pq_eotf,
+    tm::ToneMapMethod,
+};
+use clap::{app_from_crate, crate_authors, crate_description, crate_name, crate_version, Arg};
+
+const LUT_DIR: &str = "luts";
+const PQ_EOTF_FILE: &str = "pq_eotf.spi1d";
+const HLG_PQ_1000_FILE: &str = "hlg_1000_nits.clf";
+const PQ_HLG_FILE: &str = "pq_hlg.clf";
+const HLG_PQ_FILE: &str = "hlg_pq.clf";
+const PQ_SDR_FILE: &str = "pq_sdr.spi3d";
+
+fn main() {
+
+    let matches = app_from_crate!()
+        .arg(Arg::with_name("exposure")
+            .long("exposure")
+            .short("e")
+            .value_name("FACTOR")
+            .help("Scales the exposure of the input video by the specified factor using Oklab \
+                (power curve)")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("ref-white")
+            .validator(|value| {
+                let ref_white = value.parse::<f64>();
+                if ref_white.is_err() {
+                    return Err("Must be a floating point value".to_string())
+                }
+                let ref_white_value = ref_white.unwrap();
+                if !ref_white_value.is_normal() {
+                    return Err("Must be a normal number".to_string())
+                }
+                if !ref_white_value.is_sign_positive() {
+                    return Err("Must be a positive number".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("ref-white")
+            .long("ref-white")
+            .short("r")
+            .value_name("NITS")
+            .help("Brightness of the input video stream's reference white level")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("lum-scale")
+            .validator(|value| {
+                let ref_white = value.parse::<f64>();
+                if ref_white.is_err() {
+                    return Err("Must be a floating point value".to_string())
+                }
+                let ref_white_value = ref_white.unwrap();
+                if !ref_white_value.is_normal() {
+                    return Err("Must be a normal number".to_string())
+                }
+                if !ref_white_value.is_sign_positive() {
+                    return Err("Must be a positive number".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("lum-scale")
+            .long("lum-scale")
+            .short("l")
+            .value_name("FACTOR")
+            .help("Scales the exposure of the input video by the specified factor using Oklab \
+                (linear)")
+            .takes_value(true)
+            .required(false)
+            .conflicts_with("exposure")
+            .validator(|value| {
+                let ref_white = value.parse::<f64>();
+                if ref_white.is_err() {
+                    return Err("Must be a floating point value".to_string())
+                }
+                let ref_white_value = ref_white.unwrap();
+                if !ref_white_value.is_normal() {
+                    return Err("Must be a normal number".to_string())
+                }
+                if !ref_white_value.is_sign_positive() {
+                    return Err("Must be a positive number".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("max-cll")
+            .long("max-cll")
+            .short("m")
+            .value_name("NITS")
+            .help("MaxCLL value of the PQ input and the PQ output of HLG conversions.")
+            .takes_value(true)
+            .required(false)
+            .default_value("1000")
+            .validator(|value| {
+                let max_cll = value.parse::<f64>();
+                if max_cll.is_err() {
+                    return Err("Must be a floating point value".to_string())
+                }
+                let max_cll_value = max_cll.unwrap();
+                if !max_cll_value.is_normal() {
+                    return Err("Must be a normal number".to_string())
+                }
+                if !max_cll_value.is_sign_positive() {
+                    return Err("Must be a positive number".to_string())
+                }
+                if max_cll_value > 10_000.0 {
+                    return Err("Must not exceed 10,000.0.".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("tone-map-method")
+            .long("tone-map-method")
+            .help("Tone mapping method to use.")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["rgb", "maxrgb"])
+            .default_value("maxrgb")
+        )
+        .arg(Arg::with_name("size")
+            .long("size")
+            .short("s")
+            .value_name("COUNT")
+            .help("The size of each dimension of the 3D LUTs")
+            .takes_value(true)
+            .required(false)
+            .default_value("64")
+            .validator(|value| {
+                let size = value.parse::<usize>();
+                if size.is_err() {
+                    return Err("Must be an unsigned integer value".to_string())
+                }
+                let size_value = size.unwrap();
+                if !(2..=256).contains(&size_value) {
+                    return Err("Must be between 2 and 256".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("output")
+            .index(1)
+            .value_name("OUTPUT-DIR")
+            .help("Directory to receive config.ocio and its LUTs")
+            .required(true)
+        )
+        .after_help(format!("This utility writes an OpenColorIO v2 config describing BT.2100 \
+            PQ, BT.2100 HLG, and BT.709 SDR as display color spaces, along with one display per \
+            color space. The conversions performed by PQ2HLG, HLG2PQ, and PQ2HLG --preview are \
+            added as looks using the provided --max-cll and exposure settings, and each look is \
+            offered as a view on the display of its input. The LUTs these depend upon are \
+            written into a luts directory beside config.ocio.\n\n\
+            Copyright © 2024 William Swartzendruber\n\
+            Licensed under the Mozilla Public License 2.0\n\
+            <{}>", env!("CARGO_PKG_REPOSITORY")).as_str())
+        .get_matches();
+    let mut header = vec![];
+    let max_cll = matches.value_of("max-cll").unwrap().parse::<f64>().unwrap();
+    let tm_method = || match matches.value_of("tone-map-method").unwrap() {
+        "rgb" => ToneMapMethod::Rgb,
+        "maxrgb" => ToneMapMethod::MaxRgb,
+        _ => unreachable!("--tone-map-method select is irrational"),
+    };
+    let size = matches.value_of("size").unwrap().parse::<usize>().unwrap();
+    let (pq_hlg_mapper, pq_sdr_mapper) = match (
+        matches.value_of("exposure"),
+        matches.value_of("ref-white"),
+        matches.value_of("lum-scale")
+    ) {
+        (None, None, None) => {
+            (
+                PqHlgMapper::new(max_cll, tm_method()),
+                PqSdrMapper::new(max_cll, tm_method()),
+            )
+        }
+        (Some(exposure), None, None) => {
+            header.push(format!("exposure: {}", exposure));
+            let factor = exposure.parse::<f64>().unwrap();
+            (
+                PqHlgMapper::new_by_factor(factor, max_cll, tm_method()),
+                PqSdrMapper::new_by_factor(factor, max_cll, tm_method()),
+            )
+        }
+        (None, Some(ref_white), None) => {
+            header.push(format!("ref-white: {}", ref_white));
+            let ref_white = ref_white.parse::<f64>().unwrap();
+            (
+                PqHlgMapper::new_by_ref_white(ref_white, max_cll, tm_method()),
+                PqSdrMapper::new_by_ref_white(ref_white, max_cll, tm_method()),
+            )
+        }
+        (None, None, Some(lum_scale)) => {
+            header.push(format!("lum-scale: {}", lum_scale));
+            let lum_scale = lum_scale.parse::<f64>().unwrap();
+            (
+                PqHlgMapper::new_by_lum_scale(lum_scale, max_cll, tm_method()),
+                PqSdrMapper::new_by_lum_scale(lum_scale, max_cll, tm_method()),
+            )
+        }
+        _ => {
+            unreachable!("Two or more of --exposure, --ref-white, and --lum-scale were somehow \
+                defined")
+        }
+    };
+    let generator = format!("OCIOCONF {}", env!("CARGO_PKG_VERSION"));
+    let output_dir = Path::new(matches.value_of("output").unwrap());
+    let lut_dir = output_dir.join(LUT_DIR);
+
+    create_dir_all(&lut_dir).expect("Could not create output directory.");
+
+    Lut1d::from_fn(4_096, pq_eotf)
+        .write_spi1d(&mut create(&lut_dir.join(PQ_EOTF_FILE)))
+        .expect("Could not write to output file.");
+    write_clf(
+        hlg_pq_process_list(&HlgPqMapper::new(1_000.0), size),
+        &generator,
+        vec![(String::from("max-cll"), String::from("1000"))],
+        &lut_dir.join(HLG_PQ_1000_FILE),
+    );
+
+    let mut pq_hlg_parameters = vec![
+        (String::from("max-cll"), max_cll.to_string()),
+        (String::from("tone-map-method"), matches.value_of("tone-map-method").unwrap().into()),
+    ];
+
+    pq_hlg_parameters.extend(header.iter().filter_map(|line| {
+        line.split_once(": ").map(|(name, value)| (String::from(name), String::from(value)))
+    }));
+    write_clf(
+        pq_hlg_process_list(&pq_hlg_mapper, size),
+        &generator,
+        pq_hlg_parameters,
+        &lut_dir.join(PQ_HLG_FILE),
+    );
+    write_clf(
+        hlg_pq_process_list(&HlgPqMapper::new(max_cll), size),
+        &generator,
+        vec![(String::from("max-cll"), max_cll.to_string())],
+        &lut_dir.join(HLG_PQ_FILE),
+    );
+    Lut3d::from_mapper(&pq_sdr_mapper, size)
+        .write_spi3d(&mut create(&lut_dir.join(PQ_SDR_FILE)))
+        .expect("Could not write to output file.");
+
+    // OCIO separates the looks of a view with commas and colons, so neither may appear in a name.
+    let settings = std::iter::once(format!("max-cll {}", max_cll))
+        .chain(header.iter().map(|line| line.replace(": ", " ")))
+        .collect::<Vec<String>>()
+        .join(" ");
+    let config = OcioConfig {
+        description: format!("Generated by {}", generator),
+        search_path: String::from(LUT_DIR),
+        pq_eotf_file: String::from(PQ_EOTF_FILE),
+        hlg_pq_file: String::from(HLG_PQ_1000_FILE),
+        looks: vec![
+            OcioLook {
+                name: format!("PQ to HLG ({})", settings),
+                description: String::from("BT.2408 conversion from PQ to HLG"),
+                process_space: OcioSpace::Pq,
+                output_space: OcioSpace::Hlg,
+                file: String::from(PQ_HLG_FILE),
+            },
+            OcioLook {
+                name: format!("PQ to SDR Preview ({})", settings),
+                description: String::from("Black and white SDR preview of PQ to HLG"),
+                process_space: OcioSpace::Pq,
+                output_space: OcioSpace::Sdr,
+                file: String::from(PQ_SDR_FILE),
+            },
+            OcioLook {
+                name: format!("HLG to PQ (max-cll {})", max_cll),
+                description: String::from("BT.2408 conversion from HLG to PQ"),
+                process_space: OcioSpace::Hlg,
+                output_space: OcioSpace::Pq,
+                file: String::from(HLG_PQ_FILE),
+            },
+        ],
+    };
+
+    config.write_config(&mut create(&output_dir.join("config.ocio")))
+        .expect("Could not write to output file.");
+}
+
+fn create(path: &Path) -> BufWriter<File> {
+    BufWriter::new(File::create(path).expect("Could not open output file for writing."))
+}
+
+fn write_clf(
+    mut process_list: ProcessList,
+    generator: &str,
+    parameters: Vec<(String, String)>,
+    path: &Path,
+) {
+
+    process_list.generator = Some(String::from(generator));
+    process_list.parameters = parameters;
+    process_list.write_clf(&mut create(path)).expect("Could not write to output file.");
+}
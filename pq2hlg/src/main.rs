@@ -16,14 +16,27 @@ use dsp::{
     Mapper,
     PqHlgMapper,
     PqSdrMapper,
-    pixel::RgbPixel,
+    clf::pq_hlg_process_list,
+    dctl::pq_hlg_dctl,
+    glsl::{pq_hlg_shader, pq_sdr_shader, MpvHook},
+    lut::{
+        InterpolationMethod,
+        Lut3d,
+        ShapedLut,
+        accuracy::{accuracy_report, OutputSignal},
+    },
+    range::{RangeMapper, SignalRange},
     tm::ToneMapMethod,
 };
-use clap::{app_from_crate, crate_authors, crate_description, crate_name, crate_version, Arg};
+use clap::{
+    app_from_crate, crate_authors, crate_description, crate_name, crate_version, AppSettings,
+    Arg, Error, ErrorKind, SubCommand,
+};
 
 fn main() {
 
     let matches = app_from_crate!()
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(Arg::with_name("title")
             .long("title")
             .short("t")
@@ -166,18 +179,136 @@ fn main() {
                     return Err("Must be an unsigned integer value".to_string())
                 }
                 let size_value = size.unwrap();
-                if size_value < 2 || size_value > 256 {
+                if !(2..=256).contains(&size_value) {
                     return Err("Must be between 2 and 256".to_string())
                 }
                 Ok(())
             })
         )
+        .arg(Arg::with_name("shaper-size")
+            .long("shaper-size")
+            .value_name("COUNT")
+            .help("Prepends a 1D shaper of the specified size to the 3D LUT of a Cube file")
+            .takes_value(true)
+            .required(false)
+            .validator(|value| {
+                let size = value.parse::<usize>();
+                if size.is_err() {
+                    return Err("Must be an unsigned integer value".to_string())
+                }
+                let size_value = size.unwrap();
+                if !(3..=65_536).contains(&size_value) {
+                    return Err("Must be between 3 and 65,536".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("format")
+            .long("format")
+            .short("f")
+            .help("File format of the generated LUT")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["cube", "3dl", "clf", "dctl", "glsl", "hald", "spi3d"])
+            .default_value("cube")
+        )
+        .arg(Arg::with_name("hook")
+            .long("hook")
+            .value_name("STAGE")
+            .help("Texture hooked by an mpv user shader generated with --format glsl")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["main", "output"])
+            .default_value("output")
+        )
+        .arg(Arg::with_name("bit-depth")
+            .long("bit-depth")
+            .short("b")
+            .value_name("BITS")
+            .help("Bit depth of the output code values in integer formats such as 3DL")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["10", "12", "16"])
+            .default_value("12")
+        )
+        .arg(Arg::with_name("hald-level")
+            .long("hald-level")
+            .value_name("LEVEL")
+            .help("Level of a Hald CLUT image, which replaces --size with the level squared")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["8", "12", "16"])
+            .default_value("8")
+        )
+        .arg(Arg::with_name("input-range")
+            .long("input-range")
+            .value_name("RANGE")
+            .help("Quantization range of the input signal")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["full", "limited"])
+            .default_value("full")
+        )
+        .arg(Arg::with_name("output-range")
+            .long("output-range")
+            .value_name("RANGE")
+            .help("Quantization range of the output signal")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["full", "limited"])
+            .default_value("full")
+        )
+        .arg(Arg::with_name("range-depth")
+            .long("range-depth")
+            .value_name("BITS")
+            .help("Bit depth of the video whose limited range code values are targeted")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["8", "10", "12", "16"])
+            .default_value("10")
+        )
         .arg(Arg::with_name("output")
             .index(1)
             .value_name("OUTPUT-FILE")
-            .help("Output Cube LUT file; use - for STDOUT")
+            .help("Output LUT file; use - for STDOUT")
             .required(true)
         )
+        .subcommand(SubCommand::with_name("accuracy")
+            .about("Reports how closely a LUT of the selected size reproduces the exact \
+                conversion")
+            .arg(Arg::with_name("interpolation")
+                .long("interpolation")
+                .short("i")
+                .help("Interpolation method used to evaluate the LUT")
+                .takes_value(true)
+                .required(false)
+                .possible_values(&["trilinear", "tetrahedral"])
+                .default_value("tetrahedral")
+            )
+            .arg(Arg::with_name("samples")
+                .long("samples")
+                .short("n")
+                .value_name("COUNT")
+                .help("Number of off-lattice samples taken along each dimension")
+                .takes_value(true)
+                .required(false)
+                .default_value("64")
+                .validator(|value| {
+                    let samples = value.parse::<usize>();
+                    if samples.is_err() {
+                        return Err("Must be an unsigned integer value".to_string())
+                    }
+                    if samples.unwrap() < 1 {
+                        return Err("Must be at least 1".to_string())
+                    }
+                    Ok(())
+                })
+            )
+            .after_help("Densely samples the input cube between lattice points and compares the \
+                interpolated LUT against the exact conversion. Errors are reported in Delta E \
+                ITP (BT.2124) and in nits, both overall and broken out by the brightness of the \
+                exact output.")
+        )
         .after_help(format!("This utility follows the BT.2408 method for generating a \
             PQ-to-HLG conversion LUT. If either --exposure or --ref-white are provided, the \
             exposure will either be scaled by the provided factor, or scaled to bring the \
@@ -185,9 +316,33 @@ fn main() {
             cause the --max-cll value to be internally adjusted as well. If the internal \
             MaxCLL value then exceeds 1,000 nits, BT.2408 tone mapping will be applied to \
             compress the input to 1,000 nits using either the maxRGB or R'G'B' method. From \
-            there, the signal will be converted to HLG. The generated LUTs are completely full \
-            range with 0.0 representing minimum brightness and 1.0 representing maximum \
-            brightness.\n\n\
+            there, the signal will be converted to HLG. By default, the generated LUTs are \
+            completely full range with 0.0 representing minimum brightness and 1.0 representing \
+            maximum brightness.\n\n\
+            With --input-range limited or --output-range limited, the LUT instead expects or \
+            produces limited range code values for the --range-depth bit depth, such as 64 to \
+            940 at 10 bits. PQ input below or above those levels is clipped, and HLG output \
+            never exceeds nominal white. These options only apply to LUT formats.\n\n\
+            With --format clf, the conversion is written as an Academy Common LUT Format \
+            process list instead. The PQ curve, the BT.2020 and XYZ matrices, and the exposure \
+            scaling appear as their own nodes, while tone mapping and the HLG inverse OOTF are \
+            baked into LUTs of the selected --size.\n\n\
+            With --format dctl, the conversion is instead written as DaVinci Resolve DCTL \
+            source that evaluates every step directly on the GPU, with the chosen exposure and \
+            tone mapping parameters embedded as constants. No LUT is involved, so --size has no \
+            effect.\n\n\
+            With --format glsl, the conversion or its preview is written as an mpv user shader \
+            for use with --glsl-shaders. By default, it hooks OUTPUT, after mpv has applied its \
+            own color management, while --hook main converts the decoded PQ signal before it. \
+            Either way, mpv still treats the converted image as PQ, so it must be played with \
+            --target-trc=pq and --target-prim=bt.2020 to reach the display unchanged.\n\n\
+            If --shaper-size is provided, a 1D shaper derived from the curvature of the \
+            conversion will precede the 3D LUT in the same Cube file, concentrating lattice \
+            points where the conversion bends the most. This layout is understood by DaVinci \
+            Resolve, but not by FFmpeg's lut3d filter. No other format accepts a shaper. The \
+            3DL, Hald, and SPI3D formats have nowhere to put one, and the 3D LUTs of --format \
+            clf are already indexed by PQ signal, so a shaper would only add entries to the \
+            file without making them any more accurate.\n\n\
             Optionally, a preview LUT can be generated to convert the input to black and white \
             SDR. This can be used to compare the converted output to available BT.709 frames \
             once they are also converted to black and white. In this way, --exposure can be \
@@ -204,78 +359,165 @@ fn main() {
         "maxrgb" => ToneMapMethod::MaxRgb,
         _ => unreachable!("--tone-map-method select is irrational"),
     };
+    let signal = if matches.is_present("preview") {
+        OutputSignal::Sdr
+    } else {
+        OutputSignal::Hlg
+    };
+    let shaper_size = matches.value_of("shaper-size").map(|value| value.parse::<usize>().unwrap());
+    let format = matches.value_of("format").unwrap();
+    let size = if format == "hald" {
+        matches.value_of("hald-level").unwrap().parse::<usize>().unwrap().pow(2)
+    } else {
+        matches.value_of("size").unwrap().parse::<usize>().unwrap()
+    };
+    let bit_depth = matches.value_of("bit-depth").unwrap().parse::<u32>().unwrap();
+    let input_range = match matches.value_of("input-range").unwrap() {
+        "full" => SignalRange::Full,
+        "limited" => SignalRange::Limited,
+        _ => unreachable!("--input-range select is irrational"),
+    };
+    let output_range = match matches.value_of("output-range").unwrap() {
+        "full" => SignalRange::Full,
+        "limited" => SignalRange::Limited,
+        _ => unreachable!("--output-range select is irrational"),
+    };
+    let range_depth = matches.value_of("range-depth").unwrap().parse::<u32>().unwrap();
+
+    if shaper_size.is_some() && format != "cube" {
+        Error::with_description(
+            "--shaper-size is only supported with --format cube",
+            ErrorKind::ArgumentConflict,
+        ).exit()
+    }
+
+    if matches.is_present("preview") && ["clf", "dctl"].contains(&format) {
+        Error::with_description(
+            &format!("--preview is not supported with --format {}", format),
+            ErrorKind::ArgumentConflict,
+        ).exit()
+    }
+
+    if (input_range, output_range) != (SignalRange::Full, SignalRange::Full) {
+        if ["clf", "dctl", "glsl"].contains(&format) {
+            Error::with_description(
+                &format!("--input-range and --output-range are not supported with --format {}",
+                    format),
+                ErrorKind::ArgumentConflict,
+            ).exit()
+        }
+        if matches.subcommand_matches("accuracy").is_some() {
+            Error::with_description(
+                "--input-range and --output-range are not supported with accuracy",
+                ErrorKind::ArgumentConflict,
+            ).exit()
+        }
+        header.push(format!("input-range: {}", matches.value_of("input-range").unwrap()));
+        header.push(format!("output-range: {}", matches.value_of("output-range").unwrap()));
+        header.push(format!("range-depth: {}", range_depth));
+    }
+
+    let mut process_list = None;
+    let mut dctl = None;
+    let mut shader = None;
     let mapper: Box<dyn Mapper> = if matches.is_present("preview") {
         header.push(String::from("preview: true"));
-        Box::new(
-            match (
-                matches.value_of("exposure"),
-                matches.value_of("ref-white"),
-                matches.value_of("lum-scale")
-            ) {
-                (None, None, None) => {
-                    PqSdrMapper::new(max_cll, tm_method)
-                }
-                (Some(exposure), None, None) => {
-                    header.push(format!("exposure: {}", exposure));
-                    PqSdrMapper::new_by_factor(
-                        exposure.parse::<f64>().unwrap(), max_cll, tm_method
-                    )
-                }
-                (None, Some(ref_white), None) => {
-                    header.push(format!("ref-white: {}", ref_white));
-                    PqSdrMapper::new_by_ref_white(
-                        ref_white.parse::<f64>().unwrap(), max_cll, tm_method
-                    )
-                }
-                (None, None, Some(lum_scale)) => {
-                    header.push(format!("lum-scale: {}", lum_scale));
-                    PqSdrMapper::new_by_lum_scale(
-                        lum_scale.parse::<f64>().unwrap(), max_cll, tm_method
-                    )
-                }
-                _ => {
-                    unreachable!("Two or more of --exposure, --ref-white, and --lum-scale were \
-                        somehow defined")
-                }
+        let sdr_mapper = match (
+            matches.value_of("exposure"),
+            matches.value_of("ref-white"),
+            matches.value_of("lum-scale")
+        ) {
+            (None, None, None) => {
+                PqSdrMapper::new(max_cll, tm_method)
             }
-        )
+            (Some(exposure), None, None) => {
+                header.push(format!("exposure: {}", exposure));
+                PqSdrMapper::new_by_factor(
+                    exposure.parse::<f64>().unwrap(), max_cll, tm_method
+                )
+            }
+            (None, Some(ref_white), None) => {
+                header.push(format!("ref-white: {}", ref_white));
+                PqSdrMapper::new_by_ref_white(
+                    ref_white.parse::<f64>().unwrap(), max_cll, tm_method
+                )
+            }
+            (None, None, Some(lum_scale)) => {
+                header.push(format!("lum-scale: {}", lum_scale));
+                PqSdrMapper::new_by_lum_scale(
+                    lum_scale.parse::<f64>().unwrap(), max_cll, tm_method
+                )
+            }
+            _ => {
+                unreachable!("Two or more of --exposure, --ref-white, and --lum-scale were \
+                    somehow defined")
+            }
+        };
+        if format == "glsl" {
+            shader = Some(pq_sdr_shader(&sdr_mapper));
+        }
+        Box::new(sdr_mapper)
     } else {
         header.push(String::from("preview: false"));
-        Box::new(
-            match (
-                matches.value_of("exposure"),
-                matches.value_of("ref-white"),
-                matches.value_of("lum-scale")
-            ) {
-                (None, None, None) => {
-                    PqHlgMapper::new(max_cll, tm_method)
-                }
-                (Some(exposure), None, None) => {
-                    header.push(format!("exposure: {}", exposure));
-                    PqHlgMapper::new_by_factor(
-                        exposure.parse::<f64>().unwrap(), max_cll, tm_method
-                    )
-                }
-                (None, Some(ref_white), None) => {
-                    header.push(format!("ref-white: {}", ref_white));
-                    PqHlgMapper::new_by_ref_white(
-                        ref_white.parse::<f64>().unwrap(), max_cll, tm_method
-                    )
-                }
-                (None, None, Some(lum_scale)) => {
-                    header.push(format!("lum-scale: {}", lum_scale));
-                    PqHlgMapper::new_by_lum_scale(
-                        lum_scale.parse::<f64>().unwrap(), max_cll, tm_method
-                    )
-                }
-                _ => {
-                    unreachable!("Two or more of --exposure, --ref-white, and --lum-scale were \
-                        somehow defined")
-                }
+        let hlg_mapper = match (
+            matches.value_of("exposure"),
+            matches.value_of("ref-white"),
+            matches.value_of("lum-scale")
+        ) {
+            (None, None, None) => {
+                PqHlgMapper::new(max_cll, tm_method)
             }
-        )
+            (Some(exposure), None, None) => {
+                header.push(format!("exposure: {}", exposure));
+                PqHlgMapper::new_by_factor(
+                    exposure.parse::<f64>().unwrap(), max_cll, tm_method
+                )
+            }
+            (None, Some(ref_white), None) => {
+                header.push(format!("ref-white: {}", ref_white));
+                PqHlgMapper::new_by_ref_white(
+                    ref_white.parse::<f64>().unwrap(), max_cll, tm_method
+                )
+            }
+            (None, None, Some(lum_scale)) => {
+                header.push(format!("lum-scale: {}", lum_scale));
+                PqHlgMapper::new_by_lum_scale(
+                    lum_scale.parse::<f64>().unwrap(), max_cll, tm_method
+                )
+            }
+            _ => {
+                unreachable!("Two or more of --exposure, --ref-white, and --lum-scale were \
+                    somehow defined")
+            }
+        };
+        if format == "clf" {
+            process_list = Some(pq_hlg_process_list(&hlg_mapper, size));
+        }
+        if format == "dctl" {
+            dctl = Some(pq_hlg_dctl(&hlg_mapper));
+        }
+        if format == "glsl" {
+            shader = Some(pq_hlg_shader(&hlg_mapper));
+        }
+        Box::new(hlg_mapper)
     };
-    let size = matches.value_of("size").unwrap().parse::<usize>().unwrap();
+
+    if let Some(accuracy_matches) = matches.subcommand_matches("accuracy") {
+
+        let interpolation = match accuracy_matches.value_of("interpolation").unwrap() {
+            "trilinear" => InterpolationMethod::Trilinear,
+            "tetrahedral" => InterpolationMethod::Tetrahedral,
+            _ => unreachable!("--interpolation select is irrational"),
+        };
+        let samples = accuracy_matches.value_of("samples").unwrap().parse::<usize>().unwrap();
+
+        print!("{}", accuracy_report(
+            mapper.as_ref(), size, shaper_size, interpolation, signal, samples,
+        ));
+
+        return
+    }
+
     let output_value = matches.value_of("output").unwrap();
     let (mut stdout_write, mut file_write);
     let mut output = BufWriter::<&mut dyn Write>::new(
@@ -289,32 +531,68 @@ fn main() {
         }
     );
 
-    writeln!(output, "# Generated by PQ2HLG {}", env!("CARGO_PKG_VERSION")).unwrap();
-    writeln!(output, "# max-cll: {}", max_cll).unwrap();
-    for line in header.iter() {
-        writeln!(output, "# {}", line).unwrap();
+    let mut comments = vec![
+        format!("Generated by PQ2HLG {}", env!("CARGO_PKG_VERSION")),
+        format!("max-cll: {}", max_cll),
+    ];
+
+    comments.extend(header);
+
+    if let Some(mut process_list) = process_list {
+        if let Some(title) = title {
+            process_list.name = String::from(title);
+        }
+        process_list.generator = Some(format!("PQ2HLG {}", env!("CARGO_PKG_VERSION")));
+        process_list.parameters = comments[1..]
+            .iter()
+            .filter_map(|comment| comment.split_once(": "))
+            .map(|(name, value)| (String::from(name), String::from(value)))
+            .collect();
+        process_list.write_clf(&mut output).expect("Could not write to output file.");
+        return
     }
-    if title.is_some() {
-        writeln!(output, "TITLE \"{}\"", title.unwrap()).unwrap();
+
+    if let Some(mut dctl) = dctl {
+        dctl.comments = title.map(String::from).into_iter().chain(comments).collect();
+        dctl.write_dctl(&mut output).expect("Could not write to output file.");
+        return
     }
-    writeln!(output, "LUT_3D_SIZE {}", size).unwrap();
 
-    for b in 0..size {
-        for g in 0..size {
-            for r in 0..size {
+    if let Some(mut shader) = shader {
+        if let Some(title) = title {
+            shader.description = String::from(title);
+        }
+        shader.comments = comments;
+        shader.hook = match matches.value_of("hook").unwrap() {
+            "main" => MpvHook::Main,
+            "output" => MpvHook::Output,
+            _ => unreachable!("--hook select is irrational"),
+        };
+        shader.write_glsl(&mut output).expect("Could not write to output file.");
+        return
+    }
 
-                let pixel = mapper.map(RgbPixel {
-                    red: r as f64 / (size - 1) as f64,
-                    green: g as f64 / (size - 1) as f64,
-                    blue: b as f64 / (size - 1) as f64,
-                }).clamp(0.0, 1.0);
+    let mapper = RangeMapper::new(mapper.as_ref(), input_range, output_range, range_depth, false);
 
-                writeln!(output, "{} {} {}",
-                    pixel.red as f32,
-                    pixel.green as f32,
-                    pixel.blue as f32,
-                ).unwrap();
-            }
+    match shaper_size {
+        Some(shaper_size) => {
+            let mut lut = ShapedLut::from_mapper(&mapper, shaper_size, size);
+            comments.push(format!("shaper-size: {}", shaper_size));
+            lut.lut_3d.title = title.map(String::from);
+            lut.lut_3d.comments = comments;
+            lut.write_cube(&mut output).expect("Could not write to output file.");
+        }
+        None => {
+            let mut lut = Lut3d::from_mapper(&mapper, size);
+            lut.title = title.map(String::from);
+            lut.comments = comments;
+            match format {
+                "cube" => lut.write_cube(&mut output),
+                "3dl" => lut.write_3dl(&mut output, bit_depth),
+                "hald" => lut.write_hald(&mut output),
+                "spi3d" => lut.write_spi3d(&mut output),
+                _ => unreachable!("--format select is irrational"),
+            }.expect("Could not write to output file.");
         }
     }
 }
@@ -0,0 +1,104 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use std::io::{Result, Write};
+
+//
+// HDR10+ Metadata
+//
+// This is the JSON layout that x265 accepts through --dhdr10-info, with one SceneInfo entry for
+// every frame. Each carries the ST 2094-40 luminance parameters of a single window covering the
+// whole frame: the brightest value of each channel (maxscl), the average of the brightest
+// channel of each pixel (average_maxrgb), and percentiles of the brightest channel of each
+// pixel. All of them are written in units of 0.1 nits, as ST 2094-40 carries them. Curve data
+// for profile B is not generated, so the metadata is always profile A.
+//
+
+pub const DISTRIBUTION_INDEX: [f64; 9] = [1.0, 5.0, 10.0, 25.0, 50.0, 75.0, 90.0, 95.0, 99.0];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hdr10PlusFrame {
+    pub scene: usize,
+    // index of the frame within its scene
+    pub scene_frame: usize,
+    // brightest red, green, and blue in nits
+    pub max_scl: [f64; 3],
+    pub average_max_rgb: f64,
+    // brightest channel in nits at each of DISTRIBUTION_INDEX
+    pub distribution: Vec<f64>,
+}
+
+pub fn write_hdr10plus(output: &mut dyn Write, frames: &[Hdr10PlusFrame]) -> Result<()> {
+
+    let scene_starts = frames
+        .iter()
+        .enumerate()
+        .filter(|(_, frame)| frame.scene_frame == 0)
+        .map(|(index, _)| index)
+        .collect::<Vec<usize>>();
+    let scene_lengths = scene_starts
+        .iter()
+        .zip(scene_starts.iter().skip(1).chain([frames.len()].iter()))
+        .map(|(start, end)| end - start)
+        .collect::<Vec<usize>>();
+
+    writeln!(output, "{{")?;
+    writeln!(output, "  \"JSONInfo\": {{")?;
+    writeln!(output, "    \"HDR10plusProfile\": \"A\",")?;
+    writeln!(output, "    \"Version\": \"1.0\"")?;
+    writeln!(output, "  }},")?;
+    writeln!(output, "  \"SceneInfo\": [")?;
+    for (index, frame) in frames.iter().enumerate() {
+        writeln!(output, "    {{")?;
+        writeln!(output, "      \"LuminanceParameters\": {{")?;
+        writeln!(output, "        \"AverageRGB\": {},", units(frame.average_max_rgb))?;
+        writeln!(output, "        \"LuminanceDistributions\": {{")?;
+        writeln!(output, "          \"DistributionIndex\": [{}],", list(
+            DISTRIBUTION_INDEX.iter().map(|percentage| *percentage as u32)
+        ))?;
+        writeln!(output, "          \"DistributionValues\": [{}]", list(
+            frame.distribution.iter().map(|nits| units(*nits))
+        ))?;
+        writeln!(output, "        }},")?;
+        writeln!(output, "        \"MaxScl\": [{}]", list(
+            frame.max_scl.iter().map(|nits| units(*nits))
+        ))?;
+        writeln!(output, "      }},")?;
+        writeln!(output, "      \"NumberOfWindows\": 1,")?;
+        writeln!(output, "      \"TargetedSystemDisplayMaximumLuminance\": 0,")?;
+        writeln!(output, "      \"SceneFrameIndex\": {},", frame.scene_frame)?;
+        writeln!(output, "      \"SceneId\": {},", frame.scene)?;
+        writeln!(output, "      \"SequenceFrameIndex\": {}", index)?;
+        writeln!(output, "    }}{}", if index + 1 < frames.len() { "," } else { "" })?;
+    }
+    writeln!(output, "  ],")?;
+    writeln!(output, "  \"SceneInfoSummary\": {{")?;
+    writeln!(output, "    \"SceneFirstFrameIndex\": [{}],", list(scene_starts.into_iter()))?;
+    writeln!(output, "    \"SceneFrameNumbers\": [{}]", list(scene_lengths.into_iter()))?;
+    writeln!(output, "  }},")?;
+    writeln!(output, "  \"ToolInfo\": {{")?;
+    writeln!(output, "    \"Tool\": \"PQStat\",")?;
+    writeln!(output, "    \"Version\": \"{}\"", env!("CARGO_PKG_VERSION"))?;
+    writeln!(output, "  }}")?;
+    writeln!(output, "}}")
+}
+
+// ST 2094-40 luminance values are 17-bit integers counting tenths of a nit.
+fn units(nits: f64) -> u32 {
+    (nits * 10.0).round().clamp(0.0, 100_000.0) as u32
+}
+
+fn list<T, I>(values: I) -> String
+    where T: ToString, I: Iterator<Item = T> {
+    values.map(|value| value.to_string()).collect::<Vec<String>>().join(", ")
+}
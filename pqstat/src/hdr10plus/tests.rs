@@ -0,0 +1,86 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+
+fn frame(scene: usize, scene_frame: usize, peak: f64) -> Hdr10PlusFrame {
+    Hdr10PlusFrame {
+        scene,
+        scene_frame,
+        max_scl: [peak, peak / 2.0, 0.04],
+        average_max_rgb: 12.345,
+        distribution: vec![0.0, 0.1, 1.0, 2.5, 10.0, 20.0, 100.0, 203.0, peak],
+    }
+}
+
+fn written(frames: &[Hdr10PlusFrame]) -> String {
+
+    let mut output = Vec::new();
+
+    write_hdr10plus(&mut output, frames).unwrap();
+
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_units() {
+    assert_eq!(units(0.0), 0);
+    assert_eq!(units(0.04), 0);
+    assert_eq!(units(0.05), 1);
+    assert_eq!(units(203.0), 2_030);
+    assert_eq!(units(10_000.0), 100_000);
+    assert_eq!(units(-1.0), 0);
+}
+
+#[test]
+fn test_write_hdr10plus() {
+
+    let json = written(&[frame(0, 0, 1_000.0), frame(0, 1, 800.0), frame(1, 0, 100.0)]);
+
+    assert_eq!(json.matches("\"SequenceFrameIndex\"").count(), 3);
+    assert!(json.starts_with("{\n  \"JSONInfo\": {\n    \"HDR10plusProfile\": \"A\",\n"));
+    assert!(json.contains(&[
+        "    {",
+        "      \"LuminanceParameters\": {",
+        "        \"AverageRGB\": 123,",
+        "        \"LuminanceDistributions\": {",
+        "          \"DistributionIndex\": [1, 5, 10, 25, 50, 75, 90, 95, 99],",
+        "          \"DistributionValues\": [0, 1, 10, 25, 100, 200, 1000, 2030, 8000]",
+        "        },",
+        "        \"MaxScl\": [8000, 4000, 0]",
+        "      },",
+        "      \"NumberOfWindows\": 1,",
+        "      \"TargetedSystemDisplayMaximumLuminance\": 0,",
+        "      \"SceneFrameIndex\": 1,",
+        "      \"SceneId\": 0,",
+        "      \"SequenceFrameIndex\": 1",
+        "    },",
+    ].join("\n")));
+    assert!(json.contains("    \"SceneId\": 1,\n      \"SequenceFrameIndex\": 2\n    }\n  ],\n"));
+    assert!(json.contains(&[
+        "  \"SceneInfoSummary\": {",
+        "    \"SceneFirstFrameIndex\": [0, 2],",
+        "    \"SceneFrameNumbers\": [2, 1]",
+        "  },",
+    ].join("\n")));
+    assert!(json.contains("    \"Tool\": \"PQStat\",\n"));
+    assert!(json.ends_with("  }\n}\n"));
+}
+
+#[test]
+fn test_write_hdr10plus_empty() {
+
+    let json = written(&[]);
+
+    assert!(json.contains("\"SceneInfo\": [\n  ],\n"));
+    assert!(json.contains("\"SceneFirstFrameIndex\": [],\n    \"SceneFrameNumbers\": []\n"));
+}
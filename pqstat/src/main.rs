@@ -11,13 +11,31 @@
 #[cfg(test)]
 mod tests;
 
+mod hdr10plus;
+mod report;
+
 use std::{
     fs::File,
-    io::{stdin, BufReader, ErrorKind, Read, Result},
+    io::{stdin, stdout, BufReader, BufWriter, ErrorKind, Read, Result, Write},
+    sync::OnceLock,
+};
+use dsp::{
+    frame::{default_threads, map_bands, unit_to_code},
+    pixel::RgbPixel,
+    range::SignalRange,
+    stats::{PqHistogram, PERCENTILES},
+    ref_white_factor,
+    tf::pq_eotf,
+    y4m::{open_rgb_frames, OpenFramesError, RgbFrames},
+    yuv::{ChromaSiting, ChromaUpsampling, YuvDecoder, YuvFormat},
+};
+use hdr10plus::{write_hdr10plus, Hdr10PlusFrame, DISTRIBUTION_INDEX};
+use report::{FrameSummary, Recommendation, Report, Summary};
+use byteorder::{ByteOrder, LittleEndian};
+use clap::{
+    app_from_crate, crate_authors, crate_description, crate_name, crate_version, Arg, Error,
+    ErrorKind as ClapErrorKind,
 };
-use dsp::tf::pq_eotf;
-use byteorder::{LittleEndian, ReadBytesExt};
-use clap::{app_from_crate, crate_authors, crate_description, crate_name, crate_version, Arg};
 
 fn main() {
 
@@ -26,9 +44,9 @@ fn main() {
             .long("width")
             .short("w")
             .value_name("PIXELS")
-            .help("Width of the input video stream")
+            .help("Width of the input video stream; taken from the header of Y4M input")
             .takes_value(true)
-            .required(true)
+            .required(false)
             .validator(|value| {
                 if value.parse::<usize>().is_ok() {
                     Ok(())
@@ -41,9 +59,9 @@ fn main() {
             .long("height")
             .short("h")
             .value_name("PIXELS")
-            .help("Height of the input video stream")
+            .help("Height of the input video stream; taken from the header of Y4M input")
             .takes_value(true)
-            .required(true)
+            .required(false)
             .validator(|value| {
                 if value.parse::<usize>().is_ok() {
                     Ok(())
@@ -52,18 +70,177 @@ fn main() {
                 }
             })
         )
+        .arg(Arg::with_name("threads")
+            .long("threads")
+            .short("j")
+            .value_name("COUNT")
+            .help("Number of threads analyzing each frame; defaults to the number of logical \
+                CPUs")
+            .takes_value(true)
+            .required(false)
+            .validator(|value| {
+                match value.parse::<usize>() {
+                    Ok(threads) if threads > 0 => Ok(()),
+                    _ => Err("Must be a positive integer".to_string()),
+                }
+            })
+        )
+        .arg(Arg::with_name("format")
+            .long("format")
+            .short("f")
+            .help("Format of the printed statistics")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["text", "json", "csv"])
+            .default_value("text")
+        )
+        .arg(Arg::with_name("per-frame")
+            .long("per-frame")
+            .help("Prints the MaxCLL and MaxFALL of every frame before those of the stream")
+            .takes_value(false)
+        )
+        .arg(Arg::with_name("scenes")
+            .long("scenes")
+            .help("Prints the statistics of every detected scene before those of the stream")
+            .takes_value(false)
+        )
+        .arg(Arg::with_name("scene-threshold")
+            .long("scene-threshold")
+            .value_name("DIFFERENCE")
+            .help("Luminance histogram difference between consecutive frames that marks a scene \
+                cut, from 0.0 to 1.0")
+            .takes_value(true)
+            .required(false)
+            .default_value("0.4")
+            .validator(|value| {
+                match value.parse::<f64>() {
+                    Ok(threshold) if (0.0..=1.0).contains(&threshold) => Ok(()),
+                    _ => Err("Must be a number from 0.0 to 1.0".to_string()),
+                }
+            })
+        )
+        .arg(Arg::with_name("hdr10plus")
+            .long("hdr10plus")
+            .value_name("JSON-FILE")
+            .help("Also writes HDR10+ metadata for the --dhdr10-info option of x265 to this file")
+            .takes_value(true)
+            .required(false)
+        )
+        .arg(Arg::with_name("pixel-format")
+            .long("pixel-format")
+            .value_name("FORMAT")
+            .help("Pixel format of raw input frames; defaults to rgb48le")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["rgb48le", "yuv420p10le", "yuv422p10le", "yuv444p12le", "p010"])
+        )
+        .arg(Arg::with_name("range")
+            .long("range")
+            .value_name("RANGE")
+            .help("Quantization range of raw YUV input frames")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["limited", "full"])
+            .default_value("limited")
+        )
+        .arg(Arg::with_name("chroma-siting")
+            .long("chroma-siting")
+            .value_name("SITING")
+            .help("Location of chroma samples in raw 4:2:0 and 4:2:2 input frames")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["left", "center", "topleft"])
+            .default_value("left")
+        )
+        .arg(Arg::with_name("chroma-upsampling")
+            .long("chroma-upsampling")
+            .value_name("METHOD")
+            .help("Method used to bring chroma of raw YUV input frames up to full resolution")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["nearest", "bilinear"])
+            .default_value("bilinear")
+        )
         .arg(Arg::with_name("input")
             .index(1)
             .value_name("INPUT-FILE")
-            .help("Raw input video stream (PQ, BT.2020, RGB48LE); use - for STDIN")
+            .help("Raw input video stream (PQ, BT.2020, RGB48LE) or Y4M stream; use - for STDIN")
             .required(true)
         )
-        .after_help(format!("Copyright © 2024 William Swartzendruber\n\
+        .after_help(format!("This utility reads raw RGB48LE frames of the dimensions given by \
+            --width and --height and reports their MaxCLL and MaxFALL as defined by CTA-861.3. \
+            MaxCLL is the brightest channel of any pixel, while MaxFALL is the brightest frame \
+            average of the brightest channel of each pixel, both in nits.\n\n\
+            A single specular highlight is enough to set MaxCLL, so the brightness of the \
+            brightest channel and of the BT.2020 luminance of every pixel are also gathered \
+            into histograms of 10-bit PQ code values. Their percentiles offer a more robust \
+            peak to pass to the --max-cll option of PQ2HLG than MaxCLL itself.\n\n\
+            With --scenes, the stream is also split into scenes wherever the luminance \
+            histograms of two consecutive frames differ by more than --scene-threshold, \
+            measured as the share of pixels that would have to move to a different brightness \
+            to turn one into the other. Each scene is reported with its range of frames, \
+            MaxCLL, MaxFALL, average light level, and percentiles.\n\n\
+            With --format json or --format csv, the statistics of the stream, of every frame, \
+            and of every scene are all printed in a stable, versioned layout meant for other \
+            programs, regardless of --per-frame and --scenes. Every brightness is given in nits, \
+            and percentiles are given for 50, 90, 99, 99.9, and 99.99 percent.\n\n\
+            Following the 203 nit reference white of BT.2408, the level exceeded by only the \
+            brightest percent of the luminance of the stream is taken as its effective \
+            reference white, kept between 100 and 1,000 nits. It is printed along with the \
+            matching --ref-white or --exposure and a --max-cll taken from the 99.99 percent \
+            MaxRGB percentile, which can be passed to PQ2HLG as a starting point instead of \
+            matching screenshots by eye. Nothing is recommended when no frames were read.\n\n\
+            With --hdr10plus, HDR10+ dynamic metadata as defined by SMPTE ST 2094-40 is also \
+            written for every frame in the JSON layout that x265 reads through its \
+            --dhdr10-info option. Each frame carries the brightest red, green, and blue, the \
+            average of the brightest channel of each pixel, and the 1, 5, 10, 25, 50, 75, 90, \
+            95, and 99 percent percentiles of the brightest channel of each pixel. Frames are \
+            grouped by the same scene detection as --scenes.\n\n\
+            Y4M input is recognized automatically, in which case the dimensions are taken from \
+            its header and each frame is converted to RGB using BT.2020 coefficients, honoring \
+            any XCOLORRANGE extension and otherwise assuming limited range. Chroma is upsampled \
+            from the siting given by the C parameter of the header.\n\n\
+            With --pixel-format, raw frames may instead be planar or semi-planar YUV as decoded \
+            by FFmpeg, such as yuv420p10le or p010. Chroma is upsampled according to \
+            --chroma-siting and --chroma-upsampling, and every pixel is converted to RGB using \
+            BT.2020 coefficients before being measured. This avoids a separate, lossy RGB48LE \
+            conversion step.\n\n\
+            Copyright © 2024 William Swartzendruber\n\
             Licensed under the Mozilla Public License 2.0\n\
             <{}>", env!("CARGO_PKG_REPOSITORY")).as_str())
         .get_matches();
-    let width = matches.value_of("width").unwrap().parse::<usize>().unwrap();
-    let height = matches.value_of("height").unwrap().parse::<usize>().unwrap();
+    let geometry = matches
+        .value_of("width")
+        .zip(matches.value_of("height"))
+        .map(|(width, height)| (width.parse::<usize>().unwrap(), height.parse::<usize>().unwrap()));
+    let threads = matches
+        .value_of("threads")
+        .map(|value| value.parse::<usize>().unwrap())
+        .unwrap_or_else(default_threads);
+    let pixel_format = matches.value_of("pixel-format").and_then(|value| match value {
+        "rgb48le" => None,
+        "yuv420p10le" => Some(YuvFormat::Yuv420p10le),
+        "yuv422p10le" => Some(YuvFormat::Yuv422p10le),
+        "yuv444p12le" => Some(YuvFormat::Yuv444p12le),
+        "p010" => Some(YuvFormat::P010),
+        _ => unreachable!("--pixel-format select is irrational"),
+    });
+    let range = match matches.value_of("range").unwrap() {
+        "limited" => SignalRange::Limited,
+        "full" => SignalRange::Full,
+        _ => unreachable!("--range select is irrational"),
+    };
+    let siting = match matches.value_of("chroma-siting").unwrap() {
+        "left" => ChromaSiting::Left,
+        "center" => ChromaSiting::Center,
+        "topleft" => ChromaSiting::TopLeft,
+        _ => unreachable!("--chroma-siting select is irrational"),
+    };
+    let upsampling = match matches.value_of("chroma-upsampling").unwrap() {
+        "nearest" => ChromaUpsampling::Nearest,
+        "bilinear" => ChromaUpsampling::Bilinear,
+        _ => unreachable!("--chroma-upsampling select is irrational"),
+    };
     let input_value = matches.value_of("input").unwrap();
     let (mut stdin_read, mut file_read);
     let mut input = BufReader::<&mut dyn Read>::new(
@@ -76,13 +253,72 @@ fn main() {
             &mut file_read
         }
     );
-    let mut max_channel = 0_u16;
 
-    'frames: loop {
+    let mut frames_read = open_rgb_frames(&mut input, geometry)
+        .unwrap_or_else(|err| open_frames_error(err));
+
+    if matches.is_present("pixel-format") && matches!(frames_read, RgbFrames::Y4m(_)) {
+        Error::with_description(
+            "--pixel-format cannot be used with Y4M input",
+            ClapErrorKind::ArgumentConflict,
+        ).exit()
+    }
+
+    let (width, height) = frames_read.size();
+    let decoder = pixel_format.map(|format| YuvDecoder {
+        range,
+        siting,
+        upsampling,
+        ..YuvDecoder::new(format, width, height)
+    });
+    let per_frame = matches.is_present("per-frame");
+    let scene_threshold = matches.value_of("scene-threshold").unwrap().parse::<f64>().unwrap();
+    let mut previous: Option<FrameStats> = None;
+    let mut scenes: Vec<SceneStats> = Vec::new();
+    let mut overall = SceneStats::new(0);
+    let mut frames = Vec::new();
+    let mut hdr10plus_frames = matches.value_of("hdr10plus").map(|_| Vec::new());
+
+    'frames: for index in 0.. {
+
+        let result = match (&mut frames_read, decoder.as_ref()) {
+            (RgbFrames::Raw { input, .. }, Some(decoder)) => {
+                read_yuv_frame_stats(input.as_mut(), decoder, threads)
+            }
+            (RgbFrames::Raw { input, .. }, None) => {
+                read_frame_stats(input.as_mut(), width, height, threads)
+            }
+            (RgbFrames::Y4m(reader), _) => {
+                reader.read_rgb_frame().map(|frame| pixel_frame_stats(&frame, width, threads))
+            }
+        };
 
-        match read_frame_max_channel(&mut input, width * height) {
-            Ok(frame_max_channel) => {
-                max_channel = max_channel.max(frame_max_channel);
+        match result {
+            Ok(stats) => {
+                frames.push(FrameSummary {
+                    max_cll: to_nits(stats.max_channel),
+                    max_fall: ceil_nits(stats.max_fall()),
+                    max_nits: code_nits(stats.max_channel),
+                    average_nits: stats.max_fall(),
+                });
+                overall.add(index, &stats);
+                if is_scene_cut(previous.as_ref(), &stats, scene_threshold) {
+                    scenes.push(SceneStats::new(index));
+                }
+                scenes.last_mut().unwrap().add(index, &stats);
+                if let Some(hdr10plus_frames) = hdr10plus_frames.as_mut() {
+                    hdr10plus_frames.push(Hdr10PlusFrame {
+                        scene: scenes.len() - 1,
+                        scene_frame: index - scenes.last().unwrap().first_frame,
+                        max_scl: stats.max_scl.map(code_nits),
+                        average_max_rgb: stats.max_fall(),
+                        distribution: DISTRIBUTION_INDEX
+                            .iter()
+                            .map(|percentile| stats.max_rgb.percentile_nits(*percentile))
+                            .collect(),
+                    });
+                }
+                previous = Some(stats);
             }
             Err(err) => {
                 match err.kind() {
@@ -93,20 +329,307 @@ fn main() {
         }
     }
 
-    println!("MaxCLL: {}", to_nits(max_channel));
+    if let Some(hdr10plus_frames) = hdr10plus_frames {
+
+        let file = File::create(matches.value_of("hdr10plus").unwrap())
+            .expect("Could not open HDR10+ file for writing.");
+        let mut hdr10plus_output = BufWriter::new(file);
+
+        write_hdr10plus(&mut hdr10plus_output, &hdr10plus_frames)
+            .and_then(|_| hdr10plus_output.flush())
+            .expect("Could not write to HDR10+ file.");
+    }
+
+    let report = Report {
+        overall: overall.summary(),
+        recommendation: recommend(&overall),
+        frames,
+        scenes: scenes.iter().map(SceneStats::summary).collect(),
+    };
+    let mut stdout_write = stdout();
+    let mut output = BufWriter::<&mut dyn Write>::new(&mut stdout_write);
+
+    match matches.value_of("format").unwrap() {
+        "text" => report.write_text(&mut output, per_frame, matches.is_present("scenes")),
+        "json" => report.write_json(&mut output),
+        "csv" => report.write_csv(&mut output),
+        _ => unreachable!("--format select is irrational"),
+    }.expect("Could not write to output stream.");
+    output.flush().expect("Could not write to output stream.");
+}
+
+//
+// Frame Statistics
+//
+// Each band of a frame is measured on its own and the bands are then merged. Most statistics
+// only consider the brightest channel of each pixel, as given by its PQ code value, while the
+// luminance histogram counts the BT.2020 luminance of each pixel instead.
+//
+
+#[derive(Debug, Clone, Default, PartialEq)]
+struct FrameStats {
+    max_channel: u16,
+    // brightest red, green, and blue
+    max_scl: [u16; 3],
+    // sum of the brightest channel of every pixel in nits
+    light_sum: f64,
+    pixels: usize,
+    max_rgb: PqHistogram,
+    luminance: PqHistogram,
+}
+
+impl FrameStats {
+
+    fn add(&mut self, red: u16, green: u16, blue: u16) {
+
+        let max_channel = red.max(green).max(blue);
+        let nits = RgbPixel::new_rgb(code_nits(red), code_nits(green), code_nits(blue));
+
+        self.max_channel = self.max_channel.max(max_channel);
+        self.max_scl = [
+            self.max_scl[0].max(red),
+            self.max_scl[1].max(green),
+            self.max_scl[2].max(blue),
+        ];
+        self.light_sum += code_nits(max_channel);
+        self.pixels += 1;
+        self.max_rgb.add_signal(max_channel as f64 / 65_535.0);
+        self.luminance.add_linear(nits.y_bt2020() / 10_000.0);
+    }
+
+    fn merge(mut self, other: Self) -> Self {
+        self.max_channel = self.max_channel.max(other.max_channel);
+        for (max, other_max) in self.max_scl.iter_mut().zip(other.max_scl) {
+            *max = (*max).max(other_max);
+        }
+        self.light_sum += other.light_sum;
+        self.pixels += other.pixels;
+        self.max_rgb.merge(&other.max_rgb);
+        self.luminance.merge(&other.luminance);
+        self
+    }
+
+    // frame-average light level in nits
+    fn max_fall(&self) -> f64 {
+        if self.pixels == 0 {
+            0.0
+        } else {
+            self.light_sum / self.pixels as f64
+        }
+    }
+}
+
+//
+// Scene Statistics
+//
+// A scene begins with the first frame and with every frame whose luminance histogram differs
+// too much from that of the frame before it.
+//
+
+#[derive(Debug, Clone, PartialEq)]
+struct SceneStats {
+    first_frame: usize,
+    last_frame: usize,
+    max_channel: u16,
+    max_fall: f64,
+    // sum of the frame-average light levels of every frame in nits
+    fall_sum: f64,
+    frames: usize,
+    max_rgb: PqHistogram,
+    luminance: PqHistogram,
+}
+
+impl SceneStats {
+
+    fn new(first_frame: usize) -> Self {
+        Self {
+            first_frame,
+            last_frame: first_frame,
+            max_channel: 0,
+            max_fall: 0.0,
+            fall_sum: 0.0,
+            frames: 0,
+            max_rgb: PqHistogram::new(),
+            luminance: PqHistogram::new(),
+        }
+    }
+
+    fn add(&mut self, index: usize, stats: &FrameStats) {
+        self.last_frame = index;
+        self.max_channel = self.max_channel.max(stats.max_channel);
+        self.max_fall = self.max_fall.max(stats.max_fall());
+        self.fall_sum += stats.max_fall();
+        self.frames += 1;
+        self.max_rgb.merge(&stats.max_rgb);
+        self.luminance.merge(&stats.luminance);
+    }
+
+    // average light level across every frame of the scene in nits
+    fn average_light(&self) -> f64 {
+        if self.frames == 0 {
+            0.0
+        } else {
+            self.fall_sum / self.frames as f64
+        }
+    }
+
+    fn summary(&self) -> Summary {
+
+        let percentiles = |histogram: &PqHistogram| {
+            PERCENTILES
+                .iter()
+                .map(|percentile| histogram.percentile_nits(*percentile))
+                .collect()
+        };
+
+        Summary {
+            first_frame: self.first_frame,
+            last_frame: self.last_frame,
+            max_cll: to_nits(self.max_channel),
+            max_fall: ceil_nits(self.max_fall),
+            max_nits: code_nits(self.max_channel),
+            average_nits: self.average_light(),
+            max_rgb: percentiles(&self.max_rgb),
+            luminance: percentiles(&self.luminance),
+        }
+    }
 }
 
-fn read_frame_max_channel(input: &mut dyn Read, count: usize) -> Result<u16> {
+//
+// Recommendation
+//
+// BT.2408 places diffuse white at 203 nits and expects only speculars and emissive highlights
+// above it. Those rarely cover more than a percent of the picture, while skin, walls, and
+// clouds fill far more of it at or below reference white, so the 99th percentile of luminance
+// lands on diffuse white for a stream graded that way. Lower percentiles fall into midtones
+// and would overexpose the conversion, and higher ones land on the highlights themselves. A
+// stream that sits on the 203 nits of BT.2408 is therefore left at an exposure of one. The
+// result is kept between the 100 nits of SDR and the 1,000 nits where nothing would be left
+// for highlights. The recommended MaxCLL is the same robust peak offered by the MaxRGB
+// percentiles rather than a single specular highlight. An empty stream has no reference white
+// at all, so nothing is recommended for it.
+//
+
+const REF_WHITE_PERCENTILE: f64 = 99.0;
+const MAX_CLL_PERCENTILE: f64 = 99.99;
 
-    let mut max_channel = 0_u16;
+fn recommend(overall: &SceneStats) -> Option<Recommendation> {
 
-    for _ in 0..(3 * count) {
-        max_channel = max_channel.max(input.read_u16::<LittleEndian>()?);
+    if overall.frames == 0 {
+        return None;
     }
 
-    Ok(max_channel)
+    let ref_white = overall
+        .luminance
+        .percentile_nits(REF_WHITE_PERCENTILE)
+        .clamp(100.0, 1_000.0)
+        .round();
+    let max_cll = overall
+        .max_rgb
+        .percentile_nits(MAX_CLL_PERCENTILE)
+        .ceil()
+        .clamp(ref_white, 10_000.0);
+
+    Some(Recommendation {
+        ref_white: ref_white as u16,
+        exposure: ref_white_factor(ref_white),
+        max_cll: max_cll as u16,
+    })
+}
+
+fn is_scene_cut(previous: Option<&FrameStats>, current: &FrameStats, threshold: f64) -> bool {
+    match previous {
+        Some(previous) => previous.luminance.difference(&current.luminance) > threshold,
+        None => true,
+    }
+}
+
+fn read_frame_stats(
+    input: &mut dyn Read,
+    width: usize,
+    height: usize,
+    threads: usize,
+) -> Result<FrameStats> {
+
+    let mut frame = vec![0_u8; 6 * width * height];
+
+    input.read_exact(&mut frame)?;
+
+    let band_stats = map_bands(&frame, 6 * width, threads, |band| {
+        let mut stats = FrameStats::default();
+        for pixel in band.chunks_exact(6) {
+            stats.add(
+                LittleEndian::read_u16(&pixel[0..2]),
+                LittleEndian::read_u16(&pixel[2..4]),
+                LittleEndian::read_u16(&pixel[4..6]),
+            );
+        }
+        stats
+    });
+
+    Ok(band_stats.into_iter().fold(FrameStats::default(), FrameStats::merge))
+}
+
+fn read_yuv_frame_stats(
+    input: &mut dyn Read,
+    decoder: &YuvDecoder,
+    threads: usize,
+) -> Result<FrameStats> {
+    Ok(pixel_frame_stats(&decoder.read_frame(input)?, decoder.width, threads))
+}
+
+fn pixel_frame_stats(frame: &[RgbPixel], width: usize, threads: usize) -> FrameStats {
+
+    let band_stats = map_bands(frame, width, threads, |band| {
+        let mut stats = FrameStats::default();
+        for pixel in band {
+            stats.add(
+                unit_to_code(pixel.red),
+                unit_to_code(pixel.green),
+                unit_to_code(pixel.blue),
+            );
+        }
+        stats
+    });
+
+    band_stats.into_iter().fold(FrameStats::default(), FrameStats::merge)
 }
 
 fn to_nits(max_channel: u16) -> u16 {
     (pq_eotf(max_channel as f64 / 65_535.0) * 10_000.0).ceil() as u16
 }
+
+fn ceil_nits(nits: f64) -> u16 {
+    nits.ceil() as u16
+}
+
+// Evaluating the PQ EOTF for every pixel would dominate the run time, so each code value is
+// only ever converted once.
+fn code_nits(code: u16) -> f64 {
+
+    static NITS: OnceLock<Vec<f64>> = OnceLock::new();
+
+    NITS.get_or_init(|| {
+        (0..=u16::MAX).map(|code| pq_eotf(code as f64 / 65_535.0) * 10_000.0).collect()
+    })[code as usize]
+}
+
+fn open_frames_error(err: OpenFramesError) -> ! {
+    match err {
+        OpenFramesError::MissingGeometry => {
+            Error::with_description(
+                "--width and --height are required unless the input is Y4M",
+                ClapErrorKind::MissingRequiredArgument,
+            ).exit()
+        }
+        OpenFramesError::ConflictingGeometry => {
+            Error::with_description(
+                "--width and --height cannot be used with Y4M input",
+                ClapErrorKind::ArgumentConflict,
+            ).exit()
+        }
+        err => {
+            Error::with_description(&err.to_string(), ClapErrorKind::InvalidValue).exit()
+        }
+    }
+}
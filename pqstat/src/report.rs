@@ -0,0 +1,235 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use dsp::stats::PERCENTILES;
+use std::io::{Result, Write};
+
+//
+// Report
+//
+// Every brightness is in nits. MaxCLL and MaxFALL are rounded up to whole nits as CTA-861.3
+// carries them, while everything else keeps three decimal places in the machine readable
+// formats. The JSON and CSV layouts are versioned by REPORT_VERSION and fields are only ever
+// added to them. The recommended PQ2HLG options describe the whole stream and so only appear in
+// the text and JSON formats, and only when at least one frame was read.
+//
+
+pub const REPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameSummary {
+    pub max_cll: u16,
+    pub max_fall: u16,
+    pub max_nits: f64,
+    pub average_nits: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub first_frame: usize,
+    pub last_frame: usize,
+    pub max_cll: u16,
+    pub max_fall: u16,
+    pub max_nits: f64,
+    // average light level across every frame
+    pub average_nits: f64,
+    // brightest channel and BT.2020 luminance at each of PERCENTILES
+    pub max_rgb: Vec<f64>,
+    pub luminance: Vec<f64>,
+}
+
+// options for PQ2HLG, where the exposure is the factor equivalent to the reference white level
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recommendation {
+    pub ref_white: u16,
+    pub exposure: f64,
+    pub max_cll: u16,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub overall: Summary,
+    pub recommendation: Option<Recommendation>,
+    pub frames: Vec<FrameSummary>,
+    pub scenes: Vec<Summary>,
+}
+
+impl Report {
+
+    pub fn write_text(&self, output: &mut dyn Write, frames: bool, scenes: bool) -> Result<()> {
+
+        if frames {
+            for (index, frame) in self.frames.iter().enumerate() {
+                writeln!(output, "Frame {}: MaxCLL {}, MaxFALL {}",
+                    index, frame.max_cll, frame.max_fall)?;
+            }
+        }
+
+        if scenes {
+            for (index, scene) in self.scenes.iter().enumerate() {
+                writeln!(output, "Scene {}: frames {}-{}, MaxCLL {}, MaxFALL {}, average {}, \
+                    MaxRGB percentiles: {}",
+                    index,
+                    scene.first_frame,
+                    scene.last_frame,
+                    scene.max_cll,
+                    scene.max_fall,
+                    scene.average_nits.ceil(),
+                    text_percentiles(&scene.max_rgb),
+                )?;
+            }
+        }
+
+        writeln!(output, "MaxCLL: {}", self.overall.max_cll)?;
+        writeln!(output, "MaxFALL: {}", self.overall.max_fall)?;
+        writeln!(output, "MaxRGB percentiles: {}", text_percentiles(&self.overall.max_rgb))?;
+        writeln!(output, "Luminance percentiles: {}", text_percentiles(&self.overall.luminance))?;
+
+        if let Some(recommendation) = self.recommendation.as_ref() {
+            writeln!(output, "Reference white: {}", recommendation.ref_white)?;
+            writeln!(output, "Suggested PQ2HLG options: --ref-white {} --max-cll {} \
+                (or --exposure {:.3} --max-cll {})",
+                recommendation.ref_white,
+                recommendation.max_cll,
+                recommendation.exposure,
+                recommendation.max_cll,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_json(&self, output: &mut dyn Write) -> Result<()> {
+
+        writeln!(output, "{{")?;
+        writeln!(output, "  \"version\": {},", REPORT_VERSION)?;
+        writeln!(output, "  \"frame_count\": {},", self.frames.len())?;
+        write!(output, "  \"overall\": ")?;
+        write_json_summary(output, &self.overall, "  ")?;
+        writeln!(output, ",")?;
+        match self.recommendation.as_ref() {
+            Some(recommendation) => writeln!(output, "  \"recommendation\": {{\"ref_white\": {}, \
+                \"exposure\": {:.3}, \"max_cll\": {}}},",
+                recommendation.ref_white,
+                recommendation.exposure,
+                recommendation.max_cll,
+            )?,
+            None => writeln!(output, "  \"recommendation\": null,")?,
+        }
+        writeln!(output, "  \"frames\": [")?;
+        for (index, frame) in self.frames.iter().enumerate() {
+            writeln!(output,
+                "    {{\"frame\": {}, \"max_nits\": {:.3}, \"average_nits\": {:.3}}}{}",
+                index,
+                frame.max_nits,
+                frame.average_nits,
+                if index + 1 < self.frames.len() { "," } else { "" },
+            )?;
+        }
+        writeln!(output, "  ],")?;
+        writeln!(output, "  \"scenes\": [")?;
+        for (index, scene) in self.scenes.iter().enumerate() {
+            write!(output, "    ")?;
+            write_json_summary(output, scene, "    ")?;
+            writeln!(output, "{}", if index + 1 < self.scenes.len() { "," } else { "" })?;
+        }
+        writeln!(output, "  ]")?;
+        writeln!(output, "}}")
+    }
+
+    //
+    // Each row is a single frame, scene, or the overall stream as named by its first column.
+    // Percentiles are left empty for frames.
+    //
+
+    pub fn write_csv(&self, output: &mut dyn Write) -> Result<()> {
+
+        write!(output,
+            "record,index,first_frame,last_frame,max_cll,max_fall,max_nits,average_nits")?;
+        for name in ["max_rgb", "luminance"] {
+            for percentile in PERCENTILES {
+                write!(output, ",{}_p{}", name, percentile)?;
+            }
+        }
+        writeln!(output)?;
+
+        for (index, frame) in self.frames.iter().enumerate() {
+            writeln!(output, "frame,{},{},{},{},{},{:.3},{:.3}{}",
+                index,
+                index,
+                index,
+                frame.max_cll,
+                frame.max_fall,
+                frame.max_nits,
+                frame.average_nits,
+                ",".repeat(2 * PERCENTILES.len()),
+            )?;
+        }
+        for (index, scene) in self.scenes.iter().enumerate() {
+            write!(output, "scene,{},", index)?;
+            write_csv_summary(output, scene)?;
+        }
+        write!(output, "overall,,")?;
+        write_csv_summary(output, &self.overall)
+    }
+}
+
+fn text_percentiles(values: &[f64]) -> String {
+    PERCENTILES
+        .iter()
+        .zip(values.iter())
+        .map(|(percentile, value)| format!("{}% {}", percentile, value.ceil()))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn write_json_summary(output: &mut dyn Write, summary: &Summary, indent: &str) -> Result<()> {
+
+    let percentiles = |values: &[f64]| {
+        PERCENTILES
+            .iter()
+            .zip(values.iter())
+            .map(|(percentile, value)| format!("\"{}\": {:.3}", percentile, value))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    writeln!(output, "{{")?;
+    writeln!(output, "{}  \"first_frame\": {},", indent, summary.first_frame)?;
+    writeln!(output, "{}  \"last_frame\": {},", indent, summary.last_frame)?;
+    writeln!(output, "{}  \"max_cll\": {},", indent, summary.max_cll)?;
+    writeln!(output, "{}  \"max_fall\": {},", indent, summary.max_fall)?;
+    writeln!(output, "{}  \"max_nits\": {:.3},", indent, summary.max_nits)?;
+    writeln!(output, "{}  \"average_nits\": {:.3},", indent, summary.average_nits)?;
+    writeln!(output, "{}  \"max_rgb_percentiles\": {{{}}},",
+        indent, percentiles(&summary.max_rgb))?;
+    writeln!(output, "{}  \"luminance_percentiles\": {{{}}}",
+        indent, percentiles(&summary.luminance))?;
+    write!(output, "{}}}", indent)
+}
+
+fn write_csv_summary(output: &mut dyn Write, summary: &Summary) -> Result<()> {
+
+    write!(output, "{},{},{},{},{:.3},{:.3}",
+        summary.first_frame,
+        summary.last_frame,
+        summary.max_cll,
+        summary.max_fall,
+        summary.max_nits,
+        summary.average_nits,
+    )?;
+    for value in summary.max_rgb.iter().chain(summary.luminance.iter()) {
+        write!(output, ",{:.3}", value)?;
+    }
+    writeln!(output)
+}
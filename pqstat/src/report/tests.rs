@@ -0,0 +1,154 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+
+fn summary(first_frame: usize, last_frame: usize, max_nits: f64) -> Summary {
+    Summary {
+        first_frame,
+        last_frame,
+        max_cll: max_nits.ceil() as u16,
+        max_fall: 50,
+        max_nits,
+        average_nits: 40.25,
+        max_rgb: vec![10.0, 20.0, 30.0, 40.0, max_nits],
+        luminance: vec![5.0, 10.0, 15.0, 20.0, 25.5],
+    }
+}
+
+fn report() -> Report {
+    Report {
+        overall: summary(0, 2, 1_000.0),
+        recommendation: Some(Recommendation { ref_white: 250, exposure: 0.9134, max_cll: 1_000 }),
+        frames: vec![
+            FrameSummary { max_cll: 204, max_fall: 31, max_nits: 203.0001, average_nits: 30.5 },
+            FrameSummary { max_cll: 1_000, max_fall: 50, max_nits: 1_000.0, average_nits: 49.9 },
+            FrameSummary { max_cll: 100, max_fall: 41, max_nits: 100.0, average_nits: 40.35 },
+        ],
+        scenes: vec![summary(0, 1, 1_000.0), summary(2, 2, 100.0)],
+    }
+}
+
+fn written<F>(f: F) -> String
+    where F: Fn(&mut dyn Write) -> Result<()> {
+
+    let mut output = Vec::new();
+
+    f(&mut output).unwrap();
+
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_write_text() {
+
+    let report = report();
+    let text = written(|output| report.write_text(output, false, false));
+
+    assert_eq!(text, "\
+        MaxCLL: 1000\n\
+        MaxFALL: 50\n\
+        MaxRGB percentiles: 50% 10, 90% 20, 99% 30, 99.9% 40, 99.99% 1000\n\
+        Luminance percentiles: 50% 5, 90% 10, 99% 15, 99.9% 20, 99.99% 26\n\
+        Reference white: 250\n\
+        Suggested PQ2HLG options: --ref-white 250 --max-cll 1000 (or --exposure 0.913 \
+        --max-cll 1000)\n");
+}
+
+#[test]
+fn test_write_text_empty() {
+
+    let report = Report {
+        overall: summary(0, 0, 0.0),
+        recommendation: None,
+        frames: vec![],
+        scenes: vec![],
+    };
+    let text = written(|output| report.write_text(output, false, false));
+
+    assert_eq!(text.lines().count(), 4);
+    assert!(!text.contains("Reference white") && !text.contains("PQ2HLG"));
+}
+
+#[test]
+fn test_write_text_frames_scenes() {
+
+    let report = report();
+    let text = written(|output| report.write_text(output, true, true));
+    let lines = text.lines().collect::<Vec<&str>>();
+
+    assert_eq!(lines.len(), 3 + 2 + 6);
+    assert_eq!(lines[0], "Frame 0: MaxCLL 204, MaxFALL 31");
+    assert_eq!(lines[4], "Scene 1: frames 2-2, MaxCLL 100, MaxFALL 50, average 41, MaxRGB \
+        percentiles: 50% 10, 90% 20, 99% 30, 99.9% 40, 99.99% 100");
+    assert_eq!(lines[5], "MaxCLL: 1000");
+}
+
+#[test]
+fn test_write_json() {
+
+    let report = report();
+    let json = written(|output| report.write_json(output));
+
+    assert!(json.starts_with("{\n  \"version\": 1,\n  \"frame_count\": 3,\n  \"overall\": {\n"));
+    assert!(json.contains("  },\n  \"recommendation\": {\"ref_white\": 250, \
+        \"exposure\": 0.913, \"max_cll\": 1000},\n  \"frames\": [\n"));
+    assert!(json.contains(
+        "    {\"frame\": 0, \"max_nits\": 203.000, \"average_nits\": 30.500},\n"
+    ));
+    assert!(json.contains(
+        "    {\"frame\": 2, \"max_nits\": 100.000, \"average_nits\": 40.350}\n  ],\n"
+    ));
+    assert!(json.contains("\"max_rgb_percentiles\": {\"50\": 10.000, \"90\": 20.000, \
+        \"99\": 30.000, \"99.9\": 40.000, \"99.99\": 1000.000},\n"));
+    assert!(json.contains("      \"first_frame\": 2,\n"));
+    assert!(json.ends_with("    }\n  ]\n}\n"));
+    assert_eq!(json.matches('{').count(), json.matches('}').count());
+    assert_eq!(json.matches('[').count(), json.matches(']').count());
+    assert!(!json.contains(",\n  ]") && !json.contains(",\n}"));
+}
+
+#[test]
+fn test_write_json_empty() {
+
+    let report = Report {
+        overall: summary(0, 0, 0.0),
+        recommendation: None,
+        frames: vec![],
+        scenes: vec![],
+    };
+    let json = written(|output| report.write_json(output));
+
+    assert!(json.contains("  \"recommendation\": null,\n"));
+    assert!(json.contains("  \"frames\": [\n  ],\n  \"scenes\": [\n  ]\n}\n"));
+}
+
+#[test]
+fn test_write_csv() {
+
+    let report = report();
+    let csv = written(|output| report.write_csv(output));
+    let lines = csv.lines().collect::<Vec<&str>>();
+
+    assert_eq!(lines.len(), 1 + 3 + 2 + 1);
+    assert_eq!(lines[0], "record,index,first_frame,last_frame,max_cll,max_fall,max_nits,\
+        average_nits,max_rgb_p50,max_rgb_p90,max_rgb_p99,max_rgb_p99.9,max_rgb_p99.99,\
+        luminance_p50,luminance_p90,luminance_p99,luminance_p99.9,luminance_p99.99");
+    assert_eq!(lines[1], "frame,0,0,0,204,31,203.000,30.500,,,,,,,,,,");
+    assert_eq!(lines[5], "scene,1,2,2,100,50,100.000,40.250,10.000,20.000,30.000,40.000,\
+        100.000,5.000,10.000,15.000,20.000,25.500");
+    assert!(lines[6].starts_with("overall,,0,2,1000,50,1000.000,40.250,"));
+
+    for line in lines {
+        assert_eq!(line.matches(',').count(), 17);
+    }
+}
@@ -39,7 +39,7 @@ fn test_frame_stats_black() {
     frame.write_u16::<LittleEndian>(0x0000).unwrap();
 
     let mut cursor = Cursor::new(frame);
-    let max_channel = read_frame_max_channel(&mut cursor, 6).unwrap();
+    let max_channel = read_frame_stats(&mut cursor, 3, 2, 2).unwrap().max_channel;
 
     assert_eq!(to_nits(max_channel), 0);
 }
@@ -63,7 +63,7 @@ fn test_frame_stats_red_rw() {
     frame.write_u16::<LittleEndian>(0x0000).unwrap();
 
     let mut cursor = Cursor::new(frame);
-    let max_channel = read_frame_max_channel(&mut cursor, 4).unwrap();
+    let max_channel = read_frame_stats(&mut cursor, 2, 2, 2).unwrap().max_channel;
 
     assert_eq!(to_nits(max_channel), 203);
 }
@@ -87,7 +87,7 @@ fn test_frame_stats_green_rw() {
     frame.write_u16::<LittleEndian>(0x0000).unwrap();
 
     let mut cursor = Cursor::new(frame);
-    let max_channel = read_frame_max_channel(&mut cursor, 4).unwrap();
+    let max_channel = read_frame_stats(&mut cursor, 2, 2, 2).unwrap().max_channel;
 
     assert_eq!(to_nits(max_channel), 203);
 }
@@ -111,7 +111,7 @@ fn test_frame_stats_blue_rw() {
     frame.write_u16::<LittleEndian>(0x94A7).unwrap();
 
     let mut cursor = Cursor::new(frame);
-    let max_channel = read_frame_max_channel(&mut cursor, 4).unwrap();
+    let max_channel = read_frame_stats(&mut cursor, 2, 2, 2).unwrap().max_channel;
 
     assert_eq!(to_nits(max_channel), 203);
 }
@@ -135,7 +135,7 @@ fn test_frame_stats_white_rw() {
     frame.write_u16::<LittleEndian>(0x94A7).unwrap();
 
     let mut cursor = Cursor::new(frame);
-    let max_channel = read_frame_max_channel(&mut cursor, 4).unwrap();
+    let max_channel = read_frame_stats(&mut cursor, 2, 2, 2).unwrap().max_channel;
 
     assert_eq!(to_nits(max_channel), 203);
 }
@@ -165,7 +165,7 @@ fn test_frame_stats_red_mw() {
     frame.write_u16::<LittleEndian>(0x0000).unwrap();
 
     let mut cursor = Cursor::new(frame);
-    let max_channel = read_frame_max_channel(&mut cursor, 6).unwrap();
+    let max_channel = read_frame_stats(&mut cursor, 3, 2, 2).unwrap().max_channel;
 
     assert_eq!(to_nits(max_channel), 10_000);
 }
@@ -195,7 +195,7 @@ fn test_frame_stats_green_mw() {
     frame.write_u16::<LittleEndian>(0x0000).unwrap();
 
     let mut cursor = Cursor::new(frame);
-    let max_channel = read_frame_max_channel(&mut cursor, 6).unwrap();
+    let max_channel = read_frame_stats(&mut cursor, 3, 2, 2).unwrap().max_channel;
 
     assert_eq!(to_nits(max_channel), 10_000);
 }
@@ -225,7 +225,7 @@ fn test_frame_stats_blue_mw() {
     frame.write_u16::<LittleEndian>(0xFFFF).unwrap();
 
     let mut cursor = Cursor::new(frame);
-    let max_channel = read_frame_max_channel(&mut cursor, 6).unwrap();
+    let max_channel = read_frame_stats(&mut cursor, 3, 2, 2).unwrap().max_channel;
 
     assert_eq!(to_nits(max_channel), 10_000);
 }
@@ -255,7 +255,269 @@ fn test_frame_stats_white_mw() {
     frame.write_u16::<LittleEndian>(0xFFFF).unwrap();
 
     let mut cursor = Cursor::new(frame);
-    let max_channel = read_frame_max_channel(&mut cursor, 6).unwrap();
+    let max_channel = read_frame_stats(&mut cursor, 3, 2, 2).unwrap().max_channel;
 
     assert_eq!(to_nits(max_channel), 10_000);
 }
+
+#[test]
+fn test_frame_stats_threads() {
+
+    let mut frame = vec![0_u8; 0];
+
+    let codes = (0..(3 * 16 * 9)).map(|i: u32| (i * 7_919 % 60_000) as u16).collect::<Vec<u16>>();
+
+    for code in codes.iter() {
+        frame.write_u16::<LittleEndian>(*code).unwrap();
+    }
+    frame.write_u16::<LittleEndian>(0x0000).unwrap();
+
+    for threads in 1..12 {
+
+        let mut cursor = Cursor::new(frame.as_slice());
+        let max_channel = read_frame_stats(&mut cursor, 16, 9, threads).unwrap().max_channel;
+
+        assert_eq!(max_channel, *codes.iter().max().unwrap());
+        assert_eq!(
+            read_frame_stats(&mut cursor, 16, 9, threads).unwrap_err().kind(),
+            ErrorKind::UnexpectedEof,
+        );
+    }
+}
+
+#[test]
+fn test_frame_stats_max_fall_black() {
+
+    let mut cursor = Cursor::new(vec![0_u8; 6 * 3 * 2]);
+    let stats = read_frame_stats(&mut cursor, 3, 2, 2).unwrap();
+
+    assert_eq!(stats.pixels, 6);
+    assert_eq!(ceil_nits(stats.max_fall()), 0);
+}
+
+#[test]
+fn test_frame_stats_max_fall_rw() {
+
+    //
+    // Two of four pixels are at reference white (203 nits) and the other two are black, so the
+    // frame-average light level is half of reference white. Only the brightest channel of a
+    // pixel counts toward it.
+    //
+
+    let mut frame = vec![0_u8; 0];
+
+    for pixel in [[0x94A7, 0x0000, 0x0000], [0x0000; 3], [0x94A7, 0x94A7, 0x4000], [0x0000; 3]] {
+        for channel in pixel {
+            frame.write_u16::<LittleEndian>(channel).unwrap();
+        }
+    }
+
+    let mut cursor = Cursor::new(frame);
+    let stats = read_frame_stats(&mut cursor, 2, 2, 2).unwrap();
+
+    assert_eq!(to_nits(stats.max_channel), 203);
+    assert!((stats.max_fall() - code_nits(0x94A7) / 2.0).abs() < 0.0000001);
+    assert_eq!(ceil_nits(stats.max_fall()), 102);
+}
+
+#[test]
+fn test_frame_stats_max_fall_mw() {
+
+    let mut frame = vec![0_u8; 0];
+
+    for _ in 0..(3 * 2 * 3) {
+        frame.write_u16::<LittleEndian>(0xFFFF).unwrap();
+    }
+
+    let mut cursor = Cursor::new(frame);
+    let stats = read_frame_stats(&mut cursor, 3, 2, 2).unwrap();
+
+    assert_eq!(to_nits(stats.max_channel), 10_000);
+    assert_eq!(ceil_nits(stats.max_fall()), 10_000);
+}
+
+#[test]
+fn test_frame_stats_max_fall_threads() {
+
+    let mut frame = vec![0_u8; 0];
+
+    for i in 0..(8 * 5 * 3) {
+        frame.write_u16::<LittleEndian>((i as u32 * 409 % 65_536) as u16).unwrap();
+    }
+
+    let single = read_frame_stats(&mut Cursor::new(frame.clone()), 8, 5, 1).unwrap();
+    let banded = read_frame_stats(&mut Cursor::new(frame), 8, 5, 3).unwrap();
+
+    assert_eq!(single.max_channel, banded.max_channel);
+    assert_eq!(single.pixels, banded.pixels);
+    assert!((single.max_fall() - banded.max_fall()).abs() < 0.0000001);
+}
+
+#[test]
+fn test_yuv_frame_stats() {
+
+    //
+    // A 4x2 yuv420p10le frame of limited range black with a single gray pixel. Since chroma is
+    // neutral, every channel of that pixel equals its luma of 502 steps above black.
+    //
+
+    let mut frame = vec![0_u8; 0];
+
+    for luma in [64, 64, 64, 566, 64, 64, 64, 64] {
+        frame.write_u16::<LittleEndian>(luma).unwrap();
+    }
+    for chroma in [512, 512, 512, 512] {
+        frame.write_u16::<LittleEndian>(chroma).unwrap();
+    }
+
+    let decoder = YuvDecoder::new(YuvFormat::Yuv420p10le, 4, 2);
+    let mut cursor = Cursor::new(frame);
+    let stats = read_yuv_frame_stats(&mut cursor, &decoder, 2).unwrap();
+
+    assert_eq!(stats.max_channel, unit_to_code(502.0 / 876.0));
+    assert!((stats.max_fall() - code_nits(stats.max_channel) / 8.0).abs() < 0.0000001);
+    assert!(read_yuv_frame_stats(&mut cursor, &decoder, 2).is_err());
+}
+
+#[test]
+fn test_frame_stats_histograms() {
+
+    //
+    // Pure blue at reference white has a brightest channel of 203 nits but a luminance of only
+    // 5.93% of that.
+    //
+
+    let mut frame = vec![0_u8; 0];
+
+    for pixel in [[0x0000, 0x0000, 0x94A7], [0x0000; 3], [0x94A7; 3], [0x0000, 0x0000, 0x94A7]] {
+        for channel in pixel {
+            frame.write_u16::<LittleEndian>(channel).unwrap();
+        }
+    }
+
+    let mut cursor = Cursor::new(frame);
+    let stats = read_frame_stats(&mut cursor, 2, 2, 2).unwrap();
+    let blue_luminance = code_nits(0x94A7) * 0.0593;
+
+    assert_eq!(stats.max_rgb.total(), 4);
+    assert_eq!(stats.luminance.total(), 4);
+    assert_eq!(ceil_nits(stats.max_rgb.percentile_nits(25.0)), 0);
+    assert!((stats.max_rgb.percentile_nits(50.0) - 203.0).abs() < 2.0);
+    assert!((stats.max_rgb.percentile_nits(100.0) - 203.0).abs() < 2.0);
+    assert!((stats.luminance.percentile_nits(75.0) / blue_luminance - 1.0).abs() < 0.02);
+    assert!((stats.luminance.percentile_nits(100.0) - 203.0).abs() < 2.0);
+}
+
+fn solid_frame_stats(width: usize, height: usize, code: u16) -> FrameStats {
+
+    let mut frame = vec![0_u8; 0];
+
+    for _ in 0..(3 * width * height) {
+        frame.write_u16::<LittleEndian>(code).unwrap();
+    }
+
+    read_frame_stats(&mut Cursor::new(frame), width, height, 1).unwrap()
+}
+
+#[test]
+fn test_scene_cuts() {
+
+    let dark = solid_frame_stats(4, 2, 0x4000);
+    let dark_fade = solid_frame_stats(4, 2, 0x4100);
+    let bright = solid_frame_stats(4, 2, 0xA000);
+
+    assert!(is_scene_cut(None, &dark, 0.4));
+    assert!(!is_scene_cut(Some(&dark), &dark, 0.4));
+    assert!(!is_scene_cut(Some(&dark), &dark_fade, 0.4));
+    assert!(is_scene_cut(Some(&dark), &bright, 0.4));
+    assert!(!is_scene_cut(Some(&dark), &bright, 1.0));
+}
+
+#[test]
+fn test_scene_stats() {
+
+    let frames = [
+        solid_frame_stats(4, 2, 0x94A7),
+        solid_frame_stats(4, 2, 0x4000),
+        solid_frame_stats(4, 2, 0x94A7),
+    ];
+    let mut scene = SceneStats::new(10);
+
+    for (index, stats) in frames.iter().enumerate() {
+        scene.add(10 + index, stats);
+    }
+
+    let expected_average = (2.0 * code_nits(0x94A7) + code_nits(0x4000)) / 3.0;
+
+    assert_eq!((scene.first_frame, scene.last_frame, scene.frames), (10, 12, 3));
+    assert_eq!(to_nits(scene.max_channel), 203);
+    assert!((scene.max_fall - code_nits(0x94A7)).abs() < 0.0000001);
+    assert!((scene.average_light() - expected_average).abs() < 0.0000001);
+    assert_eq!(scene.max_rgb.total(), 24);
+    assert!((scene.max_rgb.percentile_nits(50.0) - 203.0).abs() < 2.0);
+}
+
+#[test]
+fn test_frame_stats_max_scl() {
+
+    let mut frame = vec![0_u8; 0];
+
+    for pixel in [[0x4000, 0x1000, 0x0000], [0x2000, 0x8000, 0x0100], [0x0000, 0x0000, 0xA000]] {
+        for code in pixel {
+            frame.write_u16::<LittleEndian>(code).unwrap();
+        }
+    }
+
+    for threads in 1..4 {
+
+        let mut cursor = Cursor::new(frame.as_slice());
+        let stats = read_frame_stats(&mut cursor, 3, 1, threads).unwrap();
+
+        assert_eq!(stats.max_scl, [0x4000, 0x8000, 0xA000]);
+        assert_eq!(stats.max_channel, 0xA000);
+    }
+}
+
+#[test]
+fn test_recommend() {
+
+    let mut overall = SceneStats::new(0);
+
+    // 203 nits is roughly 58% PQ, 1,000 nits is 75% PQ, and 5,000 nits is 92.5% PQ
+    let mut stats = solid_frame_stats(100, 100, (0.581 * 65_535.0) as u16);
+    let highlights = solid_frame_stats(10, 10, (0.925 * 65_535.0) as u16);
+
+    stats = stats.merge(highlights);
+    overall.add(0, &stats);
+
+    let recommendation = recommend(&overall).unwrap();
+
+    assert!((200..=206).contains(&recommendation.ref_white));
+    assert!((recommendation.exposure - 1.0).abs() < 0.01);
+    assert!((recommendation.max_cll as f64 - 5_000.0).abs() < 100.0);
+}
+
+#[test]
+fn test_recommend_clamped() {
+
+    let mut dark = SceneStats::new(0);
+    let mut bright = SceneStats::new(0);
+
+    dark.add(0, &solid_frame_stats(4, 4, (0.3 * 65_535.0) as u16));
+    bright.add(0, &solid_frame_stats(4, 4, (0.9 * 65_535.0) as u16));
+
+    let dark_recommendation = recommend(&dark).unwrap();
+    let bright_recommendation = recommend(&bright).unwrap();
+
+    assert_eq!(dark_recommendation.ref_white, 100);
+    assert_eq!(dark_recommendation.max_cll, 100);
+    assert!(dark_recommendation.exposure > 1.0);
+    assert_eq!(bright_recommendation.ref_white, 1_000);
+    assert!(bright_recommendation.max_cll > 1_000);
+    assert!(bright_recommendation.exposure < 1.0);
+}
+
+#[test]
+fn test_recommend_empty() {
+    assert_eq!(recommend(&SceneStats::new(0)), None);
+}
@@ -0,0 +1,165 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+use dsp::{frame::code_to_unit, pixel::RgbPixel};
+use std::io::{BufRead, Error, ErrorKind, Read, Result};
+
+//
+// Frames
+//
+// Frames are either raw RGB48LE of known dimensions or binary PPM. Every PPM frame carries its
+// own header, so a stream of them, as written by FFmpeg's image2pipe muxer, may simply be
+// concatenated. PPM samples are 8-bit or big endian 16-bit depending upon the maximum value of
+// the header, and are scaled from 0.0 to 1.0 by that maximum value.
+//
+// Running out of input before a frame begins is the clean end of a stream and is reported as
+// UnexpectedEof. Running out partway through a frame is reported as InvalidData instead so that
+// a damaged file is not mistaken for a shorter one. Frame sizes are untrusted, so frames larger
+// than MAX_PIXELS are rejected and frame data is only allocated as it actually arrives.
+//
+
+const PPM_MAGIC: &[u8] = b"P6";
+const MAX_PIXELS: usize = 1 << 28;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<RgbPixel>,
+}
+
+pub fn is_ppm(input: &mut dyn BufRead) -> Result<bool> {
+    Ok(input.fill_buf()?.starts_with(PPM_MAGIC))
+}
+
+pub fn read_rgb48le(input: &mut dyn Read, width: usize, height: usize) -> Result<Frame> {
+
+    let bytes = read_frame_bytes(input, frame_size(width, height, 2)?)?;
+    let pixels = bytes
+        .chunks_exact(6)
+        .map(|pixel| RgbPixel::new_rgb(
+            code_to_unit(u16::from_le_bytes([pixel[0], pixel[1]])),
+            code_to_unit(u16::from_le_bytes([pixel[2], pixel[3]])),
+            code_to_unit(u16::from_le_bytes([pixel[4], pixel[5]])),
+        ))
+        .collect();
+
+    Ok(Frame { width, height, pixels })
+}
+
+pub fn read_ppm(input: &mut dyn BufRead) -> Result<Frame> {
+
+    if input.fill_buf()?.is_empty() {
+        return Err(Error::new(ErrorKind::UnexpectedEof, "no more PPM frames"))
+    }
+
+    read_ppm_frame(input).map_err(|err| match err.kind() {
+        ErrorKind::UnexpectedEof => invalid("PPM frame is truncated"),
+        _ => err,
+    })
+}
+
+fn read_ppm_frame(input: &mut dyn BufRead) -> Result<Frame> {
+
+    let mut magic = [0_u8; 2];
+
+    input.read_exact(&mut magic)?;
+
+    if magic != PPM_MAGIC {
+        return Err(invalid("PPM frame does not begin with P6"))
+    }
+
+    let width = read_header_value(input)?;
+    let height = read_header_value(input)?;
+    let max_value = read_header_value(input)?;
+
+    if width == 0 || height == 0 {
+        return Err(invalid("PPM frame has no pixels"))
+    }
+    if !(1..=65_535).contains(&max_value) {
+        return Err(invalid("PPM maximum value must be from 1 to 65535"))
+    }
+
+    let sample_size = if max_value < 256 { 1 } else { 2 };
+    let bytes = read_frame_bytes(input, frame_size(width, height, sample_size)?)?;
+
+    let samples = bytes
+        .chunks_exact(sample_size)
+        .map(|sample| match sample {
+            [value] => *value as f64,
+            [high, low] => u16::from_be_bytes([*high, *low]) as f64,
+            _ => unreachable!("PPM sample size is irrational"),
+        } / max_value as f64)
+        .map(|sample| sample.min(1.0))
+        .collect::<Vec<f64>>();
+    let pixels = samples
+        .chunks_exact(3)
+        .map(|pixel| RgbPixel::new_rgb(pixel[0], pixel[1], pixel[2]))
+        .collect();
+
+    Ok(Frame { width, height, pixels })
+}
+
+// Number of bytes in a frame of three samples per pixel
+fn frame_size(width: usize, height: usize, sample_size: usize) -> Result<usize> {
+    width
+        .checked_mul(height)
+        .filter(|pixels| *pixels <= MAX_PIXELS)
+        .map(|pixels| 3 * sample_size * pixels)
+        .ok_or_else(|| invalid("frame is too large"))
+}
+
+fn read_frame_bytes(input: &mut dyn Read, size: usize) -> Result<Vec<u8>> {
+
+    let mut bytes = Vec::new();
+
+    match input.take(size as u64).read_to_end(&mut bytes)? {
+        0 if size > 0 => Err(Error::new(ErrorKind::UnexpectedEof, "no more frames")),
+        length if length < size => Err(invalid("frame is truncated")),
+        _ => Ok(bytes),
+    }
+}
+
+// Reads a decimal header value and the single whitespace character that follows it, skipping
+// any whitespace and comments before it.
+fn read_header_value(input: &mut dyn BufRead) -> Result<usize> {
+
+    let mut digits = String::new();
+    let mut in_comment = false;
+
+    loop {
+
+        let mut byte = [0_u8; 1];
+
+        input.read_exact(&mut byte)?;
+
+        match byte[0] {
+            b'\n' if in_comment => in_comment = false,
+            _ if in_comment => {}
+            b'#' if digits.is_empty() => in_comment = true,
+            byte if byte.is_ascii_whitespace() => {
+                if !digits.is_empty() {
+                    break
+                }
+            }
+            byte if byte.is_ascii_digit() => digits.push(byte as char),
+            _ => return Err(invalid("PPM header contains an invalid character")),
+        }
+    }
+
+    digits.parse::<usize>().map_err(|_| invalid("PPM header value is too large"))
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
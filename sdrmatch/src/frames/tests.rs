@@ -0,0 +1,96 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use std::io::Cursor;
+use super::*;
+
+#[test]
+fn test_is_ppm() {
+    assert!(is_ppm(&mut &b"P6\n1 1\n255\n\0\0\0"[..]).unwrap());
+    assert!(!is_ppm(&mut &b"YUV4MPEG2 "[..]).unwrap());
+    assert!(!is_ppm(&mut &b""[..]).unwrap());
+}
+
+#[test]
+fn test_read_rgb48le() {
+
+    let bytes = [0x00, 0x00, 0xFF, 0xFF, 0x00, 0x80, 0xFF, 0xFF, 0x00, 0x00, 0x00, 0x00];
+    let mut input = Cursor::new(bytes);
+    let frame = read_rgb48le(&mut input, 2, 1).unwrap();
+
+    assert_eq!((frame.width, frame.height), (2, 1));
+    assert_eq!(frame.pixels[0].red, 0.0);
+    assert_eq!(frame.pixels[0].green, 1.0);
+    assert_eq!(frame.pixels[0].blue, code_to_unit(0x8000));
+    assert_eq!(frame.pixels[1], RgbPixel::new_rgb(1.0, 0.0, 0.0));
+    assert_eq!(read_rgb48le(&mut input, 2, 1).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_read_ppm_8_bit() {
+
+    let mut input = Cursor::new(b"P6\n# comment\n2 1\n255\n\xFF\x00\x33\x00\x80\xFF".to_vec());
+    let frame = read_ppm(&mut input).unwrap();
+
+    assert_eq!((frame.width, frame.height), (2, 1));
+    assert_eq!(frame.pixels[0], RgbPixel::new_rgb(1.0, 0.0, 0.2));
+    assert_eq!(frame.pixels[1], RgbPixel::new_rgb(0.0, 128.0 / 255.0, 1.0));
+    assert_eq!(read_ppm(&mut input).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_read_ppm_16_bit_concatenated() {
+
+    let mut bytes = b"P6 1 1 65535\n\xFF\xFF\x80\x00\x00\x01".to_vec();
+
+    bytes.extend_from_slice(b"P6\n1\n1\n1023\n\x03\xFF\x02\x00\x00\x00");
+
+    let mut input = Cursor::new(bytes);
+    let first = read_ppm(&mut input).unwrap();
+    let second = read_ppm(&mut input).unwrap();
+
+    assert_eq!(first.pixels, vec![RgbPixel::new_rgb(1.0, 32_768.0 / 65_535.0, 1.0 / 65_535.0)]);
+    assert_eq!(second.pixels, vec![RgbPixel::new_rgb(1.0, 512.0 / 1_023.0, 0.0)]);
+    assert_eq!(read_ppm(&mut input).unwrap_err().kind(), ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn test_read_ppm_invalid() {
+
+    let kind = |bytes: &[u8]| read_ppm(&mut Cursor::new(bytes.to_vec())).unwrap_err().kind();
+
+    assert_eq!(kind(b"P5\n1 1\n255\n\0"), ErrorKind::InvalidData);
+    assert_eq!(kind(b"P6\n1 x\n255\n\0\0\0"), ErrorKind::InvalidData);
+    assert_eq!(kind(b"P6\n0 1\n255\n"), ErrorKind::InvalidData);
+    assert_eq!(kind(b"P6\n1 1\n70000\n\0\0\0"), ErrorKind::InvalidData);
+    assert_eq!(kind(b"P6\n2 1\n255\n\0\0\0"), ErrorKind::InvalidData);
+    assert_eq!(kind(b"P6\n2 1\n255\n"), ErrorKind::InvalidData);
+    assert_eq!(kind(b"P6\n2 1"), ErrorKind::InvalidData);
+    assert_eq!(kind(b"P6\n65536 65536\n255\n\0\0\0"), ErrorKind::InvalidData);
+    assert_eq!(
+        kind(format!("P6\n{} {}\n255\n", usize::MAX, usize::MAX).as_bytes()),
+        ErrorKind::InvalidData,
+    );
+}
+
+#[test]
+fn test_read_rgb48le_truncated() {
+
+    let mut input = Cursor::new(vec![0_u8; 9]);
+
+    assert!(read_rgb48le(&mut input, 1, 1).is_ok());
+    assert_eq!(read_rgb48le(&mut input, 1, 1).unwrap_err().kind(), ErrorKind::InvalidData);
+    assert_eq!(
+        read_rgb48le(&mut Cursor::new(Vec::new()), usize::MAX, 2).unwrap_err().kind(),
+        ErrorKind::InvalidData,
+    );
+}
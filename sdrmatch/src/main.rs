@@ -0,0 +1,400 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+#[cfg(test)]
+mod tests;
+
+mod frames;
+
+use std::{
+    fs::File,
+    io::{stdin, BufRead, BufReader, ErrorKind, Read, Result},
+};
+use dsp::{
+    PqSdrMapper,
+    SdrMonoMapper,
+    frame::{default_threads, map_bands},
+    pixel::RgbPixel,
+    tf::sdr_e_to_o,
+    tm::ToneMapMethod,
+};
+use frames::{is_ppm, read_ppm, read_rgb48le, Frame};
+use clap::{
+    app_from_crate, crate_authors, crate_description, crate_name, crate_version, Arg, Error,
+    ErrorKind as ClapErrorKind,
+};
+
+fn main() {
+
+    let matches = app_from_crate!()
+        .arg(Arg::with_name("width")
+            .long("width")
+            .short("w")
+            .value_name("PIXELS")
+            .help("Width of raw input frames; taken from the header of PPM input")
+            .takes_value(true)
+            .required(false)
+            .validator(|value| {
+                if value.parse::<usize>().is_ok() {
+                    Ok(())
+                } else {
+                    Err("Must be an unsigned integer".to_string())
+                }
+            })
+        )
+        .arg(Arg::with_name("height")
+            .long("height")
+            .short("h")
+            .value_name("PIXELS")
+            .help("Height of raw input frames; taken from the header of PPM input")
+            .takes_value(true)
+            .required(false)
+            .validator(|value| {
+                if value.parse::<usize>().is_ok() {
+                    Ok(())
+                } else {
+                    Err("Must be an unsigned integer".to_string())
+                }
+            })
+        )
+        .arg(Arg::with_name("threads")
+            .long("threads")
+            .short("j")
+            .value_name("COUNT")
+            .help("Number of threads comparing each candidate exposure; defaults to the number \
+                of logical CPUs")
+            .takes_value(true)
+            .required(false)
+            .validator(|value| {
+                match value.parse::<usize>() {
+                    Ok(threads) if threads > 0 => Ok(()),
+                    _ => Err("Must be a positive integer".to_string()),
+                }
+            })
+        )
+        .arg(Arg::with_name("max-cll")
+            .long("max-cll")
+            .short("m")
+            .value_name("NITS")
+            .help("MaxCLL value of the PQ input.")
+            .takes_value(true)
+            .required(false)
+            .default_value("1000")
+            .validator(|value| {
+                let max_cll = value.parse::<f64>();
+                if max_cll.is_err() {
+                    return Err("Must be a floating point value".to_string())
+                }
+                let max_cll_value = max_cll.unwrap();
+                if !max_cll_value.is_normal() {
+                    return Err("Must be a normal number".to_string())
+                }
+                if !max_cll_value.is_sign_positive() {
+                    return Err("Must be a positive number".to_string())
+                }
+                if max_cll_value > 10_000.0 {
+                    return Err("Must not exceed 10,000.0.".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("tone-map-method")
+            .long("tone-map-method")
+            .help("Tone mapping method to use.")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["rgb", "maxrgb"])
+            .default_value("maxrgb")
+        )
+        .arg(Arg::with_name("pq-input")
+            .index(1)
+            .value_name("PQ-FILE")
+            .help("PQ frames (BT.2020, RGB48LE) or PPM frames; use - for STDIN")
+            .required(true)
+        )
+        .arg(Arg::with_name("sdr-input")
+            .index(2)
+            .value_name("SDR-FILE")
+            .help("Matching SDR frames (BT.709, RGB48LE) or PPM frames; use - for STDIN")
+            .required(true)
+        )
+        .after_help(format!("This utility reads frames of a PQ source along with the same \
+            frames of its SDR BT.709 release and searches for the --exposure value of PQ2HLG \
+            that makes them match most closely. The PQ frames are run through the black and \
+            white preview of PQ2HLG --preview, the SDR frames are run through the black and \
+            white transform of MONO709, and the two are compared by their Oklab lightness. The \
+            exposure with the lowest root mean square difference in lightness across the \
+            pixels is printed along with that difference, which ranges from 0.0 to 1.0. This \
+            replaces comparing screenshots from HLGPREV.SH and SDRPREV.SH by eye.\n\n\
+            Frames are paired in order until either input runs out, and paired frames must \
+            have the same dimensions, so the SDR frames may need to be scaled to match. Pixels \
+            that are clipped in the SDR frames say nothing about exposure and are skipped. \
+            To bound memory, long inputs are compared by about a million pixels spread evenly \
+            across every frame. \
+            The --max-cll and --tone-map-method options should be given the same values that \
+            will be passed to PQ2HLG.\n\n\
+            Each input may be raw RGB48LE of the dimensions given by --width and --height, or \
+            binary PPM as written by FFmpeg, which is recognized automatically. A pair of \
+            frames can be extracted at the same timestamp of each video with:\n\n\
+            ffmpeg -ss TIME -i PQ-VIDEO -frames:v 1 -pix_fmt rgb48be pq.ppm\n\
+            ffmpeg -ss TIME -i SDR-VIDEO -frames:v 1 -pix_fmt rgb48be sdr.ppm\n\n\
+            Copyright © 2024 William Swartzendruber\n\
+            Licensed under the Mozilla Public License 2.0\n\
+            <{}>", env!("CARGO_PKG_REPOSITORY")).as_str())
+        .get_matches();
+    let geometry = matches
+        .value_of("width")
+        .zip(matches.value_of("height"))
+        .map(|(width, height)| (width.parse::<usize>().unwrap(), height.parse::<usize>().unwrap()));
+    let threads = matches
+        .value_of("threads")
+        .map(|value| value.parse::<usize>().unwrap())
+        .unwrap_or_else(default_threads);
+    let max_cll = matches.value_of("max-cll").unwrap().parse::<f64>().unwrap();
+    let tm_method = match matches.value_of("tone-map-method").unwrap() {
+        "rgb" => ToneMapMethod::Rgb,
+        "maxrgb" => ToneMapMethod::MaxRgb,
+        _ => unreachable!("--tone-map-method select is irrational"),
+    };
+    let pq_value = matches.value_of("pq-input").unwrap();
+    let sdr_value = matches.value_of("sdr-input").unwrap();
+
+    if pq_value == "-" && sdr_value == "-" {
+        Error::with_description(
+            "PQ-FILE and SDR-FILE cannot both be read from STDIN",
+            ClapErrorKind::ArgumentConflict,
+        ).exit()
+    }
+
+    let mut pq_input = open_input(pq_value);
+    let mut sdr_input = open_input(sdr_value);
+    let pq_ppm = is_ppm(&mut pq_input).expect("Could not read from PQ input stream.");
+    let sdr_ppm = is_ppm(&mut sdr_input).expect("Could not read from SDR input stream.");
+
+    if (!pq_ppm || !sdr_ppm) && geometry.is_none() {
+        Error::with_description(
+            "--width and --height are required unless both inputs are PPM",
+            ClapErrorKind::MissingRequiredArgument,
+        ).exit()
+    }
+
+    let mut sampler = Sampler::new(MAX_SAMPLES);
+
+    'frames: loop {
+
+        let pair = read_frame(&mut pq_input, pq_ppm, geometry)
+            .and_then(|pq| Ok((pq, read_frame(&mut sdr_input, sdr_ppm, geometry)?)));
+
+        match pair {
+            Ok((pq, sdr)) => {
+                if (pq.width, pq.height) != (sdr.width, sdr.height) {
+                    Error::with_description(
+                        &format!(
+                            "PQ frame is {}x{} but SDR frame is {}x{}",
+                            pq.width, pq.height, sdr.width, sdr.height,
+                        ),
+                        ClapErrorKind::InvalidValue,
+                    ).exit()
+                }
+                sampler.add_frames(&pq, &sdr);
+            }
+            Err(err) => {
+                match err.kind() {
+                    ErrorKind::UnexpectedEof => break 'frames,
+                    _ => panic!("Could not read frame from input stream: {:?}", err),
+                }
+            }
+        }
+    }
+
+    let samples = sampler.samples;
+
+    if samples.is_empty() {
+        Error::with_description(
+            "No unclipped pixels could be paired between the inputs",
+            ClapErrorKind::EmptyValue,
+        ).exit()
+    }
+
+    let (exposure, error) = search_exposure(&samples, max_cll, tm_method, threads);
+
+    println!("Exposure: {:.3}", exposure);
+    println!("RMS Oklab lightness difference: {:.5}", error);
+    println!("Compared pixels: {}", samples.len());
+}
+
+//
+// Samples
+//
+// Each sampled pixel is kept as its PQ signal along with the Oklab lightness of the matching SDR
+// pixel once it has been made black and white. Every unclipped pixel is sampled until there are
+// MAX_SAMPLES of them, at which point every other sample is dropped and only every other pixel is
+// sampled from then on, and so on. This keeps memory bounded however long the inputs are while
+// leaving the samples evenly spread across them.
+//
+
+const MAX_SAMPLES: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Sample {
+    pq: RgbPixel,
+    lightness: f64,
+}
+
+struct Sampler {
+    samples: Vec<Sample>,
+    limit: usize,
+    // only every stride-th unclipped pixel is sampled
+    stride: usize,
+    unclipped: usize,
+}
+
+impl Sampler {
+
+    fn new(limit: usize) -> Self {
+        Self { samples: Vec::new(), limit, stride: 1, unclipped: 0 }
+    }
+
+    fn add_frames(&mut self, pq: &Frame, sdr: &Frame) {
+
+        let pairs = pq.pixels
+            .iter()
+            .zip(sdr.pixels.iter())
+            .filter(|(_, sdr)| sdr.red.max(sdr.green).max(sdr.blue) < 1.0);
+
+        for (pq, sdr) in pairs {
+
+            if self.unclipped.is_multiple_of(self.stride) {
+
+                if self.samples.len() >= self.limit {
+
+                    let mut index = 0;
+
+                    self.samples.retain(|_| {
+                        index += 1;
+                        index % 2 == 1
+                    });
+                    self.stride *= 2;
+                }
+
+                if self.unclipped.is_multiple_of(self.stride) {
+                    self.samples.push(
+                        Sample { pq: *pq, lightness: lightness(SdrMonoMapper.map(*sdr)) }
+                    );
+                }
+            }
+
+            self.unclipped += 1;
+        }
+    }
+}
+
+// Oklab lightness of a black and white SDR signal
+fn lightness(signal: RgbPixel) -> f64 {
+    signal.with_each_channel(sdr_e_to_o).bt709_to_xyz().to_oklab().l
+}
+
+fn rms_error(
+    samples: &[Sample],
+    exposure: f64,
+    max_cll: f64,
+    tm_method: ToneMapMethod,
+    threads: usize,
+) -> f64 {
+
+    let mapper = PqSdrMapper::new_by_factor(exposure, max_cll, tm_method);
+    let band_sums = map_bands(samples, 1, threads, |band| {
+        band.iter()
+            .map(|sample| (lightness(mapper.map(sample.pq)) - sample.lightness).powi(2))
+            .sum::<f64>()
+    });
+
+    (band_sums.iter().sum::<f64>() / samples.len() as f64).sqrt()
+}
+
+//
+// Exposure Search
+//
+// Exposures are first compared at even steps of their logarithm to find the neighborhood of the
+// best one, which is then narrowed down with a golden section search. Comparing every exposure
+// first keeps the search from settling on a local minimum caused by tone mapping or clipping.
+//
+
+const MIN_EXPOSURE: f64 = 0.25;
+const MAX_EXPOSURE: f64 = 4.0;
+const GRID_STEPS: usize = 32;
+const REFINEMENTS: usize = 24;
+
+fn search_exposure(
+    samples: &[Sample],
+    max_cll: f64,
+    tm_method: ToneMapMethod,
+    threads: usize,
+) -> (f64, f64) {
+
+    let error = |log_exposure: f64| {
+        rms_error(samples, log_exposure.exp(), max_cll, tm_method, threads)
+    };
+    let (min, max) = (MIN_EXPOSURE.ln(), MAX_EXPOSURE.ln());
+    let step = (max - min) / GRID_STEPS as f64;
+    let best_step = (0..=GRID_STEPS)
+        .map(|index| (index, error(min + step * index as f64)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .unwrap()
+        .0;
+    let ratio = (5.0_f64.sqrt() - 1.0) / 2.0;
+    let mut low = min + step * best_step.saturating_sub(1) as f64;
+    let mut high = min + step * (best_step + 1).min(GRID_STEPS) as f64;
+    let mut a = high - ratio * (high - low);
+    let mut b = low + ratio * (high - low);
+    let (mut error_a, mut error_b) = (error(a), error(b));
+
+    for _ in 0..REFINEMENTS {
+        if error_a <= error_b {
+            high = b;
+            b = a;
+            error_b = error_a;
+            a = high - ratio * (high - low);
+            error_a = error(a);
+        } else {
+            low = a;
+            a = b;
+            error_a = error_b;
+            b = low + ratio * (high - low);
+            error_b = error(b);
+        }
+    }
+
+    let log_exposure = (low + high) / 2.0;
+
+    (log_exposure.exp(), error(log_exposure))
+}
+
+fn open_input(value: &str) -> BufReader<Box<dyn Read>> {
+    BufReader::new(
+        if value == "-" {
+            Box::new(stdin())
+        } else {
+            Box::new(File::open(value).expect("Could not open input file for reading."))
+        }
+    )
+}
+
+fn read_frame(
+    input: &mut dyn BufRead,
+    ppm: bool,
+    geometry: Option<(usize, usize)>,
+) -> Result<Frame> {
+    match (ppm, geometry) {
+        (true, _) => read_ppm(input),
+        (false, Some((width, height))) => read_rgb48le(input, width, height),
+        (false, None) => unreachable!("--width and --height are somehow missing"),
+    }
+}
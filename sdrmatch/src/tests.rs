@@ -0,0 +1,129 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * To the extent possible under law, the person who associated CC0 with this file has waived all
+ * copyright and related or neighboring rights to this file.
+ *
+ * You should have received a copy of the CC0 legalcode along with this work. If not, see
+ * <http://creativecommons.org/publicdomain/zero/1.0/>.
+ *
+ * SPDX-License-Identifier: CC0-1.0
+ */
+
+use super::*;
+use dsp::tf::pq_ieotf;
+
+fn frame(pixels: Vec<RgbPixel>) -> Frame {
+    Frame { width: pixels.len(), height: 1, pixels }
+}
+
+// PQ pixels from deep shadows to bright highlights in a variety of hues
+fn pq_frame() -> Frame {
+    frame(
+        (0..96)
+            .map(|index| {
+                let nits = 0.5 * 1.07_f64.powi(index);
+                let hue = index % 3;
+                RgbPixel::new_rgb(
+                    pq_ieotf(nits / 10_000.0),
+                    pq_ieotf(nits * if hue == 0 { 0.5 } else { 1.0 } / 10_000.0),
+                    pq_ieotf(nits * if hue == 1 { 0.25 } else { 1.0 } / 10_000.0),
+                )
+            })
+            .collect()
+    )
+}
+
+fn frame_samples(pq: &Frame, sdr: &Frame) -> Vec<Sample> {
+
+    let mut sampler = Sampler::new(MAX_SAMPLES);
+
+    sampler.add_frames(pq, sdr);
+    sampler.samples
+}
+
+fn sdr_frame(pq: &Frame, exposure: f64) -> Frame {
+
+    let mapper = PqSdrMapper::new_by_factor(exposure, 1_000.0, ToneMapMethod::MaxRgb);
+
+    frame(pq.pixels.iter().map(|pixel| mapper.map(*pixel)).collect())
+}
+
+#[test]
+fn test_lightness() {
+    assert!(lightness(RgbPixel::new_y(0.0)).abs() < 0.000001);
+    assert!((lightness(RgbPixel::new_y(1.0)) - 1.0).abs() < 0.0001);
+    assert!(lightness(RgbPixel::new_y(0.25)) < lightness(RgbPixel::new_y(0.5)));
+}
+
+#[test]
+fn test_frame_samples_skip_clipped() {
+
+    let pq = frame(vec![RgbPixel::new_y(0.5); 4]);
+    let sdr = frame(vec![
+        RgbPixel::new_y(0.5),
+        RgbPixel::new_rgb(1.0, 0.2, 0.2),
+        RgbPixel::new_y(1.0),
+        RgbPixel::new_y(0.0),
+    ]);
+    let samples = frame_samples(&pq, &sdr);
+
+    assert_eq!(samples.len(), 2);
+    assert_eq!(samples[0].lightness, lightness(SdrMonoMapper.map(RgbPixel::new_y(0.5))));
+    assert_eq!(samples[1].lightness, lightness(RgbPixel::new_y(0.0)));
+}
+
+#[test]
+fn test_sampler_decimates() {
+
+    let pq = frame((0..10).map(|index| RgbPixel::new_y(index as f64 / 10.0)).collect());
+    let sdr = frame(vec![RgbPixel::new_y(0.5); 10]);
+    let mut sampler = Sampler::new(4);
+
+    sampler.add_frames(&pq, &sdr);
+
+    assert_eq!(sampler.stride, 4);
+    assert_eq!(
+        sampler.samples.iter().map(|sample| sample.pq).collect::<Vec<RgbPixel>>(),
+        vec![pq.pixels[0], pq.pixels[4], pq.pixels[8]],
+    );
+
+    sampler.add_frames(&pq, &sdr);
+
+    assert_eq!(sampler.stride, 8);
+    assert_eq!(
+        sampler.samples.iter().map(|sample| sample.pq).collect::<Vec<RgbPixel>>(),
+        vec![pq.pixels[0], pq.pixels[8], pq.pixels[6]],
+    );
+}
+
+#[test]
+fn test_rms_error_threads() {
+
+    let pq = pq_frame();
+    let samples = frame_samples(&pq, &sdr_frame(&pq, 1.0));
+    let single = rms_error(&samples, 1.2, 1_000.0, ToneMapMethod::MaxRgb, 1);
+
+    assert!(rms_error(&samples, 1.0, 1_000.0, ToneMapMethod::MaxRgb, 1) < 0.000001);
+    assert!(single > 0.001);
+
+    for threads in 2..8 {
+        let multiple = rms_error(&samples, 1.2, 1_000.0, ToneMapMethod::MaxRgb, threads);
+        assert!((single - multiple).abs() < 0.000000001);
+    }
+}
+
+#[test]
+fn test_search_exposure() {
+
+    let pq = pq_frame();
+
+    for expected in [0.6, 1.0, 1.3, 2.5] {
+
+        let samples = frame_samples(&pq, &sdr_frame(&pq, expected));
+        let (exposure, error) = search_exposure(&samples, 1_000.0, ToneMapMethod::MaxRgb, 4);
+
+        assert!((exposure / expected - 1.0).abs() < 0.005, "{} != {}", exposure, expected);
+        assert!(error < 0.001);
+    }
+}
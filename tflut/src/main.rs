@@ -0,0 +1,174 @@
+/*
+ * Copyright 2024 William Swartzendruber
+ *
+ * This Source Code Form is subject to the terms of the Mozilla Public License, v. 2.0. If a
+ * copy of the MPL was not distributed with this file, You can obtain one at
+ * https://mozilla.org/MPL/2.0/.
+ *
+ * SPDX-License-Identifier: MPL-2.0
+ */
+
+use std::{
+    fs::File,
+    io::{stdout, BufWriter, Write},
+};
+use dsp::{
+    lut::Lut1d,
+    tf::{hlg_ioetf, hlg_oetf, pq_eotf, pq_ieotf, sdr_e_to_o, sdr_o_to_e},
+};
+use clap::{
+    app_from_crate, crate_authors, crate_description, crate_name, crate_version, Arg, Error,
+    ErrorKind,
+};
+
+fn main() {
+
+    let matches = app_from_crate!()
+        .arg(Arg::with_name("title")
+            .long("title")
+            .short("t")
+            .value_name("STRING")
+            .help("Title of the LUT")
+            .takes_value(true)
+            .required(false)
+            .validator(|value| {
+                if value.contains("\"") {
+                    return Err("Must not contain a double quote mark".to_string())
+                }
+                if value.len() > 242 {
+                    return Err("Must not have a length greater than 242 bytes".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("size")
+            .long("size")
+            .short("s")
+            .value_name("COUNT")
+            .help("The number of entries in the 1D LUT")
+            .takes_value(true)
+            .required(false)
+            .default_value("4096")
+            .validator(|value| {
+                let size = value.parse::<usize>();
+                if size.is_err() {
+                    return Err("Must be an unsigned integer value".to_string())
+                }
+                let size_value = size.unwrap();
+                if !(2..=65_536).contains(&size_value) {
+                    return Err("Must be between 2 and 65,536".to_string())
+                }
+                Ok(())
+            })
+        )
+        .arg(Arg::with_name("domain-min")
+            .long("domain-min")
+            .value_name("VALUE")
+            .help("Input value of the first LUT entry")
+            .takes_value(true)
+            .required(false)
+            .default_value("0")
+            .validator(|value| {
+                match value.parse::<f64>() {
+                    Ok(value) if value.is_finite() => Ok(()),
+                    _ => Err("Must be a finite floating point value".to_string()),
+                }
+            })
+        )
+        .arg(Arg::with_name("domain-max")
+            .long("domain-max")
+            .value_name("VALUE")
+            .help("Input value of the last LUT entry")
+            .takes_value(true)
+            .required(false)
+            .default_value("1")
+            .validator(|value| {
+                match value.parse::<f64>() {
+                    Ok(value) if value.is_finite() => Ok(()),
+                    _ => Err("Must be a finite floating point value".to_string()),
+                }
+            })
+        )
+        .arg(Arg::with_name("format")
+            .long("format")
+            .short("f")
+            .help("File format of the generated LUT")
+            .takes_value(true)
+            .required(false)
+            .possible_values(&["cube", "spi1d"])
+            .default_value("cube")
+        )
+        .arg(Arg::with_name("function")
+            .index(1)
+            .value_name("FUNCTION")
+            .help("Transfer function to sample")
+            .required(true)
+            .possible_values(&[
+                "pq-eotf", "pq-ieotf", "hlg-oetf", "hlg-ioetf", "sdr-eotf", "sdr-ieotf",
+            ])
+        )
+        .arg(Arg::with_name("output")
+            .index(2)
+            .value_name("OUTPUT-FILE")
+            .help("Output LUT file; use - for STDOUT")
+            .required(true)
+        )
+        .after_help(format!("This utility samples one transfer function into a 1D LUT. PQ \
+            display light is normalized such that 1.0 represents 10,000 nits, HLG scene light \
+            such that 1.0 represents the nominal peak, and SDR uses the BT.1886 2.4 power \
+            curve. The domain can be narrowed or widened to concentrate entries where they are \
+            needed, such as using --domain-max 0.1 to sample pq-ieotf up to 1,000 nits. Inputs \
+            below zero are evaluated as zero.\n\n\
+            Copyright © 2024 William Swartzendruber\n\
+            Licensed under the Mozilla Public License 2.0\n\
+            <{}>", env!("CARGO_PKG_REPOSITORY")).as_str())
+        .get_matches();
+    let title = matches.value_of("title");
+    let size = matches.value_of("size").unwrap().parse::<usize>().unwrap();
+    let domain_min = matches.value_of("domain-min").unwrap().parse::<f64>().unwrap();
+    let domain_max = matches.value_of("domain-max").unwrap().parse::<f64>().unwrap();
+    let format = matches.value_of("format").unwrap();
+    let function_name = matches.value_of("function").unwrap();
+    let function: fn(f64) -> f64 = match function_name {
+        "pq-eotf" => pq_eotf,
+        "pq-ieotf" => pq_ieotf,
+        "hlg-oetf" => hlg_oetf,
+        "hlg-ioetf" => hlg_ioetf,
+        "sdr-eotf" => sdr_e_to_o,
+        "sdr-ieotf" => sdr_o_to_e,
+        _ => unreachable!("FUNCTION select is irrational"),
+    };
+
+    if domain_min >= domain_max {
+        Error::with_description(
+            "--domain-min must be less than --domain-max",
+            ErrorKind::InvalidValue,
+        ).exit()
+    }
+
+    let output_value = matches.value_of("output").unwrap();
+    let (mut stdout_write, mut file_write);
+    let mut output = BufWriter::<&mut dyn Write>::new(
+        if output_value == "-" {
+            stdout_write = stdout();
+            &mut stdout_write
+        } else {
+            file_write = File::create(output_value)
+                .expect("Could not open output file for writing.");
+            &mut file_write
+        }
+    );
+
+    let mut lut = Lut1d::from_fn_with_domain(size, domain_min, domain_max, |x| {
+        function(x.max(0.0))
+    });
+
+    lut.title = title.map(String::from);
+    lut.comments.push(format!("Generated by TFLUT {}", env!("CARGO_PKG_VERSION")));
+    lut.comments.push(format!("function: {}", function_name));
+    match format {
+        "cube" => lut.write_cube(&mut output),
+        "spi1d" => lut.write_spi1d(&mut output),
+        _ => unreachable!("--format select is irrational"),
+    }.expect("Could not write to output file.");
+}